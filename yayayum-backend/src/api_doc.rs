@@ -1,13 +1,65 @@
-use utoipa::OpenApi;
-use crate::models::{CreateUser, User, CreateDish, Dish, DietaryRestriction, DishCategory, CreateRating, Rating};
+use utoipa::{openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme}, Modify, OpenApi};
+use crate::models::{CreateUser, UpdateUser, User, AccessToken, CreateDish, Dish, DietaryRestriction, DishCategory, DishSuitability, CreateIngredient, Ingredient, IngredientSource, Allergen, LunchSession, CreateLunchSession, LunchParticipant, JoinLunchSession, RatingPrompt, Order, CreateOrder, OrderItem, CreateOrderItem, OrderSummary, OrderSummaryLine, OrderShare, Debt, Payment, CreatePayment, SettleDebt, Balance, Restaurant, CreateRestaurant, OpeningHours, Weekday, DishStats, LeaderboardEntry, SubScoreAverages, TopDishes, RankedDish, CategoryRanking, ConfidenceInterval, RankingMethod, Recommendation, ControversialDish, DishOpinion, Workspace, CreateWorkspace, ModifyWorkspace, NewWorkspace, WorkspaceInvite, WorkspaceMembership, CreateRating, Rating, SubScores, RatingScale, Meal, CreateMeal, MealRating, Comment, CreateComment, ModifyComment, Reaction, CreateReaction, ReactionCount, RatingRevision, Event, EventKind, FeedPage, Achievement, AchievementKind, Webhook, CreateWebhook, WebhookDelivery, DeliveryStatus, SlashCommand, ChatResponse, ResponseType, ChatLinkCode, UserDigest, TeamDigest, DigestEmail, DigestRun, Job, JobKind, ModifyJob, JobRun, JobRunStatus, Notification, ReminderChannel, Holiday, CreateHoliday};
+use crate::models::digest::{DigestRating, DigestDish, WeeklyDish, WeeklyRater, UnlockedAchievement};
 use crate::routes::users::__path_create_user;
 use crate::routes::users::__path_get_users;
+use crate::routes::users::__path_get_current_user;
 use crate::routes::users::__path_modify_user;
 use crate::routes::users::__path_remove_user;
+use crate::routes::users::__path_get_followers;
+use crate::routes::users::__path_get_following;
+use crate::routes::users::__path_follow_user;
+use crate::routes::users::__path_unfollow_user;
+use crate::routes::users::__path_create_user_token;
+use crate::routes::users::__path_create_admin_token;
 use crate::routes::dishes::__path_create_dish;
 use crate::routes::dishes::__path_get_dishes;
 use crate::routes::dishes::__path_modify_dish;
 use crate::routes::dishes::__path_remove_dish;
+use crate::routes::ingredients::__path_create_ingredient;
+use crate::routes::ingredients::__path_get_ingredients;
+use crate::routes::ingredients::__path_modify_ingredient;
+use crate::routes::ingredients::__path_remove_ingredient;
+use crate::routes::lunch::__path_create_lunch_session;
+use crate::routes::lunch::__path_get_todays_lunch_session;
+use crate::routes::lunch::__path_get_lunch_session;
+use crate::routes::lunch::__path_lock_lunch_session;
+use crate::routes::lunch::__path_join_lunch_session;
+use crate::routes::lunch::__path_leave_lunch_session;
+use crate::routes::lunch::__path_get_rating_prompts;
+use crate::routes::orders::__path_create_order;
+use crate::routes::orders::__path_get_orders;
+use crate::routes::orders::__path_get_order;
+use crate::routes::orders::__path_remove_order;
+use crate::routes::orders::__path_add_order_item;
+use crate::routes::orders::__path_modify_order_item;
+use crate::routes::orders::__path_remove_order_item;
+use crate::routes::orders::__path_get_order_summary;
+use crate::routes::orders::__path_get_order_split;
+use crate::routes::ledger::__path_get_ledger;
+use crate::routes::ledger::__path_get_balances;
+use crate::routes::ledger::__path_get_settle_up;
+use crate::routes::ledger::__path_settle_debt;
+use crate::routes::ledger::__path_create_payment;
+use crate::routes::ledger::__path_get_payments;
+use crate::routes::ledger::__path_remove_payment;
+use crate::routes::restaurants::__path_create_restaurant;
+use crate::routes::restaurants::__path_get_restaurants;
+use crate::routes::restaurants::__path_get_restaurant;
+use crate::routes::restaurants::__path_modify_restaurant;
+use crate::routes::restaurants::__path_remove_restaurant;
+use crate::routes::restaurants::__path_get_restaurant_dishes;
+use crate::routes::stats::__path_get_dish_stats;
+use crate::routes::stats::__path_get_leaderboard;
+use crate::routes::stats::__path_get_top_dishes;
+use crate::routes::stats::__path_get_recommendations;
+use crate::routes::stats::__path_get_controversial_dishes;
+use crate::routes::workspaces::__path_create_workspace;
+use crate::routes::workspaces::__path_get_current_workspace;
+use crate::routes::workspaces::__path_modify_current_workspace;
+use crate::routes::workspaces::__path_renew_invite_code;
+use crate::routes::workspaces::__path_get_invite;
+use crate::routes::workspaces::__path_join_workspace;
 use crate::routes::ratings::__path_create_rating;
 use crate::routes::ratings::__path_get_ratings;
 use crate::routes::ratings::__path_get_rating;
@@ -15,33 +67,201 @@ use crate::routes::ratings::__path_get_ratings_by_dish;
 use crate::routes::ratings::__path_get_ratings_by_user;
 use crate::routes::ratings::__path_modify_rating;
 use crate::routes::ratings::__path_remove_rating;
+use crate::routes::ratings::__path_get_rating_history;
+use crate::routes::meals::__path_create_meal;
+use crate::routes::meals::__path_get_meal;
+use crate::routes::meals::__path_add_meal_rating;
+use crate::routes::meals::__path_remove_meal;
+use crate::routes::meals::__path_get_meals_by_user;
+use crate::routes::comments::__path_get_comments;
+use crate::routes::comments::__path_create_comment;
+use crate::routes::comments::__path_modify_comment;
+use crate::routes::comments::__path_remove_comment;
+use crate::routes::reactions::__path_get_reactions;
+use crate::routes::reactions::__path_create_reaction;
+use crate::routes::reactions::__path_remove_reaction;
+use crate::routes::reactions::__path_get_most_reacted;
+use crate::routes::feed::__path_get_feed;
+use crate::routes::feed::__path_stream_feed;
+use crate::routes::webhooks::__path_create_webhook;
+use crate::routes::webhooks::__path_get_webhooks;
+use crate::routes::webhooks::__path_get_webhook;
+use crate::routes::webhooks::__path_modify_webhook;
+use crate::routes::webhooks::__path_remove_webhook;
+use crate::routes::webhooks::__path_get_webhook_deliveries;
+use crate::routes::webhooks::__path_retry_webhook_delivery;
+use crate::routes::chat::{__path_create_chat_link_code, __path_run_chat_command};
+use crate::routes::digests::__path_get_user_digest;
+use crate::routes::digests::__path_get_team_digest;
+use crate::routes::digests::__path_send_weekly_digests;
+use crate::routes::jobs::__path_get_jobs;
+use crate::routes::jobs::__path_modify_job;
+use crate::routes::jobs::__path_run_job_now;
+use crate::routes::jobs::__path_get_job_runs;
+use crate::routes::notifications::__path_get_notifications;
+use crate::routes::notifications::__path_read_notification;
+use crate::routes::reminders::__path_create_holiday;
+use crate::routes::reminders::__path_get_holidays;
+use crate::routes::reminders::__path_remove_holiday;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         create_user,
         get_users,
+        get_current_user,
         modify_user,
         remove_user,
+        get_followers,
+        get_following,
+        follow_user,
+        unfollow_user,
+        create_user_token,
+        create_admin_token,
         create_dish,
         get_dishes,
         modify_dish,
         remove_dish,
+        create_ingredient,
+        get_ingredients,
+        modify_ingredient,
+        remove_ingredient,
+        create_lunch_session,
+        get_todays_lunch_session,
+        get_lunch_session,
+        lock_lunch_session,
+        join_lunch_session,
+        leave_lunch_session,
+        get_rating_prompts,
+        create_order,
+        get_orders,
+        get_order,
+        remove_order,
+        add_order_item,
+        modify_order_item,
+        remove_order_item,
+        get_order_summary,
+        get_order_split,
+        get_ledger,
+        get_balances,
+        get_settle_up,
+        settle_debt,
+        create_payment,
+        get_payments,
+        remove_payment,
+        create_restaurant,
+        get_restaurants,
+        get_restaurant,
+        modify_restaurant,
+        remove_restaurant,
+        get_restaurant_dishes,
+        get_dish_stats,
+        get_leaderboard,
+        get_top_dishes,
+        get_recommendations,
+        get_controversial_dishes,
+        create_workspace,
+        get_current_workspace,
+        modify_current_workspace,
+        renew_invite_code,
+        get_invite,
+        join_workspace,
         create_rating,
         get_ratings,
         get_rating,
         get_ratings_by_dish,
         get_ratings_by_user,
         modify_rating,
-        remove_rating
+        remove_rating,
+        get_rating_history,
+        create_meal,
+        get_meal,
+        add_meal_rating,
+        remove_meal,
+        get_meals_by_user,
+        get_comments,
+        create_comment,
+        modify_comment,
+        remove_comment,
+        get_reactions,
+        create_reaction,
+        remove_reaction,
+        get_most_reacted,
+        get_feed,
+        stream_feed,
+        create_webhook,
+        get_webhooks,
+        get_webhook,
+        modify_webhook,
+        remove_webhook,
+        get_webhook_deliveries,
+        retry_webhook_delivery,
+        create_chat_link_code,
+        run_chat_command,
+        get_user_digest,
+        get_team_digest,
+        send_weekly_digests,
+        get_jobs,
+        modify_job,
+        run_job_now,
+        get_job_runs,
+        get_notifications,
+        read_notification,
+        create_holiday,
+        get_holidays,
+        remove_holiday
     ),
     components(
-        schemas(CreateUser, User, CreateDish, Dish, DietaryRestriction, DishCategory, CreateRating, Rating)
+        schemas(CreateUser, UpdateUser, User, AccessToken, CreateDish, Dish, DietaryRestriction, DishCategory, DishSuitability, CreateIngredient, Ingredient, IngredientSource, Allergen, LunchSession, CreateLunchSession, LunchParticipant, JoinLunchSession, RatingPrompt, Order, CreateOrder, OrderItem, CreateOrderItem, OrderSummary, OrderSummaryLine, OrderShare, Debt, Payment, CreatePayment, SettleDebt, Balance, Restaurant, CreateRestaurant, OpeningHours, Weekday, DishStats, LeaderboardEntry, SubScoreAverages, TopDishes, RankedDish, CategoryRanking, ConfidenceInterval, RankingMethod, Recommendation, ControversialDish, DishOpinion, Workspace, CreateWorkspace, ModifyWorkspace, NewWorkspace, WorkspaceInvite, WorkspaceMembership, CreateRating, Rating, SubScores, RatingScale, Meal, CreateMeal, MealRating, Comment, CreateComment, ModifyComment, Reaction, CreateReaction, ReactionCount, RatingRevision, Event, EventKind, FeedPage, Achievement, AchievementKind, Webhook, CreateWebhook, WebhookDelivery, DeliveryStatus, SlashCommand, ChatResponse, ResponseType, ChatLinkCode, UserDigest, TeamDigest, DigestEmail, DigestRun, DigestRating, DigestDish, WeeklyDish, WeeklyRater, UnlockedAchievement, Job, JobKind, ModifyJob, JobRun, JobRunStatus, Notification, ReminderChannel, Holiday, CreateHoliday)
     ),
     tags(
         (name = "users", description = "User management endpoints"),
         (name = "dishes", description = "Dish management endpoints"),
-        (name = "ratings", description = "Rating management endpoints")
-    )
+        (name = "ingredients", description = "Ingredient management endpoints"),
+        (name = "ratings", description = "Rating management endpoints"),
+        (name = "meals", description = "Meals rated dish by dish"),
+        (name = "lunch", description = "Daily lunch poll endpoints"),
+        (name = "orders", description = "Group order endpoints"),
+        (name = "ledger", description = "Payments and who owes whom"),
+        (name = "restaurants", description = "Restaurant management endpoints"),
+        (name = "stats", description = "Dish statistics and leaderboards"),
+        (name = "workspaces", description = "Workspaces and invites"),
+        (name = "feed", description = "Activity feed"),
+        (name = "webhooks", description = "Outgoing webhooks and their delivery log"),
+        (name = "chat", description = "Slack and Mattermost slash commands"),
+        (name = "digests", description = "Weekly digest emails"),
+        (name = "jobs", description = "Scheduled background jobs and their run history"),
+        (name = "notifications", description = "In-app notifications"),
+        (name = "reminders", description = "Rating reminders and the holidays they skip")
+    ),
+    modifiers(&SecuritySchemes),
+    security(("token" = []))
 )]
-pub struct ApiDoc;
\ No newline at end of file
+pub struct ApiDoc;
+
+/// Documents the session token most requests need, and the operator's admin
+/// token.
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "token",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .description(Some("Session token from joining a workspace, or an `access_token` query parameter"))
+                        .build(),
+                ),
+            );
+            components.add_security_scheme(
+                "admin",
+                SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                    "X-Admin-Token",
+                    "The server's ADMIN_TOKEN secret",
+                ))),
+            );
+        }
+    }
+}
\ No newline at end of file
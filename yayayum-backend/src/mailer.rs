@@ -0,0 +1,139 @@
+use axum::http::StatusCode;
+use lettre::message::{Mailbox, MultiPart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use shuttle_runtime::SecretStore;
+
+const DEFAULT_FROM: &str = "YayaYum <noreply@yayayum.se>";
+
+/// Sends email through the SMTP server set up in Secrets.toml:
+///
+/// - `SMTP_HOST`, leave it out to turn email off
+/// - `SMTP_PORT`, defaults to the usual port for `SMTP_TLS`
+/// - `SMTP_TLS`, `starttls` (default), `tls`, or `none` for a local sink
+/// - `SMTP_USERNAME` and `SMTP_PASSWORD`, if the server wants a login
+/// - `SMTP_FROM`, like `YayaYum <noreply@example.com>`
+#[derive(Clone)]
+pub struct Mailer {
+    transport: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    from: Mailbox,
+}
+
+impl Mailer {
+    pub fn from_secrets(secrets: &SecretStore) -> Mailer {
+        let from = secrets.get("SMTP_FROM").unwrap_or_else(|| DEFAULT_FROM.to_string())
+            .parse()
+            .expect("Invalid SMTP_FROM");
+
+        let transport = secrets.get("SMTP_HOST").map(|host| {
+            let mut builder = match secrets.get("SMTP_TLS").as_deref() {
+                Some("none") => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&host),
+                Some("tls") => AsyncSmtpTransport::<Tokio1Executor>::relay(&host).expect("Invalid SMTP_HOST"),
+                _ => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&host).expect("Invalid SMTP_HOST"),
+            };
+            if let Some(port) = secrets.get("SMTP_PORT") {
+                builder = builder.port(port.parse().expect("Invalid SMTP_PORT"));
+            }
+            if let (Some(username), Some(password)) = (secrets.get("SMTP_USERNAME"), secrets.get("SMTP_PASSWORD")) {
+                builder = builder.credentials(Credentials::new(username, password));
+            }
+            builder.build()
+        });
+
+        Mailer { transport, from }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.transport.is_some()
+    }
+
+    /// Sends a plain text email with an HTML alternative.
+    pub async fn send(&self, to: &str, subject: &str, text: String, html: String) -> Result<(), StatusCode> {
+        let transport = self.transport.as_ref().ok_or(StatusCode::SERVICE_UNAVAILABLE)?;
+        let to: Mailbox = to.parse().map_err(|_| StatusCode::BAD_REQUEST)?;
+
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(subject)
+            .multipart(MultiPart::alternative_plain_html(text, html))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        transport.send(message).await.map_err(|_| StatusCode::BAD_GATEWAY)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+    use shuttle_runtime::SecretStore;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+    use super::Mailer;
+
+    /// Accepts one SMTP session and returns the message it got.
+    async fn smtp_sink(listener: TcpListener) -> String {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+        let mut message = String::new();
+        let mut in_data = false;
+        while let Some(line) = lines.next_line().await.unwrap() {
+            if in_data {
+                if line == "." {
+                    in_data = false;
+                    writer.write_all(b"250 Queued\r\n").await.unwrap();
+                } else {
+                    message.push_str(&line);
+                    message.push('\n');
+                }
+                continue;
+            }
+            let reply: &[u8] = match line.split_whitespace().next().unwrap_or_default().to_uppercase().as_str() {
+                "EHLO" | "HELO" => b"250 sink\r\n",
+                "DATA" => {
+                    in_data = true;
+                    b"354 Go ahead\r\n"
+                }
+                "QUIT" => {
+                    writer.write_all(b"221 Bye\r\n").await.unwrap();
+                    break;
+                }
+                _ => b"250 OK\r\n",
+            };
+            writer.write_all(reply).await.unwrap();
+        }
+        message
+    }
+
+    #[tokio::test]
+    async fn sends_through_a_plain_smtp_server() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port().to_string();
+        let sink = tokio::spawn(smtp_sink(listener));
+
+        let mailer = Mailer::from_secrets(&SecretStore::new(BTreeMap::from([
+            ("SMTP_HOST".to_string(), "127.0.0.1".to_string().into()),
+            ("SMTP_PORT".to_string(), port.into()),
+            ("SMTP_TLS".to_string(), "none".to_string().into()),
+        ])));
+        assert!(mailer.is_enabled());
+
+        mailer.send("alice@example.com", "Your week", "Plain text".to_string(), "<p>HTML</p>".to_string()).await.unwrap();
+
+        let message = sink.await.unwrap();
+        assert!(message.contains("To: alice@example.com"), "{message}");
+        assert!(message.contains("Subject: Your week"), "{message}");
+        assert!(message.contains("Plain text"), "{message}");
+        assert!(message.contains("<p>HTML</p>"), "{message}");
+    }
+
+    #[test]
+    fn is_off_without_a_host() {
+        assert!(!Mailer::from_secrets(&SecretStore::new(BTreeMap::new())).is_enabled());
+    }
+}
@@ -1,9 +1,19 @@
 mod api_doc;
+mod mailer;
 mod models;
 mod routes;
+mod workers;
+#[cfg(test)]
+mod tests;
 
 use api_doc::ApiDoc;
-use axum::{Router, http::Method};
+use mailer::Mailer;
+use models::{DishCategory, JobKind, SubScores};
+use models::job::next_run;
+use routes::feed::FeedBroadcast;
+use routes::workspaces::AdminToken;
+use workers::webhooks::WebhookTargets;
+use axum::{Extension, Router, http::Method};
 use sqlx::PgPool;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::{ServeDir, ServeFile};
@@ -12,19 +22,151 @@ use utoipa_swagger_ui::SwaggerUi;
 
 #[shuttle_runtime::main]
 async fn main(
-    #[shuttle_shared_db::Postgres] pool: PgPool
+    #[shuttle_shared_db::Postgres] pool: PgPool,
+    #[shuttle_runtime::Secrets] secrets: shuttle_runtime::SecretStore,
 ) -> shuttle_axum::ShuttleAxum {
+    create_schema(&pool).await;
+
+    let feed = FeedBroadcast::new();
+    tokio::spawn(workers::feed::relay_events(pool.clone(), feed.clone()));
+    let webhook_targets = WebhookTargets::from_secrets(&secrets);
+    tokio::spawn(workers::webhooks::deliver_webhooks(pool.clone(), webhook_targets));
+    let mailer = Mailer::from_secrets(&secrets);
+    tokio::spawn(workers::scheduler::run_scheduler(pool.clone(), mailer.clone()));
+
+    Ok(app(pool, mailer, AdminToken(secrets.get("ADMIN_TOKEN")), webhook_targets, feed).into())
+}
+
+async fn create_schema(pool: &PgPool) {
     // Create tables manually
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS workspaces (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            invite_code TEXT NOT NULL UNIQUE,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create workspaces table");
+
+    sqlx::query(
+        "INSERT INTO workspaces (name, invite_code)
+         SELECT 'Default', replace(gen_random_uuid()::text, '-', '') WHERE NOT EXISTS (SELECT 1 FROM workspaces)"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create default workspace");
+
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS rating_max INTEGER NOT NULL DEFAULT 5")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.rating_max");
+
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS rating_half_steps BOOLEAN NOT NULL DEFAULT TRUE")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.rating_half_steps");
+
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS rating_grace_days INTEGER NOT NULL DEFAULT 3 CHECK (rating_grace_days >= 0)")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.rating_grace_days");
+
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS one_meal_per_day BOOLEAN NOT NULL DEFAULT TRUE")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.one_meal_per_day");
+
+    // Slack signing secret or Mattermost token for the chat slash command
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS chat_signing_secret TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.chat_signing_secret");
+
+    // Where the weekly team summary goes, e.g. a mailing list
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS digest_email TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.digest_email");
+
+    // When to remind people who haven't rated, NULL for no reminders
+    sqlx::query("ALTER TABLE workspaces ADD COLUMN IF NOT EXISTS reminder_time TIME")
+        .execute(pool)
+        .await
+        .expect("Failed to add workspaces.reminder_time");
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS users (
             id SERIAL PRIMARY KEY,
             username TEXT NOT NULL
         )"
     )
-    .execute(&pool)
+    .execute(pool)
     .await
     .expect("Failed to create users table");
 
+    // Dietary preferences, stored as JSON strings like the dish columns
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS dietary_restrictions TEXT NOT NULL DEFAULT '[]'")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.dietary_restrictions");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS disliked_ingredients TEXT NOT NULL DEFAULT '[]'")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.disliked_ingredients");
+
+    // The user's ID in the team chat, for slash commands
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS chat_user_id TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.chat_user_id");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS email TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.email");
+
+    // Weekly digests are opt-in
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS weekly_digest BOOLEAN NOT NULL DEFAULT FALSE")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.weekly_digest");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS rating_reminders BOOLEAN NOT NULL DEFAULT TRUE")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.rating_reminders");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS reminder_channel TEXT NOT NULL DEFAULT '\"InApp\"'")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.reminder_channel");
+
+    // Only a hash of the session token is kept, see POST /users/{id}/token
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS token_hash TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.token_hash");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS is_admin BOOLEAN NOT NULL DEFAULT FALSE")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.is_admin");
+
+    // One-time code for linking a chat account, see POST /chat/link-code
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS chat_link_code_hash TEXT")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.chat_link_code_hash");
+
+    sqlx::query("ALTER TABLE users ADD COLUMN IF NOT EXISTS chat_link_expires_at TIMESTAMP")
+        .execute(pool)
+        .await
+        .expect("Failed to add users.chat_link_expires_at");
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS dishes (
             id SERIAL PRIMARY KEY,
@@ -36,57 +178,657 @@ async fn main(
             category TEXT NOT NULL
         )"
     )
-    .execute(&pool)
+    .execute(pool)
     .await
     .expect("Failed to create dishes table");
 
+    sqlx::query("ALTER TABLE dishes ADD COLUMN IF NOT EXISTS allergens TEXT NOT NULL DEFAULT '[]'")
+        .execute(pool)
+        .await
+        .expect("Failed to add dishes.allergens");
+
+    sqlx::query("ALTER TABLE dishes ADD COLUMN IF NOT EXISTS spiciness INTEGER NOT NULL DEFAULT 0 CHECK (spiciness >= 0 AND spiciness <= 5)")
+        .execute(pool)
+        .await
+        .expect("Failed to add dishes.spiciness");
+
+    // The old DietaryRestriction::None variant is gone, strip it from stored lists
+    for table in ["dishes", "users"] {
+        sqlx::query(&format!(
+            "UPDATE {table} SET dietary_restrictions = COALESCE(
+                (SELECT json_agg(r)::text FROM json_array_elements_text(dietary_restrictions::json) r WHERE r <> 'None'),
+                '[]')
+             WHERE dietary_restrictions LIKE '%\"None\"%'"
+        ))
+        .execute(pool)
+        .await
+        .expect("Failed to migrate dietary restrictions");
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS ingredients (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            source TEXT NOT NULL,
+            allergens TEXT NOT NULL DEFAULT '[]'
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create ingredients table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS dish_ingredients (
+            dish_id INTEGER NOT NULL REFERENCES dishes(id) ON DELETE CASCADE,
+            ingredient_id INTEGER NOT NULL REFERENCES ingredients(id) ON DELETE RESTRICT,
+            PRIMARY KEY (dish_id, ingredient_id)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create dish_ingredients table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS restaurants (
+            id SERIAL PRIMARY KEY,
+            name TEXT NOT NULL,
+            address TEXT NOT NULL DEFAULT '',
+            opening_hours TEXT NOT NULL DEFAULT '[]',
+            categories TEXT NOT NULL DEFAULT '[]'
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create restaurants table");
+
+    sqlx::query("ALTER TABLE dishes ADD COLUMN IF NOT EXISTS restaurant_id INTEGER REFERENCES restaurants(id) ON DELETE RESTRICT")
+        .execute(pool)
+        .await
+        .expect("Failed to add dishes.restaurant_id");
+
     sqlx::query(
         "CREATE TABLE IF NOT EXISTS ratings (
             id SERIAL PRIMARY KEY,
             dish_id INTEGER NOT NULL REFERENCES dishes(id) ON DELETE CASCADE,
-            rating INTEGER NOT NULL CHECK (rating >= 1 AND rating <= 5),
+            rating DOUBLE PRECISION NOT NULL,
             user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
             description TEXT,
             photo TEXT,
             date TIMESTAMP NOT NULL DEFAULT NOW()
         )"
     )
-    .execute(&pool)
+    .execute(pool)
     .await
     .expect("Failed to create ratings table");
 
+    // Optional sub-scores next to the overall rating
+    for column in SubScores::COLUMNS {
+        sqlx::query(&format!("ALTER TABLE ratings ADD COLUMN IF NOT EXISTS {column} DOUBLE PRECISION"))
+            .execute(pool)
+            .await
+            .expect("Failed to add rating sub-score");
+    }
+
+    // Scores allow half steps, and the range depends on the workspace's scale
+    // so it is checked by RatingScale instead of the table
+    for column in std::iter::once("rating").chain(SubScores::COLUMNS) {
+        sqlx::query(&format!("ALTER TABLE ratings DROP CONSTRAINT IF EXISTS ratings_{column}_check"))
+            .execute(pool)
+            .await
+            .expect("Failed to drop rating range check");
+
+        sqlx::query(&format!("ALTER TABLE ratings ALTER COLUMN {column} TYPE DOUBLE PRECISION"))
+            .execute(pool)
+            .await
+            .expect("Failed to allow fractional ratings");
+    }
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS lunch_sessions (
+            id SERIAL PRIMARY KEY,
+            date DATE NOT NULL DEFAULT CURRENT_DATE,
+            title TEXT NOT NULL,
+            created_by INTEGER REFERENCES users(id) ON DELETE SET NULL,
+            cutoff_at TIMESTAMP NOT NULL,
+            locked BOOLEAN NOT NULL DEFAULT FALSE,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create lunch_sessions table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS lunch_participants (
+            session_id INTEGER NOT NULL REFERENCES lunch_sessions(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            dish_id INTEGER REFERENCES dishes(id) ON DELETE SET NULL,
+            joined_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (session_id, user_id)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create lunch_participants table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS orders (
+            id SERIAL PRIMARY KEY,
+            paid_by INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            session_id INTEGER REFERENCES lunch_sessions(id) ON DELETE SET NULL,
+            delivery_fee_kr INTEGER NOT NULL DEFAULT 0 CHECK (delivery_fee_kr >= 0),
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create orders table");
+
+    // Dish nr, name and price are copied onto the item when it's ordered
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS order_items (
+            id SERIAL PRIMARY KEY,
+            order_id INTEGER NOT NULL REFERENCES orders(id) ON DELETE CASCADE,
+            user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+            dish_id INTEGER REFERENCES dishes(id) ON DELETE SET NULL,
+            dish_nr INTEGER NOT NULL,
+            dish_name TEXT NOT NULL,
+            unit_price_kr INTEGER NOT NULL,
+            quantity INTEGER NOT NULL DEFAULT 1 CHECK (quantity > 0),
+            notes TEXT
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create order_items table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS payments (
+            id SERIAL PRIMARY KEY,
+            from_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            to_user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            amount_kr INTEGER NOT NULL CHECK (amount_kr > 0),
+            order_id INTEGER REFERENCES orders(id) ON DELETE SET NULL,
+            note TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            CHECK (from_user_id <> to_user_id)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create payments table");
+
+    // Everything created before workspaces belongs to the default workspace
+    for table in ["users", "dishes", "ratings", "ingredients", "restaurants", "lunch_sessions", "orders", "payments"] {
+        sqlx::query(&format!(
+            "ALTER TABLE {table} ADD COLUMN IF NOT EXISTS workspace_id INTEGER REFERENCES workspaces(id) ON DELETE CASCADE"
+        ))
+        .execute(pool)
+        .await
+        .expect("Failed to add workspace_id");
+
+        sqlx::query(&format!(
+            "UPDATE {table} SET workspace_id = (SELECT MIN(id) FROM workspaces) WHERE workspace_id IS NULL"
+        ))
+        .execute(pool)
+        .await
+        .expect("Failed to assign rows to the default workspace");
+
+        sqlx::query(&format!("ALTER TABLE {table} ALTER COLUMN workspace_id SET NOT NULL"))
+            .execute(pool)
+            .await
+            .expect("Failed to make workspace_id required");
+    }
+
+    // Everything before multi-restaurant support was from Yaya
+    let yaya_categories = serde_json::to_string(&DishCategory::ALL)
+        .expect("Failed to serialize Yaya categories");
+    sqlx::query(
+        "INSERT INTO restaurants (workspace_id, name, categories)
+         SELECT MIN(id), 'Yaya', $1 FROM workspaces HAVING NOT EXISTS (SELECT 1 FROM restaurants)"
+    )
+    .bind(&yaya_categories)
+    .execute(pool)
+    .await
+    .expect("Failed to create Yaya restaurant");
+
+    sqlx::query(
+        "UPDATE dishes d SET restaurant_id = (SELECT MIN(r.id) FROM restaurants r WHERE r.workspace_id = d.workspace_id)
+         WHERE restaurant_id IS NULL"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to assign dishes to Yaya");
+
+    sqlx::query("ALTER TABLE dishes ALTER COLUMN restaurant_id SET NOT NULL")
+        .execute(pool)
+        .await
+        .expect("Failed to make dishes.restaurant_id required");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS meals (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            date DATE NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create meals table");
+
+    sqlx::query("ALTER TABLE ratings ADD COLUMN IF NOT EXISTS meal_id INTEGER REFERENCES meals(id) ON DELETE CASCADE")
+        .execute(pool)
+        .await
+        .expect("Failed to add ratings.meal_id");
+
+    // Ratings from before meals each become a meal of their own day
+    sqlx::query(
+        "INSERT INTO meals (workspace_id, user_id, date)
+         SELECT DISTINCT workspace_id, user_id, DATE(date) FROM ratings WHERE meal_id IS NULL"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create meals for existing ratings");
+
+    sqlx::query(
+        "UPDATE ratings r SET meal_id = m.id FROM meals m
+         WHERE r.meal_id IS NULL AND m.user_id = r.user_id AND m.date = DATE(r.date)"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to assign ratings to meals");
+
+    sqlx::query("ALTER TABLE ratings ALTER COLUMN meal_id SET NOT NULL")
+        .execute(pool)
+        .await
+        .expect("Failed to make ratings.meal_id required");
+
+    sqlx::query("ALTER TABLE ratings ADD COLUMN IF NOT EXISTS updated_at TIMESTAMP")
+        .execute(pool)
+        .await
+        .expect("Failed to add ratings.updated_at");
+
+    // Every edit keeps the rating as it was before
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS rating_revisions (
+            id SERIAL PRIMARY KEY,
+            rating_id INTEGER NOT NULL REFERENCES ratings(id) ON DELETE CASCADE,
+            dish_id INTEGER NOT NULL REFERENCES dishes(id) ON DELETE CASCADE,
+            rating DOUBLE PRECISION NOT NULL,
+            description TEXT,
+            photo TEXT,
+            taste DOUBLE PRECISION,
+            portion DOUBLE PRECISION,
+            spiciness_accuracy DOUBLE PRECISION,
+            temperature DOUBLE PRECISION,
+            value DOUBLE PRECISION,
+            wait_time DOUBLE PRECISION,
+            replaced_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create rating_revisions table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS rating_comments (
+            id SERIAL PRIMARY KEY,
+            rating_id INTEGER NOT NULL REFERENCES ratings(id) ON DELETE CASCADE,
+            parent_id INTEGER REFERENCES rating_comments(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            body TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMP
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create rating_comments table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS user_follows (
+            follower_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            followee_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (follower_id, followee_id),
+            CHECK (follower_id <> followee_id)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create user_follows table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS user_achievements (
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            achievement TEXT NOT NULL,
+            unlocked_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (user_id, achievement)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create user_achievements table");
+
+    // The activity feed, kind and data are stored as JSON strings
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS events (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            kind TEXT NOT NULL,
+            user_id INTEGER REFERENCES users(id) ON DELETE CASCADE,
+            subject_id INTEGER,
+            data TEXT NOT NULL DEFAULT '{}',
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create events table");
+
+    // Event types are stored as a JSON list
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS webhooks (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            url TEXT NOT NULL,
+            event_types TEXT NOT NULL,
+            secret TEXT NOT NULL,
+            active BOOLEAN NOT NULL DEFAULT TRUE,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create webhooks table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS webhook_deliveries (
+            id SERIAL PRIMARY KEY,
+            webhook_id INTEGER NOT NULL REFERENCES webhooks(id) ON DELETE CASCADE,
+            event_id INTEGER NOT NULL REFERENCES events(id) ON DELETE CASCADE,
+            status TEXT NOT NULL DEFAULT '\"Pending\"',
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            last_status_code INTEGER,
+            last_error TEXT,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            delivered_at TIMESTAMP,
+            UNIQUE (webhook_id, event_id)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create webhook_deliveries table");
+
+    // One row per digest sent, so running the digests twice doesn't send them twice
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS sent_digests (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            user_id INTEGER REFERENCES users(id) ON DELETE CASCADE, -- NULL for the team summary
+            email TEXT NOT NULL,
+            period_end DATE NOT NULL,
+            sent_at TIMESTAMP NOT NULL DEFAULT NOW()
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create sent_digests table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS rating_reactions (
+            rating_id INTEGER NOT NULL REFERENCES ratings(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            emoji TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (rating_id, user_id, emoji)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create rating_reactions table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS notifications (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            message TEXT NOT NULL,
+            created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            read_at TIMESTAMP
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create notifications table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS holidays (
+            id SERIAL PRIMARY KEY,
+            workspace_id INTEGER NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+            date DATE NOT NULL,
+            name TEXT NOT NULL,
+            UNIQUE (workspace_id, date)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create holidays table");
+
+    // One row per reminder, so nobody is reminded twice on the same day
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS sent_reminders (
+            user_id INTEGER NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+            date DATE NOT NULL,
+            channel TEXT NOT NULL,
+            sent_at TIMESTAMP NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (user_id, date)
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create sent_reminders table");
+
+    // Scheduled jobs, one row per JobKind. Times are UTC.
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS jobs (
+            name TEXT PRIMARY KEY,
+            schedule TEXT NOT NULL,
+            enabled BOOLEAN NOT NULL DEFAULT TRUE,
+            next_run_at TIMESTAMP NOT NULL,
+            last_run_at TIMESTAMP,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            locked_until TIMESTAMP -- Set while an instance runs the job
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create jobs table");
+
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS job_runs (
+            id SERIAL PRIMARY KEY,
+            job_name TEXT NOT NULL REFERENCES jobs(name) ON DELETE CASCADE,
+            attempt INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            output TEXT,
+            started_at TIMESTAMP NOT NULL,
+            finished_at TIMESTAMP
+        )"
+    )
+    .execute(pool)
+    .await
+    .expect("Failed to create job_runs table");
+
+    // New jobs start on their default schedule, existing ones keep theirs.
+    // Jobs that no longer exist are dropped along with their history.
+    let job_names = JobKind::ALL.iter()
+        .map(|job| serde_json::to_string(job).expect("Failed to serialize job name"))
+        .collect::<Vec<_>>();
+    sqlx::query("DELETE FROM jobs WHERE name <> ALL($1)")
+        .bind(&job_names)
+        .execute(pool)
+        .await
+        .expect("Failed to remove old jobs");
+    for (job, name) in JobKind::ALL.iter().zip(&job_names) {
+        let next_run_at = next_run(job.default_schedule(), chrono::Utc::now().naive_utc())
+            .expect("Invalid default job schedule");
+        sqlx::query("INSERT INTO jobs (name, schedule, next_run_at) VALUES ($1, $2, $3) ON CONFLICT (name) DO NOTHING")
+            .bind(name)
+            .bind(job.default_schedule())
+            .bind(next_run_at)
+            .execute(pool)
+            .await
+            .expect("Failed to register job");
+    }
+
     // Create indexes
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_dishes_category ON dishes(category)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .ok();
     
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_dishes_restaurant_id ON dishes(restaurant_id)")
+        .execute(pool)
+        .await
+        .ok();
+
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_dishes_nr ON dishes(nr)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .ok();
 
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_ratings_dish_id ON ratings(dish_id)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .ok();
 
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_ratings_user_id ON ratings(user_id)")
-        .execute(&pool)
+        .execute(pool)
         .await
         .ok();
 
     sqlx::query("CREATE INDEX IF NOT EXISTS idx_ratings_rating ON ratings(rating)")
-        .execute(&pool)
+        .execute(pool)
+        .await
+        .ok();
+
+    // Ingredient names are unique within a workspace
+    sqlx::query("DROP INDEX IF EXISTS idx_ingredients_name_unique")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_sent_digests_unique ON sent_digests (workspace_id, COALESCE(user_id, 0), period_end)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_users_workspace_chat_user_unique ON users (workspace_id, chat_user_id)")
+        .execute(pool)
         .await
         .ok();
 
-    // Add unique constraint for one rating per user per day
-    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_ratings_user_date_unique ON ratings (user_id, DATE(date))")
-        .execute(&pool)
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_users_token_hash_unique ON users (token_hash)")
+        .execute(pool)
         .await
         .ok();
 
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_ingredients_workspace_name_unique ON ingredients (workspace_id, LOWER(name))")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_dish_ingredients_ingredient_id ON dish_ingredients(ingredient_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_lunch_participants_user_id ON lunch_participants(user_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_order_items_order_id ON order_items(order_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    // One lunch session per workspace per day
+    sqlx::query("DROP INDEX IF EXISTS idx_lunch_sessions_date_unique")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_lunch_sessions_workspace_date_unique ON lunch_sessions (workspace_id, date)")
+        .execute(pool)
+        .await
+        .ok();
+
+    for table in ["users", "dishes", "ratings", "ingredients", "restaurants", "orders", "payments"] {
+        sqlx::query(&format!("CREATE INDEX IF NOT EXISTS idx_{table}_workspace_id ON {table}(workspace_id)"))
+            .execute(pool)
+            .await
+            .ok();
+    }
+
+    // The one-per-day rule moved from ratings to meals, see one_meal_per_day
+    sqlx::query("DROP INDEX IF EXISTS idx_ratings_user_date_unique")
+        .execute(pool)
+        .await
+        .ok();
+
+    // A dish is only rated once per meal
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_ratings_meal_dish_unique ON ratings (meal_id, dish_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_meals_user_date ON meals(user_id, date)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_rating_comments_rating_id ON rating_comments(rating_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_rating_revisions_rating_id ON rating_revisions(rating_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_user_follows_followee_id ON user_follows(followee_id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries(next_attempt_at) WHERE status = '\"Pending\"'")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_events_workspace_id ON events(workspace_id, id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_job_runs_job_name ON job_runs(job_name, id)")
+        .execute(pool)
+        .await
+        .ok();
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_notifications_user_id ON notifications(user_id, id)")
+        .execute(pool)
+        .await
+        .ok();
+}
+
+fn app(pool: PgPool, mailer: Mailer, admin_token: AdminToken, webhook_targets: WebhookTargets, feed: FeedBroadcast) -> Router {
     // CORS
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -96,13 +838,14 @@ async fn main(
     let static_files = Router::new()
         .fallback_service(ServeDir::new("assets").not_found_service(ServeFile::new("assets/index.html")));
 
-    // Build router
-    let router = Router::new()
+    Router::new()
         .merge(routes::routes())
         .merge(SwaggerUi::new("/swagger-ui").url("/swagger-ui/openapi.json", ApiDoc::openapi()))
         .merge(static_files)
+        .layer(Extension(mailer))
+        .layer(Extension(admin_token))
+        .layer(Extension(webhook_targets))
+        .layer(Extension(feed))
         .layer(cors)
-        .with_state(pool);
-
-    Ok(router.into())
+        .with_state(pool)
 }
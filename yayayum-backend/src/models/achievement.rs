@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// Achievements shown on the leaderboard, unlocked by rating.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementKind {
+    FirstSteps,
+    FoodCritic,
+    Completionist,
+    Consistency,
+    Dedication,
+    BaliGorengKing,
+    SpicyChiliKing,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Achievement {
+    pub id: AchievementKind,
+    pub name: String,
+    pub description: String,
+    pub emoji: String,
+}
+
+/// The King achievements are for dishes on Yaya's menu, every workspace's
+/// first restaurant. Other restaurants can use the same menu numbers.
+pub const YAYA: &str = "Yaya";
+pub const BALI_GORENG_NR: i32 = 43;
+pub const SPICY_CHILI_NR: i32 = 7;
+
+/// What a user has rated so far, for working out their achievements.
+pub struct AchievementStats {
+    pub ratings: i64,
+    pub unique_dishes: i64,
+    pub total_dishes: i64, // Dishes in the workspace
+    pub longest_streak: i32, // Most days in a row with a rating
+    pub bali_goreng: i64, // Ratings of Yaya's dish nr 43
+    pub spicy_chili: i64, // Ratings of Yaya's dish nr 7
+}
+
+impl AchievementKind {
+    pub const ALL: [AchievementKind; 7] = [
+        AchievementKind::FirstSteps,
+        AchievementKind::FoodCritic,
+        AchievementKind::Completionist,
+        AchievementKind::Consistency,
+        AchievementKind::Dedication,
+        AchievementKind::BaliGorengKing,
+        AchievementKind::SpicyChiliKing,
+    ];
+
+    pub fn is_unlocked(&self, stats: &AchievementStats) -> bool {
+        match self {
+            AchievementKind::FirstSteps => stats.ratings >= 3,
+            AchievementKind::FoodCritic => stats.ratings >= 10,
+            AchievementKind::Completionist => stats.total_dishes > 0 && stats.unique_dishes >= stats.total_dishes,
+            AchievementKind::Consistency => stats.longest_streak >= 3,
+            AchievementKind::Dedication => stats.longest_streak >= 5,
+            AchievementKind::BaliGorengKing => stats.bali_goreng >= 5,
+            AchievementKind::SpicyChiliKing => stats.spicy_chili >= 5,
+        }
+    }
+
+    // Names and descriptions match the frontend, which is in Swedish
+    pub fn achievement(&self) -> Achievement {
+        let (name, description, emoji) = match self {
+            AchievementKind::FirstSteps => ("Gröngöling", "Skriv 3 recensioner", "🌱"),
+            AchievementKind::FoodCritic => ("Kritiker", "Skriv 10 recensioner", "📝"),
+            AchievementKind::Completionist => ("Stormästare", "Recensera alla rätter", "🎯"),
+            AchievementKind::Consistency => ("Fett hungrig", "Recensera 3 dagar i rad", "🔥"),
+            AchievementKind::Dedication => ("Kung av Yaya", "Recensera 5 dagar i rad", "⚡"),
+            AchievementKind::BaliGorengKing => ("Kung av Bali Goreng", "Ät Bali Goreng 5 gånger", "👑"),
+            AchievementKind::SpicyChiliKing => ("Kung av Spicy Chili Noodles", "Ät Spicy Chili Noodles 5 gånger", "🌶️"),
+        };
+
+        Achievement {
+            id: *self,
+            name: name.to_string(),
+            description: description.to_string(),
+            emoji: emoji.to_string(),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::ToSchema;
+use crate::models::{LeaderboardEntry, RankedDish};
+
+/// The form Slack and Mattermost post when someone types `/yaya ...`.
+/// Fields we don't use, like the channel, are ignored.
+#[derive(Deserialize, ToSchema)]
+pub struct SlashCommand {
+    pub user_id: String, // The chat's ID for the user, matched against users.chat_user_id
+    #[serde(default)]
+    pub text: String, // Everything after the command, e.g. "rate 43 4 too salty"
+    pub token: Option<String>, // Only sent by Mattermost, Slack signs the request instead
+}
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseType {
+    Ephemeral, // Only shown to whoever ran the command
+    InChannel,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct ChatResponse {
+    pub response_type: ResponseType,
+    pub text: String,
+}
+
+impl ChatResponse {
+    pub fn ephemeral(text: impl Into<String>) -> Self {
+        ChatResponse { response_type: ResponseType::Ephemeral, text: text.into() }
+    }
+
+    pub fn in_channel(text: impl Into<String>) -> Self {
+        ChatResponse { response_type: ResponseType::InChannel, text: text.into() }
+    }
+}
+
+/// A one-time code that links a chat account to the user it was made for,
+/// typed in the chat as `/yaya link <code>`.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct ChatLinkCode {
+    pub code: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+/// How long a chat link code works
+pub fn chat_link_lifetime() -> chrono::Duration {
+    chrono::Duration::minutes(15)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ChatCommand {
+    Rate { nr: i32, score: f64, description: Option<String> },
+    Top { query: Option<String> },
+    Leaderboard,
+    Link { code: String },
+    Help,
+}
+
+pub const HELP_TEXT: &str = "`/yaya rate <nr> <score> [comment]` rate today's lunch
+`/yaya top [search]` best rated dishes, optionally matching a name or category
+`/yaya leaderboard` who has rated the most
+`/yaya link <code>` connect your chat account to your YayaYum user, get the code in the app";
+
+/// Dishes or users listed in a reply
+pub const CHAT_LIST_LIMIT: usize = 5;
+
+/// Slack requests older than this are rejected to stop replays
+pub const MAX_SIGNATURE_AGE_SECS: i64 = 300;
+
+impl ChatCommand {
+    /// Parses the text after the slash command. Scores can use a decimal
+    /// comma, as in "4,5".
+    pub fn parse(text: &str) -> Option<ChatCommand> {
+        let text = text.trim();
+        let (name, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let rest = rest.trim();
+
+        match name.to_lowercase().as_str() {
+            "" | "help" => Some(ChatCommand::Help),
+            "rate" => {
+                let mut words = rest.split_whitespace();
+                let nr = words.next()?.parse().ok()?;
+                let score = words.next()?.replace(',', ".").parse().ok()?;
+                let description = words.collect::<Vec<_>>().join(" ");
+                Some(ChatCommand::Rate { nr, score, description: (!description.is_empty()).then_some(description) })
+            }
+            "top" => Some(ChatCommand::Top { query: (!rest.is_empty()).then(|| rest.to_string()) }),
+            "leaderboard" => Some(ChatCommand::Leaderboard),
+            "link" if !rest.is_empty() => Some(ChatCommand::Link { code: rest.to_string() }),
+            _ => None,
+        }
+    }
+}
+
+/// Checks Slack's `X-Slack-Signature`, a hex HMAC-SHA256 of
+/// `"v0:{timestamp}:{body}"` keyed with the app's signing secret.
+pub fn verify_slack_signature(secret: &str, timestamp: &str, body: &str, signature: &str) -> bool {
+    let Some(signature) = signature.strip_prefix("v0=").and_then(|hex| hex::decode(hex).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("v0:{timestamp}:{body}").as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
+pub fn format_top_dishes(query: Option<&str>, dishes: &[RankedDish]) -> String {
+    if dishes.is_empty() {
+        return match query {
+            Some(query) => format!("No rated dishes match \"{query}\" yet"),
+            None => "No dishes have been rated yet".to_string(),
+        };
+    }
+
+    let mut text = match query {
+        Some(query) => format!("*Top dishes matching \"{query}\"*\n"),
+        None => "*Top dishes*\n".to_string(),
+    };
+    for (place, dish) in dishes.iter().enumerate() {
+        text.push_str(&format!(
+            "{}. #{} {} ({}) - {:.1} from {} rating{}\n",
+            place + 1, dish.nr, dish.name, dish.category, dish.average_rating,
+            dish.rating_count, if dish.rating_count == 1 { "" } else { "s" },
+        ));
+    }
+    text
+}
+
+pub fn format_leaderboard(entries: &[LeaderboardEntry]) -> String {
+    let mut text = "*Leaderboard*\n".to_string();
+    for (place, entry) in entries.iter().enumerate() {
+        text.push_str(&format!(
+            "{}. {} - {} ratings of {} dishes, longest streak {} days\n",
+            place + 1, entry.username, entry.total_reviews, entry.unique_dishes, entry.longest_streak,
+        ));
+    }
+    text
+}
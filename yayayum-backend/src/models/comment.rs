@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// A comment on a rating. Replies to it are nested under `replies`.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Comment {
+    pub id: i32,
+    pub rating_id: i32,
+    pub parent_id: Option<i32>, // The comment this replies to
+    pub user_id: i32,
+    pub username: String,
+    pub body: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub updated_at: Option<chrono::NaiveDateTime>, // None until edited
+    #[schema(no_recursion)]
+    pub replies: Vec<Comment>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateComment {
+    pub body: String,
+    pub parent_id: Option<i32>,
+}
+
+/// Only the author can edit a comment.
+#[derive(Deserialize, ToSchema)]
+pub struct ModifyComment {
+    pub body: String,
+}
+
+/// Nests comments under the ones they reply to. Comments keep their order
+/// within each level.
+pub fn thread(comments: Vec<Comment>) -> Vec<Comment> {
+    let mut by_parent: HashMap<Option<i32>, Vec<Comment>> = HashMap::new();
+    for comment in comments {
+        by_parent.entry(comment.parent_id).or_default().push(comment);
+    }
+
+    fn attach(comment: &mut Comment, by_parent: &mut HashMap<Option<i32>, Vec<Comment>>) {
+        comment.replies = by_parent.remove(&Some(comment.id)).unwrap_or_default();
+        for reply in &mut comment.replies {
+            attach(reply, by_parent);
+        }
+    }
+
+    let mut roots = by_parent.remove(&None).unwrap_or_default();
+    for root in &mut roots {
+        attach(root, &mut by_parent);
+    }
+    roots
+}
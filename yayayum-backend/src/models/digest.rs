@@ -0,0 +1,251 @@
+use std::sync::OnceLock;
+use minijinja::Environment;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::Achievement;
+
+#[derive(Deserialize, IntoParams)]
+pub struct DigestQuery {
+    /// Last day of the week the digest covers, defaults to yesterday
+    #[param(value_type = Option<String>, format = "date")]
+    pub ending: Option<chrono::NaiveDate>,
+}
+
+/// Days a digest covers, up to and including its last day
+pub const DIGEST_DAYS: i64 = 7;
+
+/// Dishes or people listed in each part of the team summary
+pub const DIGEST_LIST_LIMIT: i64 = 5;
+
+/// The week a digest covers, ending on `ending`.
+pub fn digest_period(ending: chrono::NaiveDate) -> (chrono::NaiveDate, chrono::NaiveDate) {
+    (ending - chrono::Duration::days(DIGEST_DAYS - 1), ending)
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct DigestRating {
+    pub dish_id: i32,
+    pub nr: i32,
+    pub dish_name: String,
+    pub rating: f64,
+    pub description: Option<String>,
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct DigestDish {
+    pub dish_id: i32,
+    pub nr: i32,
+    pub name: String,
+    pub category: String,
+}
+
+/// A dish's ratings during the week.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct WeeklyDish {
+    pub dish_id: i32,
+    pub nr: i32,
+    pub name: String,
+    pub rating_count: i64,
+    pub average_rating: f64,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct WeeklyRater {
+    pub user_id: i32,
+    pub username: String,
+    pub rating_count: i64,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct UnlockedAchievement {
+    pub user_id: i32,
+    pub username: String,
+    pub achievement: Achievement,
+}
+
+/// One user's week.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct UserDigest {
+    pub user_id: i32,
+    pub username: String,
+    pub workspace_name: String,
+    #[schema(value_type = String, format = "date")]
+    pub start: chrono::NaiveDate,
+    #[schema(value_type = String, format = "date")]
+    pub end: chrono::NaiveDate,
+    pub ratings: Vec<DigestRating>,
+    pub rank: i64, // Place on the leaderboard by number of ratings at the end of the week
+    pub previous_rank: i64, // Place before the week started
+    pub achievements: Vec<Achievement>, // Unlocked during the week
+    pub new_dishes: Vec<DigestDish>,
+}
+
+/// The whole workspace's week.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct TeamDigest {
+    pub workspace_name: String,
+    #[schema(value_type = String, format = "date")]
+    pub start: chrono::NaiveDate,
+    #[schema(value_type = String, format = "date")]
+    pub end: chrono::NaiveDate,
+    pub rating_count: i64,
+    pub active_raters: i64,
+    pub top_dishes: Vec<WeeklyDish>, // Best average during the week
+    pub top_raters: Vec<WeeklyRater>,
+    pub achievements: Vec<UnlockedAchievement>,
+    pub new_dishes: Vec<DigestDish>,
+}
+
+/// A digest rendered for sending.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct DigestEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+}
+
+/// What sending the digests for a week did.
+#[derive(Serialize, ToSchema, Deserialize, Default)]
+pub struct DigestRun {
+    pub sent: i64,
+    pub skipped: i64, // Already sent for the week
+    pub failed: i64,
+}
+
+// Templates live in templates/ and are built into the binary. HTML templates
+// escape everything they insert.
+fn templates() -> &'static Environment<'static> {
+    static TEMPLATES: OnceLock<Environment<'static>> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        let mut env = Environment::new();
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        for (name, source) in [
+            ("user_digest.txt", include_str!("../../templates/user_digest.txt")),
+            ("user_digest.html", include_str!("../../templates/user_digest.html")),
+            ("team_digest.txt", include_str!("../../templates/team_digest.txt")),
+            ("team_digest.html", include_str!("../../templates/team_digest.html")),
+        ] {
+            env.add_template(name, source).expect("Invalid digest template");
+        }
+        env
+    })
+}
+
+fn render<T: Serialize>(name: &str, subject: String, digest: &T) -> Option<DigestEmail> {
+    let env = templates();
+    Some(DigestEmail {
+        subject,
+        text: env.get_template(&format!("{name}.txt")).ok()?.render(digest).ok()?,
+        html: env.get_template(&format!("{name}.html")).ok()?.render(digest).ok()?,
+    })
+}
+
+impl UserDigest {
+    pub fn render(&self) -> Option<DigestEmail> {
+        let subject = format!("Your week at {}: {} rating{}", self.workspace_name, self.ratings.len(),
+            if self.ratings.len() == 1 { "" } else { "s" });
+        render("user_digest", subject, self)
+    }
+}
+
+impl TeamDigest {
+    pub fn render(&self) -> Option<DigestEmail> {
+        let subject = format!("{} this week: {} rating{}", self.workspace_name, self.rating_count,
+            if self.rating_count == 1 { "" } else { "s" });
+        render("team_digest", subject, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DigestDish, DigestRating, TeamDigest, UnlockedAchievement, UserDigest, WeeklyDish, WeeklyRater};
+    use crate::models::AchievementKind;
+
+    fn date(day: u32) -> chrono::NaiveDate {
+        chrono::NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn new_dish() -> DigestDish {
+        DigestDish { dish_id: 2, nr: 7, name: "Spicy Chili".to_string(), category: "Stew".to_string() }
+    }
+
+    fn user_digest(ratings: Vec<DigestRating>) -> UserDigest {
+        UserDigest {
+            user_id: 1,
+            username: "alice".to_string(),
+            workspace_name: "Lunch club".to_string(),
+            start: date(3),
+            end: date(9),
+            ratings,
+            rank: 2,
+            previous_rank: 4,
+            achievements: vec![AchievementKind::FirstSteps.achievement()],
+            new_dishes: vec![new_dish()],
+        }
+    }
+
+    #[test]
+    fn renders_a_users_week() {
+        let email = user_digest(vec![DigestRating {
+            dish_id: 1,
+            nr: 43,
+            dish_name: "Bali Goreng".to_string(),
+            rating: 4.5,
+            description: Some("<b>crispy</b>".to_string()),
+            date: date(4),
+        }])
+        .render()
+        .unwrap();
+
+        assert_eq!(email.subject, "Your week at Lunch club: 1 rating");
+        assert!(email.text.contains("Hi alice,"));
+        assert!(email.text.contains("2025-03-03 to 2025-03-09"));
+        assert!(email.text.contains("You rated 1 dish:"));
+        assert!(email.text.contains("#43 Bali Goreng: 4.5 - <b>crispy</b>"));
+        assert!(email.text.contains("You climbed from #4 to #2"));
+        assert!(email.text.contains(&AchievementKind::FirstSteps.achievement().name));
+        assert!(email.text.contains("#7 Spicy Chili (Stew)"));
+        // Comments are escaped in the HTML version
+        assert!(email.html.contains("&lt;b&gt;crispy&lt;&#x2f;b&gt;"));
+        assert!(!email.html.contains("<b>crispy</b>"));
+    }
+
+    #[test]
+    fn renders_a_quiet_week() {
+        let email = user_digest(Vec::new()).render().unwrap();
+
+        assert_eq!(email.subject, "Your week at Lunch club: 0 ratings");
+        assert!(email.text.contains("You didn't rate anything this week."));
+    }
+
+    #[test]
+    fn renders_the_team_summary() {
+        let email = TeamDigest {
+            workspace_name: "Lunch club".to_string(),
+            start: date(3),
+            end: date(9),
+            rating_count: 12,
+            active_raters: 1,
+            top_dishes: vec![WeeklyDish { dish_id: 1, nr: 43, name: "Bali Goreng".to_string(), rating_count: 3, average_rating: 4.333 }],
+            top_raters: vec![WeeklyRater { user_id: 1, username: "alice".to_string(), rating_count: 12 }],
+            achievements: vec![UnlockedAchievement {
+                user_id: 1,
+                username: "alice".to_string(),
+                achievement: AchievementKind::FoodCritic.achievement(),
+            }],
+            new_dishes: vec![new_dish()],
+        }
+        .render()
+        .unwrap();
+
+        assert_eq!(email.subject, "Lunch club this week: 12 ratings");
+        assert!(email.text.contains("12 ratings from 1 person."));
+        assert!(email.text.contains("1. #43 Bali Goreng: 4.3 from 3 ratings"));
+        assert!(email.text.contains("1. alice: 12"));
+        assert!(email.text.contains(&format!("alice: {}", AchievementKind::FoodCritic.achievement().name)));
+        assert!(email.html.contains("Bali Goreng"));
+    }
+}
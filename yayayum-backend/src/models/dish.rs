@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::{Allergen, Ingredient, User};
+
+pub const MAX_SPICINESS: i32 = 5;
 
 #[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
 pub enum DietaryRestriction {
@@ -13,9 +16,10 @@ pub enum DietaryRestriction {
     Kosher,
     LowCarb,
     Keto,
-    None,
 }
 
+/// Menu sections at Yaya, which was the only restaurant before dishes were
+/// scoped per restaurant. Other restaurants have their own category lists.
 #[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
 pub enum DishCategory {
     WokWithNoodles,
@@ -27,23 +31,170 @@ pub enum DishCategory {
     SideOrder,
 }
 
+impl DishCategory {
+    pub const ALL: [DishCategory; 7] = [
+        DishCategory::WokWithNoodles,
+        DishCategory::SpecialDish,
+        DishCategory::Stew,
+        DishCategory::WokWithRice,
+        DishCategory::Ramen,
+        DishCategory::KidsMenu,
+        DishCategory::SideOrder,
+    ];
+}
+
 #[derive(Serialize, ToSchema, Deserialize, FromRow)]
 pub struct Dish {
     pub id: i32,
-    pub nr: i32,
+    pub restaurant_id: i32,
+    pub nr: i32, // Menu number at the restaurant
     pub name: String,
     pub description: String,
     pub price_kr: i32,
-    pub dietary_restrictions: Vec<DietaryRestriction>,
-    pub category: DishCategory,
+    pub dietary_restrictions: Vec<DietaryRestriction>, // As declared by whoever entered the dish
+    pub category: String, // One of the restaurant's categories
+    pub ingredients: Vec<Ingredient>,
+    pub allergens: Vec<Allergen>,
+    pub spiciness: i32, // 0-5
+    pub diet_compatibility: Vec<DietaryRestriction>, // Derived from ingredients when they are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suitability: Option<DishSuitability>, // Only set when listing dishes for a user
+}
+
+/// How well a dish matches a user's dietary preferences.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct DishSuitability {
+    pub suitable: bool,
+    pub missing_restrictions: Vec<DietaryRestriction>,
+    pub disliked_ingredients: Vec<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DishQuery {
+    /// Only list dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+    /// Check dishes against this user's dietary preferences
+    pub for_user: Option<i32>,
+    /// Leave out dishes the user can't or won't eat instead of flagging them
+    #[serde(default)]
+    pub hide_unsuitable: bool,
+}
+
+/// Works out which diets a dish fits. Vegan, vegetarian and the free-from
+/// restrictions are derived from the ingredient list when there is one, the
+/// rest (Halal, Kosher, LowCarb, Keto) can only be taken from the declaration.
+/// Animal allergens like Milk or Fish rule out Vegan (and Vegetarian) either way.
+pub fn diet_compatibility(
+    declared: &[DietaryRestriction],
+    ingredients: &[Ingredient],
+    allergens: &[Allergen],
+) -> Vec<DietaryRestriction> {
+    let all_allergens = || allergens.iter().chain(ingredients.iter().flat_map(|i| &i.allergens));
+    let vegan = !all_allergens().any(Allergen::is_animal_derived);
+    let vegetarian = !all_allergens().any(Allergen::is_fish_or_shellfish);
+
+    if ingredients.is_empty() {
+        return declared.iter()
+            .filter(|restriction| match restriction {
+                DietaryRestriction::Vegan => vegan,
+                DietaryRestriction::Vegetarian => vegetarian,
+                _ => true,
+            })
+            .cloned()
+            .collect();
+    }
+
+    let contains = |allergen: Allergen| all_allergens().any(|a| *a == allergen);
+
+    let mut compatibility = Vec::new();
+    if vegan && ingredients.iter().all(|i| !i.source.is_animal_derived()) {
+        compatibility.push(DietaryRestriction::Vegan);
+    }
+    if vegetarian && ingredients.iter().all(|i| !i.source.is_meat_or_fish()) {
+        compatibility.push(DietaryRestriction::Vegetarian);
+    }
+    if !contains(Allergen::Gluten) {
+        compatibility.push(DietaryRestriction::GlutenFree);
+    }
+    if !contains(Allergen::Milk) {
+        compatibility.push(DietaryRestriction::DairyFree);
+    }
+    if !contains(Allergen::TreeNuts) && !contains(Allergen::Peanuts) {
+        compatibility.push(DietaryRestriction::NutFree);
+    }
+    for restriction in [DietaryRestriction::Halal, DietaryRestriction::Kosher, DietaryRestriction::LowCarb, DietaryRestriction::Keto] {
+        if declared.contains(&restriction) {
+            compatibility.push(restriction);
+        }
+    }
+    compatibility
+}
+
+/// Returns the names of ingredients that contradict the dish's declared diets,
+/// e.g. anything animal-derived in a dish marked Vegan.
+pub fn conflicting_ingredients(declared: &[DietaryRestriction], ingredients: &[Ingredient]) -> Vec<String> {
+    ingredients.iter()
+        .filter(|i| {
+            (declared.contains(&DietaryRestriction::Vegan) && i.source.is_animal_derived())
+                || (declared.contains(&DietaryRestriction::Vegetarian) && i.source.is_meat_or_fish())
+                || !conflicting_allergens(declared, &i.allergens).is_empty()
+        })
+        .map(|i| i.name.clone())
+        .collect()
+}
+
+/// Returns the dish's allergens that contradict its declared diets, e.g. Milk
+/// in a dish marked Vegan.
+pub fn conflicting_allergens(declared: &[DietaryRestriction], allergens: &[Allergen]) -> Vec<Allergen> {
+    allergens.iter()
+        .filter(|a| {
+            (declared.contains(&DietaryRestriction::Vegan) && a.is_animal_derived())
+                || (declared.contains(&DietaryRestriction::Vegetarian) && a.is_fish_or_shellfish())
+        })
+        .cloned()
+        .collect()
+}
+
+impl Dish {
+    pub fn suitability_for(&self, user: &User) -> DishSuitability {
+        let missing_restrictions: Vec<DietaryRestriction> = user.dietary_restrictions.iter()
+            .filter(|restriction| !self.diet_compatibility.contains(restriction))
+            .cloned()
+            .collect();
+
+        // Dishes without an ingredient list can only be checked against the menu text
+        let contents = if self.ingredients.is_empty() {
+            format!("{} {}", self.name, self.description).to_lowercase()
+        } else {
+            self.ingredients.iter().map(|i| i.name.to_lowercase()).collect::<Vec<_>>().join(" ")
+        };
+        let disliked_ingredients: Vec<String> = user.disliked_ingredients.iter()
+            .filter(|ingredient| !ingredient.trim().is_empty())
+            .filter(|ingredient| contents.contains(&ingredient.trim().to_lowercase()))
+            .cloned()
+            .collect();
+
+        DishSuitability {
+            suitable: missing_restrictions.is_empty() && disliked_ingredients.is_empty(),
+            missing_restrictions,
+            disliked_ingredients,
+        }
+    }
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreateDish {
+    pub restaurant_id: Option<i32>, // Defaults to the first restaurant, Yaya, or keeps the current one on update
     pub nr: i32,
     pub name: String,
     pub description: String,
     pub price_kr: i32,
     pub dietary_restrictions: Vec<DietaryRestriction>,
-    pub category: DishCategory,
-}
\ No newline at end of file
+    pub category: String,
+    #[serde(default)]
+    pub ingredient_ids: Vec<i32>,
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+    #[serde(default)]
+    pub spiciness: i32, // 0-5
+}
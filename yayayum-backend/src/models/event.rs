@@ -0,0 +1,67 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Copy, Debug, PartialEq)]
+pub enum EventKind {
+    #[serde(rename = "rating.created")]
+    RatingCreated,
+    #[serde(rename = "rating.updated")]
+    RatingUpdated,
+    #[serde(rename = "rating.deleted")]
+    RatingDeleted,
+    #[serde(rename = "comment.created")]
+    CommentCreated,
+    #[serde(rename = "achievement.unlocked")]
+    AchievementUnlocked,
+    #[serde(rename = "dish.created")]
+    DishCreated,
+    #[serde(rename = "dish.updated")]
+    DishUpdated,
+    #[serde(rename = "dish.deleted")]
+    DishDeleted,
+    #[serde(rename = "user.joined")]
+    UserJoined,
+    #[serde(rename = "rating.reminder")]
+    RatingReminder,
+}
+
+impl EventKind {
+    /// Kinds only sent to webhooks, never shown in the feed
+    pub const WEBHOOK_ONLY: [EventKind; 1] = [EventKind::RatingReminder];
+}
+
+/// Something that happened in the workspace, as shown in the feed.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Event {
+    pub id: i32,
+    pub kind: EventKind,
+    pub user_id: Option<i32>, // Who did it
+    pub username: Option<String>,
+    pub subject_id: Option<i32>, // The rating, comment, dish or user it's about
+    #[schema(value_type = Object)]
+    pub data: serde_json::Value, // Details for showing the event, depending on the kind
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct FeedQuery {
+    /// Only events older than this event ID, for fetching the next page
+    pub before: Option<i32>,
+    /// Page size, defaults to 20
+    pub limit: Option<i64>,
+    /// Only events of this kind
+    #[param(inline)]
+    pub kind: Option<EventKind>,
+    /// Only events by people this user follows
+    pub followed_by: Option<i32>,
+}
+
+pub const DEFAULT_FEED_LIMIT: i64 = 20;
+pub const MAX_FEED_LIMIT: i64 = 100;
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct FeedPage {
+    pub events: Vec<Event>, // Newest first
+    pub next_before: Option<i32>, // Pass as `before` for the next page, None on the last page
+}
@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use utoipa::ToSchema;
+
+/// The 14 allergens that must be declared under EU regulation 1169/2011.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub enum Allergen {
+    Gluten,
+    Crustaceans,
+    Eggs,
+    Fish,
+    Peanuts,
+    Soybeans,
+    Milk,
+    TreeNuts,
+    Celery,
+    Mustard,
+    Sesame,
+    Sulphites,
+    Lupin,
+    Molluscs,
+}
+
+impl Allergen {
+    pub fn is_animal_derived(&self) -> bool {
+        matches!(self, Allergen::Milk | Allergen::Eggs) || self.is_fish_or_shellfish()
+    }
+
+    pub fn is_fish_or_shellfish(&self) -> bool {
+        matches!(self, Allergen::Fish | Allergen::Crustaceans | Allergen::Molluscs)
+    }
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub enum IngredientSource {
+    Plant,
+    Dairy,
+    Egg,
+    Honey,
+    Meat,
+    Poultry,
+    Fish,
+    Shellfish,
+}
+
+impl IngredientSource {
+    pub fn is_animal_derived(&self) -> bool {
+        *self != IngredientSource::Plant
+    }
+
+    pub fn is_meat_or_fish(&self) -> bool {
+        matches!(
+            self,
+            IngredientSource::Meat | IngredientSource::Poultry | IngredientSource::Fish | IngredientSource::Shellfish
+        )
+    }
+}
+
+#[derive(Serialize, ToSchema, Deserialize, FromRow, Clone)]
+pub struct Ingredient {
+    pub id: i32,
+    pub name: String,
+    pub source: IngredientSource,
+    pub allergens: Vec<Allergen>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateIngredient {
+    pub name: String,
+    pub source: IngredientSource,
+    #[serde(default)]
+    pub allergens: Vec<Allergen>,
+}
@@ -0,0 +1,107 @@
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Work the scheduler runs in the background. Each kind has one row in the
+/// jobs table, named after it.
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    WeeklyDigests, // Last week's digests for every workspace
+    RatingReminders, // Reminds people who haven't rated once their workspace's reminder time has passed
+}
+
+impl JobKind {
+    pub const ALL: [JobKind; 2] = [JobKind::WeeklyDigests, JobKind::RatingReminders];
+
+    /// Used until an admin changes it
+    pub fn default_schedule(self) -> &'static str {
+        match self {
+            JobKind::WeeklyDigests => "0 0 7 * * Mon",
+            JobKind::RatingReminders => "0 */5 * * * Mon-Fri",
+        }
+    }
+}
+
+/// A scheduled job. Times are UTC.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Job {
+    pub name: JobKind,
+    pub schedule: String,
+    pub enabled: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub next_run_at: chrono::NaiveDateTime, // Also when a failed run is retried
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub last_run_at: Option<chrono::NaiveDateTime>,
+    pub last_status: Option<JobRunStatus>,
+    pub attempts: i32, // Failed attempts at the current run, back to 0 once it succeeds or gives up
+    pub running: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ModifyJob {
+    /// Cron expression with seconds, like `0 0 7 * * Mon` for 07:00 UTC on Mondays
+    pub schedule: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum JobRunStatus {
+    Running,
+    Succeeded,
+    Failed,
+}
+
+/// One attempt at running a job.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct JobRun {
+    pub id: i32,
+    pub job: JobKind,
+    pub attempt: i32,
+    pub status: JobRunStatus,
+    pub output: Option<String>, // What the job did, or why it failed
+    #[schema(value_type = String, format = "date-time")]
+    pub started_at: chrono::NaiveDateTime,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub finished_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct JobRunQuery {
+    /// Only runs with this status
+    #[param(inline)]
+    pub status: Option<JobRunStatus>,
+    /// How many runs to return, newest first, defaults to 50
+    pub limit: Option<i64>,
+}
+
+pub const DEFAULT_JOB_RUN_LIMIT: i64 = 50;
+
+/// Attempts at a run before waiting for the next scheduled one
+pub const MAX_JOB_ATTEMPTS: i32 = 3;
+
+/// How long a claimed job stays locked. The scheduler keeps renewing the lock
+/// while the job runs, so if the instance running it dies, another one picks
+/// it up soon after.
+pub fn job_lease() -> chrono::Duration {
+    chrono::Duration::minutes(2)
+}
+
+/// How long to wait after a failed attempt, 5 minutes and then 10.
+pub fn job_retry_delay(attempts: i32) -> chrono::Duration {
+    chrono::Duration::minutes(5 * 2_i64.pow(attempts.clamp(1, 4) as u32 - 1))
+}
+
+/// The first time after `after` that matches a cron schedule, or None if the
+/// schedule doesn't parse or never matches again.
+pub fn next_run(schedule: &str, after: chrono::NaiveDateTime) -> Option<chrono::NaiveDateTime> {
+    cron::Schedule::from_str(schedule).ok()?
+        .after(&after.and_utc())
+        .next()
+        .map(|next| next.naive_utc())
+}
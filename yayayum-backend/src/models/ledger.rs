@@ -0,0 +1,128 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::models::Order;
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Debug, PartialEq)]
+pub struct Debt {
+    pub from_user_id: i32,
+    pub to_user_id: i32,
+    pub amount_kr: i32,
+}
+
+/// Money handed over between colleagues, usually to pay back a lunch order.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Payment {
+    pub id: i32,
+    pub from_user_id: i32,
+    pub to_user_id: i32,
+    pub amount_kr: i32,
+    pub order_id: Option<i32>,
+    pub note: Option<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreatePayment {
+    pub from_user_id: i32,
+    pub to_user_id: i32,
+    pub amount_kr: i32,
+    pub order_id: Option<i32>,
+    pub note: Option<String>,
+}
+
+/// Marks what `from_user_id` owes `to_user_id` as paid, either everything or
+/// just their share of one order.
+#[derive(Deserialize, ToSchema)]
+pub struct SettleDebt {
+    pub from_user_id: i32,
+    pub to_user_id: i32,
+    pub order_id: Option<i32>,
+}
+
+/// Positive when the user is owed money, negative when they owe.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Balance {
+    pub user_id: i32,
+    pub balance_kr: i32,
+}
+
+/// Nets what everyone owes the payers across all orders, minus what has been
+/// paid back, so each pair of users ends up with at most one debt between them.
+pub fn net_debts(orders: &[Order], payments: &[Payment]) -> Vec<Debt> {
+    // Keyed by (lower id, higher id), positive when the lower id owes the higher
+    let mut balances: BTreeMap<(i32, i32), i32> = BTreeMap::new();
+    let mut add = |from: i32, to: i32, amount: i32| {
+        if from < to {
+            *balances.entry((from, to)).or_default() += amount;
+        } else if from > to {
+            *balances.entry((to, from)).or_default() -= amount;
+        }
+    };
+
+    for order in orders {
+        for share in order.split() {
+            add(share.user_id, order.paid_by, share.total_kr);
+        }
+    }
+    for payment in payments {
+        add(payment.from_user_id, payment.to_user_id, -payment.amount_kr);
+    }
+
+    balances.into_iter()
+        .filter(|(_, amount)| *amount != 0)
+        .map(|((low, high), amount)| {
+            if amount > 0 {
+                Debt { from_user_id: low, to_user_id: high, amount_kr: amount }
+            } else {
+                Debt { from_user_id: high, to_user_id: low, amount_kr: -amount }
+            }
+        })
+        .collect()
+}
+
+pub fn balances(debts: &[Debt]) -> Vec<Balance> {
+    let mut balances: BTreeMap<i32, i32> = BTreeMap::new();
+    for debt in debts {
+        *balances.entry(debt.to_user_id).or_default() += debt.amount_kr;
+        *balances.entry(debt.from_user_id).or_default() -= debt.amount_kr;
+    }
+
+    balances.into_iter()
+        .filter(|(_, balance)| *balance != 0)
+        .map(|(user_id, balance_kr)| Balance { user_id, balance_kr })
+        .collect()
+}
+
+/// Suggests transfers that clear every balance, always matching the largest
+/// debtor with the largest creditor. That needs at most one transfer fewer
+/// than there are people with a balance.
+pub fn settle_up(balances: &[Balance]) -> Vec<Debt> {
+    let mut debtors: Vec<(i32, i32)> = balances.iter()
+        .filter(|b| b.balance_kr < 0)
+        .map(|b| (b.user_id, -b.balance_kr))
+        .collect();
+    let mut creditors: Vec<(i32, i32)> = balances.iter()
+        .filter(|b| b.balance_kr > 0)
+        .map(|b| (b.user_id, b.balance_kr))
+        .collect();
+
+    let mut transfers = Vec::new();
+    loop {
+        debtors.sort_by_key(|(user_id, amount)| (-amount, *user_id));
+        creditors.sort_by_key(|(user_id, amount)| (-amount, *user_id));
+        let (Some(debtor), Some(creditor)) = (debtors.first_mut(), creditors.first_mut()) else {
+            break;
+        };
+
+        let amount = debtor.1.min(creditor.1);
+        transfers.push(Debt { from_user_id: debtor.0, to_user_id: creditor.0, amount_kr: amount });
+        debtor.1 -= amount;
+        creditor.1 -= amount;
+
+        debtors.retain(|(_, amount)| *amount > 0);
+        creditors.retain(|(_, amount)| *amount > 0);
+    }
+    transfers
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct LunchSession {
+    pub id: i32,
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+    pub title: String, // Where or what we're eating
+    pub created_by: Option<i32>,
+    #[schema(value_type = String, format = "date-time")]
+    pub cutoff_at: chrono::NaiveDateTime,
+    pub locked: bool, // Locked manually or past the cutoff
+    pub participants: Vec<LunchParticipant>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateLunchSession {
+    pub title: String,
+    pub created_by: i32,
+    #[schema(value_type = String, format = "date-time")]
+    pub cutoff_at: chrono::NaiveDateTime,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct LunchParticipant {
+    pub user_id: i32,
+    pub username: String,
+    pub dish_id: Option<i32>, // None until the user has picked a dish
+    #[schema(value_type = String, format = "date-time")]
+    pub joined_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct JoinLunchSession {
+    pub dish_id: Option<i32>,
+}
+
+/// A dish someone picked in a locked session but hasn't rated yet, rated by
+/// sending `date` as the rating's `eaten_at`.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct RatingPrompt {
+    pub session_id: i32,
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+    pub dish_id: i32,
+    pub dish_name: String,
+}
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::models::{CreateRating, Rating, RatingRevision, SubScores};
+
+/// Everything someone ate in one sitting, like a main and a side order,
+/// with a rating per dish.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Meal {
+    pub id: i32,
+    pub user_id: i32,
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    pub ratings: Vec<Rating>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateMeal {
+    /// Day the meal was eaten, defaults to today
+    #[schema(value_type = Option<String>, format = "date")]
+    pub eaten_at: Option<chrono::NaiveDate>,
+    pub ratings: Vec<MealRating>,
+}
+
+/// The rating of one dish in a meal.
+#[derive(Deserialize, ToSchema, PartialEq)]
+pub struct MealRating {
+    pub dish_id: i32,
+    pub rating: f64,
+    pub description: Option<String>,
+    pub photo: Option<String>, // URL or path to photo
+    #[serde(default)]
+    pub sub_scores: SubScores,
+}
+
+impl From<&Rating> for MealRating {
+    fn from(rating: &Rating) -> Self {
+        MealRating {
+            dish_id: rating.dish_id,
+            rating: rating.rating,
+            description: rating.description.clone(),
+            photo: rating.photo.clone(),
+            sub_scores: rating.sub_scores.clone(),
+        }
+    }
+}
+
+impl From<&RatingRevision> for MealRating {
+    fn from(revision: &RatingRevision) -> Self {
+        MealRating {
+            dish_id: revision.dish_id,
+            rating: revision.rating,
+            description: revision.description.clone(),
+            photo: revision.photo.clone(),
+            sub_scores: revision.sub_scores.clone(),
+        }
+    }
+}
+
+impl From<&CreateRating> for MealRating {
+    fn from(rating: &CreateRating) -> Self {
+        MealRating {
+            dish_id: rating.dish_id,
+            rating: rating.rating,
+            description: rating.description.clone(),
+            photo: rating.photo.clone(),
+            sub_scores: rating.sub_scores.clone(),
+        }
+    }
+}
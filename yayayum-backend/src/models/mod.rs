@@ -1,7 +1,57 @@
+pub mod achievement;
+pub mod chat;
+pub mod comment;
+pub mod digest;
 pub mod dish;
+pub mod event;
+pub mod ingredient;
+pub mod job;
+pub mod ledger;
+pub mod lunch;
+pub mod meal;
+pub mod notification;
+pub mod order;
+pub mod recommendation;
+pub mod reminder;
+pub mod restaurant;
+pub mod stats;
 pub mod user;
+pub mod webhook;
+pub mod workspace;
 pub mod rating;
+pub mod reaction;
 
-pub use dish::{Dish, CreateDish, DietaryRestriction, DishCategory};
-pub use user::{User, CreateUser};
-pub use rating::{Rating, CreateRating};
\ No newline at end of file
+pub use achievement::{Achievement, AchievementKind};
+pub use chat::{SlashCommand, ChatResponse, ResponseType, ChatCommand, ChatLinkCode};
+pub use comment::{Comment, CreateComment, ModifyComment};
+pub use digest::{DigestQuery, UserDigest, TeamDigest, DigestEmail, DigestRun};
+pub use dish::{Dish, CreateDish, DietaryRestriction, DishCategory, DishQuery, DishSuitability};
+pub use event::{Event, EventKind, FeedQuery, FeedPage};
+pub use ingredient::{Ingredient, CreateIngredient, Allergen, IngredientSource};
+pub use job::{Job, JobKind, ModifyJob, JobRun, JobRunStatus, JobRunQuery};
+pub use lunch::{LunchSession, CreateLunchSession, LunchParticipant, JoinLunchSession, RatingPrompt};
+pub use meal::{Meal, CreateMeal, MealRating};
+pub use notification::{Notification, NotificationQuery};
+pub use order::{Order, CreateOrder, OrderItem, CreateOrderItem, OrderSummary, OrderSummaryLine, OrderShare};
+pub use ledger::{Debt, Payment, CreatePayment, SettleDebt, Balance};
+pub use reminder::{ReminderChannel, Holiday, CreateHoliday};
+pub use recommendation::{Recommendation, RecommendationQuery};
+pub use restaurant::{Restaurant, CreateRestaurant, OpeningHours, Weekday};
+pub use stats::{StatsQuery, DishStatsQuery, DishStats, LeaderboardEntry, SubScoreAverages, TopDishesQuery, TopDishes, RankedDish, CategoryRanking, ConfidenceInterval, RankingMethod, ControversialQuery, ControversialDish, DishOpinion};
+pub use user::{User, CreateUser, UpdateUser, AccessToken};
+pub use webhook::{Webhook, CreateWebhook, WebhookDelivery, DeliveryStatus, DeliveryQuery};
+pub use workspace::{Workspace, CreateWorkspace, ModifyWorkspace, NewWorkspace, WorkspaceInvite, WorkspaceMembership};
+pub use rating::{Rating, RatingQuery, CreateRating, SubScores, RatingScale, RatingRevision};
+pub use reaction::{Reaction, CreateReaction, ReactionCount, MostReactedQuery};
+
+use serde::{Deserialize, Deserializer};
+
+/// For optional fields in partial updates, where leaving a field out (None)
+/// keeps it and null (Some(None)) clears it. Use with `#[serde(default)]`.
+pub(crate) fn nullable<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// A message shown to a user in the app.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub message: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub read_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct NotificationQuery {
+    /// Only notifications that haven't been read
+    #[serde(default)]
+    pub unread: bool,
+    /// How many notifications to return, newest first, defaults to 50
+    pub limit: Option<i64>,
+}
+
+pub const DEFAULT_NOTIFICATION_LIMIT: i64 = 50;
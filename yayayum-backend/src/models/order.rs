@@ -0,0 +1,168 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Order {
+    pub id: i32,
+    pub paid_by: i32, // The user who places and pays for the order
+    pub session_id: Option<i32>,
+    pub delivery_fee_kr: i32,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    pub items: Vec<OrderItem>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateOrder {
+    pub paid_by: i32,
+    pub session_id: Option<i32>, // Copies everyone's pick from the lunch session
+    #[serde(default)]
+    pub delivery_fee_kr: i32,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct OrderItem {
+    pub id: i32,
+    pub user_id: Option<i32>, // None for side orders shared by everyone
+    pub dish_id: Option<i32>,
+    pub dish_nr: i32,
+    pub dish_name: String,
+    pub unit_price_kr: i32, // Price when the item was added
+    pub quantity: i32,
+    pub notes: Option<String>, // e.g. "extra spicy"
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateOrderItem {
+    pub user_id: Option<i32>,
+    pub dish_id: i32,
+    #[serde(default = "default_quantity")]
+    pub quantity: i32,
+    pub notes: Option<String>,
+}
+
+fn default_quantity() -> i32 {
+    1
+}
+
+/// What to tell the restaurant, one line per dish.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct OrderSummary {
+    pub order_id: i32,
+    pub lines: Vec<OrderSummaryLine>,
+    pub delivery_fee_kr: i32,
+    pub total_kr: i32,
+    pub text: String, // Printable version for phoning in the order
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct OrderSummaryLine {
+    pub nr: i32,
+    pub name: String,
+    pub quantity: i32,
+    pub notes: Vec<String>,
+    pub line_total_kr: i32,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct OrderShare {
+    pub user_id: i32,
+    pub items_kr: i32,
+    pub shared_kr: i32, // Share of the shared side orders
+    pub delivery_kr: i32,
+    pub total_kr: i32,
+}
+
+/// Splits `total` into `parts` whole kronor, handing the remainder out one
+/// krona at a time from the front.
+fn split_evenly(total: i32, parts: usize) -> Vec<i32> {
+    if parts == 0 {
+        return Vec::new();
+    }
+    let parts_i32 = parts as i32;
+    (0..parts_i32)
+        .map(|i| total / parts_i32 + i32::from(i < total % parts_i32))
+        .collect()
+}
+
+impl OrderItem {
+    pub fn total_kr(&self) -> i32 {
+        self.unit_price_kr * self.quantity
+    }
+}
+
+impl Order {
+    pub fn total_kr(&self) -> i32 {
+        self.items.iter().map(OrderItem::total_kr).sum::<i32>() + self.delivery_fee_kr
+    }
+
+    pub fn summary(&self) -> OrderSummary {
+        let mut lines: BTreeMap<(i32, String), OrderSummaryLine> = BTreeMap::new();
+        for item in &self.items {
+            let line = lines.entry((item.dish_nr, item.dish_name.clone())).or_insert_with(|| OrderSummaryLine {
+                nr: item.dish_nr,
+                name: item.dish_name.clone(),
+                quantity: 0,
+                notes: Vec::new(),
+                line_total_kr: 0,
+            });
+            line.quantity += item.quantity;
+            line.line_total_kr += item.total_kr();
+            if let Some(notes) = item.notes.as_ref().filter(|n| !n.trim().is_empty()) {
+                line.notes.push(format!("{} x {}", item.quantity, notes.trim()));
+            }
+        }
+        let lines: Vec<OrderSummaryLine> = lines.into_values().collect();
+
+        let mut text = format!("Order #{}\n", self.id);
+        for line in &lines {
+            text.push_str(&format!("{:>3}  {} x{}  {} kr\n", line.nr, line.name, line.quantity, line.line_total_kr));
+            for notes in &line.notes {
+                text.push_str(&format!("       - {notes}\n"));
+            }
+        }
+        if self.delivery_fee_kr > 0 {
+            text.push_str(&format!("Delivery: {} kr\n", self.delivery_fee_kr));
+        }
+        text.push_str(&format!("Total: {} kr\n", self.total_kr()));
+
+        OrderSummary {
+            order_id: self.id,
+            lines,
+            delivery_fee_kr: self.delivery_fee_kr,
+            total_kr: self.total_kr(),
+            text,
+        }
+    }
+
+    /// Works out what everyone pays. Shared side orders and the delivery fee
+    /// are split evenly between everyone with a dish of their own, or land on
+    /// the payer if nobody has one.
+    pub fn split(&self) -> Vec<OrderShare> {
+        let mut own: BTreeMap<i32, i32> = BTreeMap::new();
+        for item in &self.items {
+            if let Some(user_id) = item.user_id {
+                *own.entry(user_id).or_default() += item.total_kr();
+            }
+        }
+        if own.is_empty() {
+            own.insert(self.paid_by, 0);
+        }
+
+        let shared_total: i32 = self.items.iter().filter(|i| i.user_id.is_none()).map(OrderItem::total_kr).sum();
+        let shared = split_evenly(shared_total, own.len());
+        let delivery = split_evenly(self.delivery_fee_kr, own.len());
+
+        own.into_iter()
+            .zip(shared.into_iter().zip(delivery))
+            .map(|((user_id, items_kr), (shared_kr, delivery_kr))| OrderShare {
+                user_id,
+                items_kr,
+                shared_kr,
+                delivery_kr,
+                total_kr: items_kr + shared_kr + delivery_kr,
+            })
+            .collect()
+    }
+}
@@ -1,24 +1,139 @@
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
-use utoipa::ToSchema;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::{MealRating, ReactionCount};
 
 #[derive(Serialize, ToSchema, Deserialize, FromRow)]
 pub struct Rating {
     pub id: i32,
+    pub meal_id: i32,
     pub dish_id: i32,
-    pub rating: i32, // 1-5
+    pub rating: f64, // On the workspace's rating scale
     pub user_id: i32,
     pub description: Option<String>,
     pub photo: Option<String>, // URL or path to photo
     #[schema(value_type = String, format = "date-time")]
     pub date: chrono::NaiveDateTime,
+    #[sqlx(flatten)]
+    pub sub_scores: SubScores,
+    pub comment_count: i64,
+    #[sqlx(skip)]
+    pub reactions: Vec<ReactionCount>, // Most used emoji first
+    pub edited: bool,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub updated_at: Option<chrono::NaiveDateTime>, // Last edit
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct RatingQuery {
+    /// Only ratings by people this user follows
+    pub followed_by: Option<i32>,
 }
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreateRating {
     pub dish_id: i32,
-    pub rating: i32, // 1-5
-    pub user_id: i32,
+    pub rating: f64, // On the workspace's rating scale
     pub description: Option<String>,
     pub photo: Option<String>, // URL or path to photo
-}
\ No newline at end of file
+    #[serde(default)]
+    pub sub_scores: SubScores,
+    /// Day the meal was eaten, for rating a lunch from the last few days.
+    /// Defaults to today, or the meal's day when adding to a meal.
+    #[schema(value_type = Option<String>, format = "date")]
+    pub eaten_at: Option<chrono::NaiveDate>,
+    /// Adds the rating to this meal instead of starting a new one
+    pub meal_id: Option<i32>,
+}
+
+/// Optional scores for parts of the meal, on the same scale as the overall
+/// rating.
+#[derive(Serialize, ToSchema, Deserialize, FromRow, Clone, Default, PartialEq)]
+pub struct SubScores {
+    pub taste: Option<f64>,
+    pub portion: Option<f64>, // Portion size
+    pub spiciness_accuracy: Option<f64>, // As spicy as the menu promised
+    pub temperature: Option<f64>,
+    pub value: Option<f64>, // Value for money
+    pub wait_time: Option<f64>,
+}
+
+impl SubScores {
+    pub const COLUMNS: [&'static str; 6] = ["taste", "portion", "spiciness_accuracy", "temperature", "value", "wait_time"];
+
+    pub fn values(&self) -> [Option<f64>; 6] {
+        [self.taste, self.portion, self.spiciness_accuracy, self.temperature, self.value, self.wait_time]
+    }
+}
+
+/// A rating as it was before an edit.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct RatingRevision {
+    pub id: i32,
+    pub rating_id: i32,
+    pub dish_id: i32,
+    pub rating: f64,
+    pub description: Option<String>,
+    pub photo: Option<String>,
+    pub sub_scores: SubScores,
+    #[schema(value_type = String, format = "date-time")]
+    pub replaced_at: chrono::NaiveDateTime, // When the edit was made
+    pub changed: Vec<String>, // Fields the edit changed, like "rating" or "taste"
+}
+
+/// Names of the fields that differ between two versions of a rating.
+pub fn changed_fields(before: &MealRating, after: &MealRating) -> Vec<String> {
+    let mut changed = Vec::new();
+    if before.dish_id != after.dish_id {
+        changed.push("dish_id".to_string());
+    }
+    if before.rating != after.rating {
+        changed.push("rating".to_string());
+    }
+    if before.description != after.description {
+        changed.push("description".to_string());
+    }
+    if before.photo != after.photo {
+        changed.push("photo".to_string());
+    }
+    let sub_scores = before.sub_scores.values().into_iter().zip(after.sub_scores.values());
+    for (column, (before, after)) in SubScores::COLUMNS.into_iter().zip(sub_scores) {
+        if before != after {
+            changed.push(column.to_string());
+        }
+    }
+    changed
+}
+
+/// The scores a workspace rates on, from 1 up to `max`, in whole or half steps.
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct RatingScale {
+    pub max: i32,
+    pub half_steps: bool,
+}
+
+impl Default for RatingScale {
+    fn default() -> Self {
+        RatingScale { max: 5, half_steps: true }
+    }
+}
+
+impl RatingScale {
+    pub const MIN: f64 = 1.0;
+    pub const LARGEST_MAX: i32 = 10;
+
+    pub fn is_valid(&self) -> bool {
+        (2..=Self::LARGEST_MAX).contains(&self.max)
+    }
+
+    /// Whether `score` is a value on this scale. Every rating and sub-score
+    /// goes through here.
+    pub fn accepts(&self, score: f64) -> bool {
+        let steps = if self.half_steps { score * 2.0 } else { score };
+        (Self::MIN..=f64::from(self.max)).contains(&score) && steps.fract() == 0.0
+    }
+
+    pub fn accepts_rating(&self, rating: &MealRating) -> bool {
+        self.accepts(rating.rating) && rating.sub_scores.values().into_iter().flatten().all(|s| self.accepts(s))
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// One user's emoji on a rating.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Reaction {
+    pub rating_id: i32,
+    pub user_id: i32,
+    pub username: String,
+    pub emoji: String,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+pub struct CreateReaction {
+    pub emoji: String, // e.g. "👍", "😂", "🔥" or "🌶️"
+}
+
+/// Longest emoji accepted, long enough for flags, skin tones and
+/// zero-width-joined sequences like "🧑‍🍳"
+const MAX_EMOJI_CHARS: usize = 8;
+
+/// Pictographs from the Unicode emoji blocks.
+fn is_pictograph(c: char) -> bool {
+    matches!(c as u32,
+        0x00A9 | 0x00AE | 0x203C | 0x2049 | 0x2122 | 0x2139 | 0x3030 | 0x303D | 0x3297 | 0x3299
+        | 0x2194..=0x21AA // Arrows
+        | 0x231A..=0x23FF // Watches, hourglasses and media buttons
+        | 0x25AA..=0x25FE // Geometric shapes
+        | 0x2600..=0x27BF // Miscellaneous symbols and dingbats
+        | 0x2934..=0x2935 | 0x2B05..=0x2B55 // More arrows, stars and circles
+        | 0x1F000..=0x1F0FF // Mahjong and playing cards
+        | 0x1F170..=0x1F251 // Enclosed letters and ideographs
+        | 0x1F300..=0x1FAFF // Pictographs, emoticons, transport and food, including skin tones
+    )
+}
+
+/// Characters that only combine pictographs into one emoji.
+fn is_emoji_component(c: char) -> bool {
+    matches!(c as u32,
+        0x200D // Zero-width joiner
+        | 0xFE0F // Variation selector 16, emoji style
+        | 0x20E3 // Combining keycap
+        | 0x1F1E6..=0x1F1FF // Regional indicators, in pairs for flags
+        | 0xE0020..=0xE007F // Tags, for subdivision flags
+    )
+}
+
+impl CreateReaction {
+    /// Reactions are a single emoji, not text or punctuation.
+    pub fn is_valid(&self) -> bool {
+        let chars = self.emoji.chars().count();
+        let starts_with_emoji = self.emoji.chars().next()
+            .is_some_and(|c| is_pictograph(c) || (0x1F1E6..=0x1F1FF).contains(&(c as u32)));
+
+        (1..=MAX_EMOJI_CHARS).contains(&chars)
+            && starts_with_emoji
+            && self.emoji.chars().all(|c| is_pictograph(c) || is_emoji_component(c))
+    }
+}
+
+/// How many users reacted to a rating with an emoji.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct ReactionCount {
+    pub emoji: String,
+    pub count: i64,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct MostReactedQuery {
+    /// Only count reactions with this emoji
+    pub emoji: Option<String>,
+    /// How many ratings to return, defaults to 10
+    pub limit: Option<i64>,
+}
+
+pub const DEFAULT_MOST_REACTED_LIMIT: i64 = 10;
+
+#[cfg(test)]
+mod tests {
+    use super::CreateReaction;
+
+    fn reaction(emoji: &str) -> CreateReaction {
+        CreateReaction { emoji: emoji.to_string() }
+    }
+
+    #[test]
+    fn accepts_emoji() {
+        for emoji in ["👍", "😂", "🔥", "🌶️", "❤️", "🧑‍🍳", "👍🏽", "🇸🇪", "👨‍👩‍👧‍👦"] {
+            assert!(reaction(emoji).is_valid(), "{emoji}");
+        }
+    }
+
+    #[test]
+    fn rejects_text_and_punctuation() {
+        for emoji in ["", "a", "ok", "!", "?!", "...", "#", "-_-", " ", "\u{200D}", "👍 👍 👍 👍 👍"] {
+            assert!(!reaction(emoji).is_valid(), "{emoji:?}");
+        }
+    }
+}
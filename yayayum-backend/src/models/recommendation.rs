@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::Dish;
+
+#[derive(Deserialize, IntoParams)]
+pub struct RecommendationQuery {
+    /// Compare raters on z-scores of their own ratings instead of raw scores
+    #[serde(default)]
+    pub normalized: bool,
+    /// Only recommend dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+    /// How many dishes to return, defaults to 10
+    pub limit: Option<usize>,
+    /// Only go by the ratings of people the user follows
+    #[serde(default)]
+    pub following_only: bool,
+}
+
+pub const DEFAULT_RECOMMENDATION_LIMIT: usize = 10;
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Recommendation {
+    pub dish: Dish,
+    pub predicted_score: f64, // A z-score when normalized
+    pub based_on: i64, // Number of colleagues' ratings the prediction uses
+}
+
+/// Weight of a rater who has no dishes in common with the user, so their
+/// opinion still counts for something.
+const STRANGER_WEIGHT: f64 = 0.25;
+
+/// Predicts how `user_id` would score the dishes they haven't rated, from
+/// `(user_id, dish_id, score)` triples. Other raters count more the closer
+/// their scores were to the user's on the dishes both have rated.
+pub fn predict_scores(user_id: i32, scores: &[(i32, i32, f64)]) -> HashMap<i32, (f64, i64)> {
+    let mut by_user: HashMap<i32, HashMap<i32, f64>> = HashMap::new();
+    for &(rater, dish_id, score) in scores {
+        by_user.entry(rater).or_default().insert(dish_id, score);
+    }
+    let own = by_user.remove(&user_id).unwrap_or_default();
+
+    let mut totals: HashMap<i32, (f64, f64, i64)> = HashMap::new();
+    for rater_scores in by_user.values() {
+        let differences: Vec<f64> = rater_scores.iter()
+            .filter_map(|(dish_id, score)| own.get(dish_id).map(|mine| (mine - score).abs()))
+            .collect();
+        let weight = if differences.is_empty() {
+            STRANGER_WEIGHT
+        } else {
+            1.0 / (1.0 + differences.iter().sum::<f64>() / differences.len() as f64)
+        };
+
+        for (dish_id, score) in rater_scores.iter().filter(|(dish_id, _)| !own.contains_key(dish_id)) {
+            let total = totals.entry(*dish_id).or_default();
+            total.0 += weight * score;
+            total.1 += weight;
+            total.2 += 1;
+        }
+    }
+
+    totals.into_iter()
+        .map(|(dish_id, (weighted, weights, count))| (dish_id, (weighted / weights, count)))
+        .collect()
+}
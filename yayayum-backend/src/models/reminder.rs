@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// How a user gets reminded to rate their lunch.
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ReminderChannel {
+    #[default]
+    InApp, // A notification, see /users/{id}/notifications
+    Email, // Falls back to InApp while email isn't set up
+    Webhook, // A rating.reminder event for the workspace's webhooks, e.g. a chat bot
+}
+
+/// A day without reminders, like a public holiday or a team offsite.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Holiday {
+    pub id: i32,
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+    pub name: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateHoliday {
+    #[schema(value_type = String, format = "date")]
+    pub date: chrono::NaiveDate,
+    pub name: String,
+}
+
+impl CreateHoliday {
+    pub fn is_valid(&self) -> bool {
+        !self.name.trim().is_empty()
+    }
+}
+
+pub const REMINDER_SUBJECT: &str = "How was lunch?";
+
+pub fn reminder_message(username: &str) -> String {
+    format!("Hi {username}, you haven't rated today's lunch yet. How was it?")
+}
+
+/// The reminder as the HTML part of an email, escaped since it contains the username.
+pub fn reminder_html(message: &str) -> String {
+    let escaped = message.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        .replace('"', "&quot;").replace('\'', "&#39;");
+    format!("<p>{escaped}</p>")
+}
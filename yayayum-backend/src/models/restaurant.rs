@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug, PartialEq)]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+pub struct OpeningHours {
+    pub weekday: Weekday,
+    #[schema(value_type = String, example = "11:00:00")]
+    pub opens: chrono::NaiveTime,
+    #[schema(value_type = String, example = "14:00:00")]
+    pub closes: chrono::NaiveTime,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Restaurant {
+    pub id: i32,
+    pub name: String,
+    pub address: String,
+    pub opening_hours: Vec<OpeningHours>,
+    pub categories: Vec<String>, // The restaurant's own menu sections
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateRestaurant {
+    pub name: String,
+    #[serde(default)]
+    pub address: String,
+    #[serde(default)]
+    pub opening_hours: Vec<OpeningHours>,
+    pub categories: Vec<String>,
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use crate::models::RatingScale;
+
+#[derive(Deserialize, IntoParams)]
+pub struct StatsQuery {
+    /// Only count dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DishStatsQuery {
+    /// Only count dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+    /// Average z-scores of each rating against the rater's own ratings instead of raw scores
+    #[serde(default)]
+    pub normalized: bool,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct DishStats {
+    pub dish_id: i32,
+    pub restaurant_id: i32,
+    pub nr: i32,
+    pub name: String,
+    pub category: String,
+    pub rating_count: i64,
+    pub average_rating: Option<f64>, // None until someone has rated the dish, a z-score when normalized
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub last_rated_at: Option<chrono::NaiveDateTime>,
+    pub sub_score_averages: SubScoreAverages,
+}
+
+/// Average of each sub-score, counting only the reviews that gave one.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct SubScoreAverages {
+    pub taste: Option<f64>,
+    pub portion: Option<f64>,
+    pub spiciness_accuracy: Option<f64>,
+    pub temperature: Option<f64>,
+    pub value: Option<f64>,
+    pub wait_time: Option<f64>,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct LeaderboardEntry {
+    pub user_id: i32,
+    pub username: String,
+    pub total_reviews: i64,
+    pub unique_dishes: i64,
+    pub average_rating: Option<f64>,
+    pub longest_streak: i32, // Most days in a row with a rating
+}
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Default, Debug, PartialEq)]
+pub enum RankingMethod {
+    /// Pulls every dish towards a prior mean until it has enough reviews
+    #[default]
+    Bayesian,
+    /// Lower bound of the Wilson score interval, ignores the prior
+    Wilson,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct TopDishesQuery {
+    /// Only rank dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+    /// Bayesian (default) or Wilson
+    #[param(inline)]
+    #[serde(default)]
+    pub method: RankingMethod,
+    /// How many reviews' worth of weight the prior gets, defaults to 5
+    pub prior_weight: Option<f64>,
+    /// Score a dish is assumed to have before any reviews, defaults to the mean of all ratings
+    pub prior_mean: Option<f64>,
+    /// Rank on z-scores of each rating against the rater's own ratings, only with Bayesian
+    #[serde(default)]
+    pub normalized: bool,
+}
+
+pub const DEFAULT_PRIOR_WEIGHT: f64 = 5.0;
+
+/// z for a 95% confidence interval.
+const Z_95: f64 = 1.96;
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct ConfidenceInterval {
+    pub low: f64,
+    pub high: f64,
+}
+
+#[derive(Serialize, ToSchema, Deserialize, Clone)]
+pub struct RankedDish {
+    pub dish_id: i32,
+    pub restaurant_id: i32,
+    pub nr: i32,
+    pub name: String,
+    pub category: String,
+    pub rating_count: i64,
+    pub average_rating: f64, // Plain average
+    pub adjusted_score: f64, // What the ranking sorts by
+    pub confidence_interval: ConfidenceInterval,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct CategoryRanking {
+    pub category: String,
+    pub dishes: Vec<RankedDish>,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct TopDishes {
+    pub method: RankingMethod,
+    pub normalized: bool, // Scores are z-scores rather than on the rating scale
+    pub prior_mean: f64,
+    pub prior_weight: f64,
+    pub overall: Vec<RankedDish>,
+    pub by_category: Vec<CategoryRanking>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct ControversialQuery {
+    /// Only include dishes from this restaurant
+    pub restaurant_id: Option<i32>,
+    /// Leave out dishes with fewer reviews than this, defaults to 3
+    pub min_reviews: Option<i64>,
+    /// Compare z-scores of each rating against the rater's own ratings instead of raw scores
+    #[serde(default)]
+    pub normalized: bool,
+}
+
+pub const DEFAULT_MIN_REVIEWS: i64 = 3;
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct ControversialDish {
+    pub dish_id: i32,
+    pub restaurant_id: i32,
+    pub nr: i32,
+    pub name: String,
+    pub category: String,
+    pub rating_count: i64,
+    pub average_rating: f64,
+    pub variance: f64, // Population variance of the scores, what the report sorts by
+    pub standard_deviation: f64,
+    pub lovers: Vec<DishOpinion>, // Scored it above its average, fondest first
+    pub haters: Vec<DishOpinion>, // Scored it below its average, harshest first
+}
+
+/// One user's average score for a dish.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct DishOpinion {
+    pub user_id: i32,
+    pub username: String,
+    pub score: f64,
+}
+
+/// The prior a Bayesian average starts from: `weight` imaginary reviews
+/// scoring `mean`, spread out like all other ratings.
+#[derive(Clone, Copy, Debug)]
+pub struct Prior {
+    pub mean: f64,
+    pub weight: f64,
+    pub variance: f64,
+}
+
+/// Bayesian average of a dish with `count` reviews averaging `mean`, with a
+/// normal approximation of its 95% interval. The interval is kept within the
+/// rating scale unless the scores are normalised and have no bounds.
+pub fn bayesian_score(count: i64, mean: f64, variance: f64, prior: &Prior, scale: Option<&RatingScale>) -> (f64, ConfidenceInterval) {
+    let n = count as f64;
+    let total_weight = prior.weight + n;
+    if total_weight <= 0.0 {
+        return (mean, ConfidenceInterval { low: mean, high: mean });
+    }
+
+    let adjusted = (prior.weight * prior.mean + n * mean) / total_weight;
+    let blended_variance = (prior.weight * prior.variance + n * variance) / total_weight;
+    let margin = Z_95 * (blended_variance / total_weight).sqrt();
+
+    let interval = match scale {
+        Some(scale) => clamped_interval(adjusted - margin, adjusted + margin, scale),
+        None => ConfidenceInterval { low: adjusted - margin, high: adjusted + margin },
+    };
+    (adjusted, interval)
+}
+
+/// Wilson score interval, treating the average as the share of the way from
+/// the bottom to the top of the scale. The dish is ranked by the lower bound.
+pub fn wilson_score(count: i64, mean: f64, scale: &RatingScale) -> (f64, ConfidenceInterval) {
+    let n = count as f64;
+    let range = f64::from(scale.max) - RatingScale::MIN;
+    let p = ((mean - RatingScale::MIN) / range).clamp(0.0, 1.0);
+
+    let z2 = Z_95 * Z_95;
+    let denominator = 1.0 + z2 / n;
+    let centre = (p + z2 / (2.0 * n)) / denominator;
+    let margin = Z_95 * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denominator;
+
+    let to_scale = |x: f64| RatingScale::MIN + x * range;
+    let interval = clamped_interval(to_scale(centre - margin), to_scale(centre + margin), scale);
+    (interval.low, interval)
+}
+
+fn clamped_interval(low: f64, high: f64, scale: &RatingScale) -> ConfidenceInterval {
+    let max = f64::from(scale.max);
+    ConfidenceInterval {
+        low: low.clamp(RatingScale::MIN, max),
+        high: high.clamp(RatingScale::MIN, max),
+    }
+}
+
+/// Longest run of consecutive days in a sorted, deduplicated list of dates.
+pub fn longest_streak(dates: &[chrono::NaiveDate]) -> i32 {
+    let mut longest = 0;
+    let mut current = 0;
+    let mut previous: Option<chrono::NaiveDate> = None;
+    for date in dates {
+        current = match previous {
+            Some(prev) if prev.succ_opt() == Some(*date) => current + 1,
+            _ => 1,
+        };
+        longest = longest.max(current);
+        previous = Some(*date);
+    }
+    longest
+}
@@ -1,14 +1,104 @@
 use serde::{Deserialize, Serialize};
 use sqlx::prelude::FromRow;
 use utoipa::ToSchema;
+use crate::models::{nullable, DietaryRestriction, ReminderChannel};
 
 #[derive(Deserialize, ToSchema)]
 pub struct CreateUser {
     pub username: String,
+    #[serde(default)]
+    pub dietary_restrictions: Vec<DietaryRestriction>,
+    #[serde(default)]
+    pub disliked_ingredients: Vec<String>,
+    /// The user's ID in the team chat, e.g. Slack's U012AB3CD. Only admins can
+    /// set it, everyone else links their account from the chat.
+    pub chat_user_id: Option<String>,
+    pub email: Option<String>,
+    /// Send a summary of the week every Monday, needs an email
+    #[serde(default)]
+    pub weekly_digest: bool,
+    /// Get reminded to rate lunch on workdays, see the workspace's reminder_time
+    #[serde(default = "default_rating_reminders")]
+    pub rating_reminders: bool,
+    /// Email needs an email address
+    #[serde(default)]
+    pub reminder_channel: ReminderChannel,
+}
+
+fn default_rating_reminders() -> bool {
+    true
+}
+
+impl CreateUser {
+    pub fn is_valid(&self) -> bool {
+        let emailed_reminders = self.rating_reminders && self.reminder_channel == ReminderChannel::Email;
+        match &self.email {
+            Some(email) => email.parse::<lettre::Address>().is_ok(),
+            None => !self.weekly_digest && !emailed_reminders,
+        }
+    }
+}
+
+/// Changes to a user. Fields that are left out keep their current value, and
+/// the chat user or email can be removed with null.
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateUser {
+    pub username: Option<String>,
+    pub dietary_restrictions: Option<Vec<DietaryRestriction>>,
+    pub disliked_ingredients: Option<Vec<String>>,
+    /// Only admins can change it, everyone else links their account from the chat
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub chat_user_id: Option<Option<String>>,
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub email: Option<Option<String>>,
+    pub weekly_digest: Option<bool>,
+    pub rating_reminders: Option<bool>,
+    pub reminder_channel: Option<ReminderChannel>,
+}
+
+impl UpdateUser {
+    /// The user with the changes made, to check and save as a whole.
+    pub fn apply_to(self, user: User) -> CreateUser {
+        CreateUser {
+            username: self.username.unwrap_or(user.username),
+            dietary_restrictions: self.dietary_restrictions.unwrap_or(user.dietary_restrictions),
+            disliked_ingredients: self.disliked_ingredients.unwrap_or(user.disliked_ingredients),
+            chat_user_id: self.chat_user_id.unwrap_or(user.chat_user_id),
+            email: self.email.unwrap_or(user.email),
+            weekly_digest: self.weekly_digest.unwrap_or(user.weekly_digest),
+            rating_reminders: self.rating_reminders.unwrap_or(user.rating_reminders),
+            reminder_channel: self.reminder_channel.unwrap_or(user.reminder_channel),
+        }
+    }
 }
 
 #[derive(Serialize, ToSchema, Deserialize, FromRow)]
 pub struct User {
     pub id: i32,
     pub username: String,
-}
\ No newline at end of file
+    pub dietary_restrictions: Vec<DietaryRestriction>,
+    pub disliked_ingredients: Vec<String>, // Matched case-insensitively against dish contents
+    pub chat_user_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>, // Only shown on the user's own record
+    pub weekly_digest: bool,
+    pub rating_reminders: bool,
+    pub reminder_channel: ReminderChannel,
+    pub is_admin: bool, // Can change the workspace's settings and manage its members
+}
+
+impl User {
+    /// The user as someone else sees them, without their email address.
+    pub fn seen_by(self, viewer_id: i32) -> User {
+        if self.id == viewer_id { self } else { User { email: None, ..self } }
+    }
+}
+
+/// A new session token for a user, only shown once.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct AccessToken {
+    pub user_id: i32,
+    pub token: String,
+}
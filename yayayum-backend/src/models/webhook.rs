@@ -0,0 +1,142 @@
+use std::net::IpAddr;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use utoipa::{IntoParams, ToSchema};
+use crate::models::EventKind;
+
+/// Somewhere to POST events to, like a team chat integration.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub event_types: Vec<EventKind>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>, // Key for the X-Yayayum-Signature header, only shown when the webhook is created
+    pub active: bool,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWebhook {
+    pub url: String,
+    pub event_types: Vec<EventKind>,
+    /// Generated when left out
+    pub secret: Option<String>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl CreateWebhook {
+    pub fn is_valid(&self) -> bool {
+        (self.url.starts_with("http://") || self.url.starts_with("https://"))
+            && !self.event_types.is_empty()
+            && self.secret.as_ref().is_none_or(|secret| !secret.is_empty())
+    }
+}
+
+#[derive(Serialize, ToSchema, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum DeliveryStatus {
+    Pending, // Waiting for the first attempt or a retry
+    Succeeded,
+    Failed, // Gave up after too many attempts
+}
+
+/// One event sent to one webhook, including the outcome of the last attempt.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: i32,
+    pub webhook_id: i32,
+    pub event_id: i32,
+    pub event_type: EventKind,
+    pub status: DeliveryStatus,
+    pub attempts: i32,
+    #[schema(value_type = String, format = "date-time")]
+    pub next_attempt_at: chrono::NaiveDateTime,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    #[schema(value_type = Option<String>, format = "date-time")]
+    pub delivered_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Deserialize, IntoParams)]
+pub struct DeliveryQuery {
+    /// Only deliveries with this status
+    #[param(inline)]
+    pub status: Option<DeliveryStatus>,
+    /// How many deliveries to return, newest first, defaults to 50
+    pub limit: Option<i64>,
+}
+
+pub const DEFAULT_DELIVERY_LIMIT: i64 = 50;
+
+/// Attempts before a delivery is marked as failed
+pub const MAX_DELIVERY_ATTEMPTS: i32 = 8;
+
+/// How long to wait after a failed attempt, doubling from 30 seconds up to
+/// about an hour.
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    chrono::Duration::seconds(30 * 2_i64.pow(attempts.clamp(1, 8) as u32 - 1))
+}
+
+/// Hex encoded HMAC-SHA256 of `"{timestamp}.{body}"`, sent as
+/// `X-Yayayum-Signature: sha256=<signature>`. Receivers recompute it with the
+/// webhook's secret, and can reject old timestamps to stop replays.
+pub fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Whether an address is out on the internet, rather than on the server
+/// itself or its network, where a webhook could reach internal services.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))) // Carrier-grade NAT
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                || ip.is_unique_local()
+                || ip.is_unicast_link_local()),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_public_address;
+
+    #[test]
+    fn private_addresses_are_not_public() {
+        for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "0.0.0.0", "100.64.0.1", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+
+    #[test]
+    fn internet_addresses_are_public() {
+        for ip in ["93.184.216.34", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{ip}");
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::models::{nullable, CreateUser, RatingScale, User};
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct Workspace {
+    pub id: i32,
+    pub name: String,
+    pub invite_code: String, // Anyone with the code can join, see /invites/{code}
+    #[schema(value_type = String, format = "date-time")]
+    pub created_at: chrono::NaiveDateTime,
+    pub rating_scale: RatingScale,
+    pub rating_grace_days: i32, // How many days back a rating can be dated
+    pub one_meal_per_day: bool, // Limit everyone to rating one meal a day
+    pub chat_enabled: bool, // Whether a chat signing secret is set, which enables /chat/{workspace_id}/command
+    pub digest_email: Option<String>, // Gets the weekly team summary
+    #[schema(value_type = Option<String>, format = "time")]
+    pub reminder_time: Option<chrono::NaiveTime>, // When to remind people who haven't rated, None for no reminders
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateWorkspace {
+    pub name: String,
+    #[serde(default)]
+    pub rating_scale: RatingScale,
+    #[serde(default = "default_rating_grace_days")]
+    pub rating_grace_days: i32,
+    #[serde(default = "default_one_meal_per_day")]
+    pub one_meal_per_day: bool,
+    /// Slack signing secret or Mattermost token for the chat slash command
+    pub chat_signing_secret: Option<String>,
+    /// Where to send the weekly team summary, e.g. a mailing list
+    pub digest_email: Option<String>,
+    /// Server time on workdays to remind people who haven't rated today's lunch,
+    /// like "13:30". Leave it out for no reminders.
+    #[schema(value_type = Option<String>, format = "time")]
+    pub reminder_time: Option<chrono::NaiveTime>,
+}
+
+fn default_rating_grace_days() -> i32 {
+    3
+}
+
+fn default_one_meal_per_day() -> bool {
+    true
+}
+
+impl CreateWorkspace {
+    pub fn is_valid(&self) -> bool {
+        self.rating_scale.is_valid()
+            && self.rating_grace_days >= 0
+            && self.chat_signing_secret.as_ref().is_none_or(|secret| !secret.is_empty())
+            && self.digest_email.as_ref().is_none_or(|email| email.parse::<lettre::Address>().is_ok())
+    }
+}
+
+/// Changes to a workspace's settings. Fields that are left out keep their
+/// current value, and the optional ones are turned off with null.
+#[derive(Deserialize, ToSchema)]
+pub struct ModifyWorkspace {
+    pub name: Option<String>,
+    pub rating_scale: Option<RatingScale>,
+    pub rating_grace_days: Option<i32>,
+    pub one_meal_per_day: Option<bool>,
+    /// Slack signing secret or Mattermost token for the chat slash command
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub chat_signing_secret: Option<Option<String>>,
+    /// Where to send the weekly team summary, e.g. a mailing list
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub digest_email: Option<Option<String>>,
+    /// Server time on workdays to remind people who haven't rated today's lunch
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>, format = "time")]
+    pub reminder_time: Option<Option<chrono::NaiveTime>>,
+}
+
+impl ModifyWorkspace {
+    pub fn is_valid(&self) -> bool {
+        self.rating_scale.as_ref().is_none_or(RatingScale::is_valid)
+            && self.rating_grace_days.is_none_or(|days| days >= 0)
+            && self.chat_signing_secret.iter().flatten().all(|secret| !secret.is_empty())
+            && self.digest_email.iter().flatten().all(|email| email.parse::<lettre::Address>().is_ok())
+    }
+}
+
+/// What an invite link shows before joining.
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct WorkspaceInvite {
+    pub workspace_id: i32,
+    pub workspace_name: String,
+}
+
+/// A new workspace along with the user who creates it, its first admin.
+#[derive(Deserialize, ToSchema)]
+pub struct NewWorkspace {
+    #[serde(flatten)]
+    pub workspace: CreateWorkspace,
+    pub admin: CreateUser,
+}
+
+#[derive(Serialize, ToSchema, Deserialize)]
+pub struct WorkspaceMembership {
+    pub workspace_id: i32,
+    pub user: User,
+    pub token: String, // Only shown once, send it as `Authorization: Bearer <token>`
+}
@@ -0,0 +1,236 @@
+use axum::{extract::{Path, Query, State}, http::{HeaderMap, StatusCode}, response::Json, routing::post, Router};
+use sqlx::{PgPool, Row};
+use subtle::ConstantTimeEq;
+use crate::models::{ChatCommand, ChatLinkCode, ChatResponse, MealRating, SlashCommand, StatsQuery, SubScores, TopDishesQuery};
+use crate::models::chat::{chat_link_lifetime, format_leaderboard, format_top_dishes, verify_slack_signature, CHAT_LIST_LIMIT, HELP_TEXT, MAX_SIGNATURE_AGE_SECS};
+use crate::routes::ratings::rate_dish;
+use crate::routes::stats::{get_leaderboard, get_top_dishes};
+use crate::routes::workspaces::{fetch_rating_scale, token_hash, CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/chat/{workspace_id}/command", post(run_chat_command))
+        .route("/chat/link-code", post(create_chat_link_code))
+}
+
+// Short enough to type in the chat. Like session tokens only the hash is
+// kept, and the code only works for a while.
+const NEW_LINK_CODE: &str = "upper(substr(replace(gen_random_uuid()::text, '-', ''), 1, 10))";
+
+const SLACK_SIGNATURE_HEADER: &str = "x-slack-signature";
+const SLACK_TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+
+/// Slack signs the raw body, Mattermost sends the token in the form instead.
+fn is_authentic(secret: &str, headers: &HeaderMap, body: &str, command: &SlashCommand) -> bool {
+    let signature = headers.get(SLACK_SIGNATURE_HEADER).and_then(|v| v.to_str().ok());
+    let timestamp = headers.get(SLACK_TIMESTAMP_HEADER).and_then(|v| v.to_str().ok());
+
+    match (signature, timestamp) {
+        (Some(signature), Some(timestamp)) => {
+            let fresh = timestamp.parse::<i64>()
+                .is_ok_and(|sent| (chrono::Utc::now().timestamp() - sent).abs() <= MAX_SIGNATURE_AGE_SECS);
+            fresh && verify_slack_signature(secret, timestamp, body, signature)
+        }
+        _ => command.token.as_deref().is_some_and(|token| bool::from(token.as_bytes().ct_eq(secret.as_bytes()))),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/chat/link-code",
+    responses(
+        (status = 200, description = "Code to send as `/yaya link <code>` in the chat, replacing any earlier one", body = ChatLinkCode)
+    ),
+    tag = "chat"
+)]
+pub async fn create_chat_link_code(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+) -> Result<Json<ChatLinkCode>, StatusCode> {
+    let expires_at = chrono::Utc::now().naive_utc() + chat_link_lifetime();
+
+    let code: String = sqlx::query_scalar(&format!(
+        "WITH new AS (SELECT {NEW_LINK_CODE} AS code)
+         UPDATE users SET chat_link_code_hash = {}, chat_link_expires_at = $2 FROM new
+         WHERE users.id = $1
+         RETURNING new.code",
+        token_hash("new.code")
+    ))
+    .bind(current_user.id)
+    .bind(expires_at)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(ChatLinkCode { code, expires_at }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/chat/{workspace_id}/command",
+    request_body(content = SlashCommand, content_type = "application/x-www-form-urlencoded"),
+    params(
+        ("workspace_id" = i32, Path, description = "Workspace the chat is connected to")
+    ),
+    responses(
+        (status = 200, description = "Reply to show in the chat, also for commands that didn't work", body = ChatResponse),
+        (status = 400, description = "Bad request - not a slash command form"),
+        (status = 401, description = "Unauthorized - bad signature or token, or a stale timestamp"),
+        (status = 404, description = "Workspace not found or has no chat signing secret")
+    ),
+    security(()),
+    tag = "chat"
+)]
+pub async fn run_chat_command(
+    State(pool): State<PgPool>,
+    Path(workspace_id): Path<i32>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<ChatResponse>, StatusCode> {
+    let secret: String = sqlx::query_scalar::<_, Option<String>>("SELECT chat_signing_secret FROM workspaces WHERE id = $1")
+        .bind(workspace_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .flatten()
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let command: SlashCommand = serde_urlencoded::from_str(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if !is_authentic(&secret, &headers, &body, &command) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let user = sqlx::query("SELECT id, username FROM users WHERE workspace_id = $1 AND chat_user_id = $2")
+        .bind(workspace_id)
+        .bind(&command.user_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map(|row| (row.get::<i32, _>("id"), row.get::<String, _>("username")));
+
+    let Some(parsed) = ChatCommand::parse(&command.text) else {
+        return Ok(Json(ChatResponse::ephemeral(format!("Sorry, I didn't get that. Try:\n{HELP_TEXT}"))));
+    };
+
+    let response = match parsed {
+        ChatCommand::Help => ChatResponse::ephemeral(HELP_TEXT),
+        ChatCommand::Link { code } => link_chat_user(&pool, workspace_id, &command.user_id, user, &code).await?,
+        ChatCommand::Rate { nr, score, description } => match user {
+            Some((user_id, username)) => rate_from_chat(&pool, workspace_id, user_id, &username, nr, score, description).await?,
+            None => ChatResponse::ephemeral("I don't know who you are yet, get a code in the app and use `/yaya link <code>` first"),
+        },
+        ChatCommand::Top { query } => {
+            let Json(top) = get_top_dishes(State(pool.clone()), CurrentWorkspace(workspace_id), Query(TopDishesQuery {
+                restaurant_id: None,
+                method: Default::default(),
+                prior_weight: None,
+                prior_mean: None,
+                normalized: false,
+            })).await?;
+
+            let search = query.as_deref().map(str::to_lowercase);
+            let dishes: Vec<_> = top.overall.into_iter()
+                .filter(|dish| search.as_ref().is_none_or(|search| {
+                    dish.name.to_lowercase().contains(search) || dish.category.to_lowercase().contains(search)
+                }))
+                .take(CHAT_LIST_LIMIT)
+                .collect();
+            ChatResponse::ephemeral(format_top_dishes(query.as_deref(), &dishes))
+        }
+        ChatCommand::Leaderboard => {
+            let Json(mut leaderboard) = get_leaderboard(
+                State(pool.clone()),
+                CurrentWorkspace(workspace_id),
+                Query(StatsQuery { restaurant_id: None }),
+            ).await?;
+            leaderboard.truncate(CHAT_LIST_LIMIT);
+            ChatResponse::ephemeral(format_leaderboard(&leaderboard))
+        }
+    };
+
+    Ok(Json(response))
+}
+
+async fn link_chat_user(
+    pool: &PgPool,
+    workspace_id: i32,
+    chat_user_id: &str,
+    current: Option<(i32, String)>,
+    code: &str,
+) -> Result<ChatResponse, StatusCode> {
+    if let Some((_, linked)) = current {
+        return Ok(ChatResponse::ephemeral(format!("You're already linked to {linked}")));
+    }
+
+    // The code proves who you are in the app, and only works once
+    let linked: Option<String> = sqlx::query_scalar(&format!(
+        "UPDATE users SET chat_user_id = $1, chat_link_code_hash = NULL, chat_link_expires_at = NULL
+         WHERE workspace_id = $2 AND chat_link_code_hash = {} AND chat_link_expires_at > $4
+         RETURNING username",
+        token_hash("upper($3)")
+    ))
+    .bind(chat_user_id)
+    .bind(workspace_id)
+    .bind(code.trim())
+    .bind(chrono::Utc::now().naive_utc())
+    .fetch_optional(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(ChatResponse::ephemeral(match linked {
+        Some(username) => format!("Linked! You're {username} from now on"),
+        None => "That code doesn't work, it may have expired. Get a new one in the app".to_string(),
+    }))
+}
+
+async fn rate_from_chat(
+    pool: &PgPool,
+    workspace_id: i32,
+    user_id: i32,
+    username: &str,
+    nr: i32,
+    score: f64,
+    description: Option<String>,
+) -> Result<ChatResponse, StatusCode> {
+    let dishes = sqlx::query("SELECT id, name FROM dishes WHERE workspace_id = $1 AND nr = $2 ORDER BY id")
+        .bind(workspace_id)
+        .bind(nr)
+        .fetch_all(pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let (dish_id, name): (i32, String) = match dishes.as_slice() {
+        [dish] => (dish.get("id"), dish.get("name")),
+        [] => return Ok(ChatResponse::ephemeral(format!("There's no dish number {nr}"))),
+        _ => return Ok(ChatResponse::ephemeral(format!("Several menus have a dish number {nr}, rate it in the app instead"))),
+    };
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let scale = fetch_rating_scale(&mut tx, workspace_id).await?;
+    if !scale.accepts(score) {
+        let steps = if scale.half_steps { " in half steps" } else { "" };
+        return Ok(ChatResponse::ephemeral(format!("{score} isn't on the rating scale, use 1 to {}{steps}", scale.max)));
+    }
+
+    let rating = MealRating {
+        dish_id,
+        rating: score,
+        description: description.clone(),
+        photo: None,
+        sub_scores: SubScores::default(),
+    };
+    let created = rate_dish(&mut tx, workspace_id, user_id, None, None, &rating).await;
+    if created.is_ok() {
+        tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    Ok(match created {
+        Ok(_) => ChatResponse::in_channel(match description {
+            Some(description) => format!("{username} rated #{nr} {name} {score}: {description}"),
+            None => format!("{username} rated #{nr} {name} {score}"),
+        }),
+        Err(StatusCode::BAD_REQUEST) => ChatResponse::ephemeral("Couldn't save that rating, try it in the app instead"),
+        Err(StatusCode::CONFLICT) => ChatResponse::ephemeral("You've already rated that lunch today, edit it in the app instead"),
+        Err(status) => return Err(status),
+    })
+}
@@ -0,0 +1,211 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{get, put}, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{Comment, CreateComment, EventKind, ModifyComment};
+use crate::models::comment::thread;
+use crate::routes::feed::record_event;
+use crate::routes::ratings::rating_exists;
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/ratings/{id}/comments", get(get_comments).post(create_comment))
+        .route("/ratings/{id}/comments/{comment_id}", put(modify_comment).delete(remove_comment))
+}
+
+const COMMENT_COLUMNS: &str = "c.id, c.rating_id, c.parent_id, c.user_id, u.username, c.body, c.created_at, c.updated_at";
+
+fn comment_from_row(row: &PgRow) -> Comment {
+    Comment {
+        id: row.get("id"),
+        rating_id: row.get("rating_id"),
+        parent_id: row.get("parent_id"),
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        body: row.get("body"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        replies: Vec::new(),
+    }
+}
+
+/// A rating's comments, oldest first, or just one of them.
+async fn fetch_comments(pool: &PgPool, rating_id: i32, comment_id: Option<i32>) -> Result<Vec<Comment>, StatusCode> {
+    let rows = sqlx::query(&format!(
+        "SELECT {COMMENT_COLUMNS} FROM rating_comments c JOIN users u ON u.id = c.user_id
+         WHERE c.rating_id = $1 AND ($2::int IS NULL OR c.id = $2)
+         ORDER BY c.created_at, c.id"
+    ))
+    .bind(rating_id)
+    .bind(comment_id)
+    .fetch_all(pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(rows.iter().map(comment_from_row).collect())
+}
+
+/// Checks that the current user wrote the comment before letting them change it.
+async fn check_author(pool: &PgPool, current_user: CurrentUser, rating_id: i32, comment_id: i32) -> Result<(), StatusCode> {
+    let author: i32 = sqlx::query_scalar(
+        "SELECT c.user_id FROM rating_comments c JOIN ratings r ON r.id = c.rating_id
+         WHERE c.id = $1 AND c.rating_id = $2 AND r.workspace_id = $3"
+    )
+    .bind(comment_id)
+    .bind(rating_id)
+    .bind(current_user.workspace_id)
+    .fetch_one(pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    if author == current_user.id { Ok(()) } else { Err(StatusCode::FORBIDDEN) }
+}
+
+#[utoipa::path(
+    get,
+    path = "/ratings/{id}/comments",
+    params(
+        ("id" = i32, Path, description = "Rating ID")
+    ),
+    responses(
+        (status = 200, description = "Comments on the rating, oldest first with replies nested", body = [Comment]),
+        (status = 404, description = "Rating not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn get_comments(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Comment>>, StatusCode> {
+    rating_exists(&pool, workspace_id, id).await?;
+
+    Ok(Json(thread(fetch_comments(&pool, id, None).await?)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/ratings/{id}/comments",
+    params(
+        ("id" = i32, Path, description = "Rating ID")
+    ),
+    request_body = CreateComment,
+    responses(
+        (status = 201, description = "Comment added by the current user", body = Comment),
+        (status = 400, description = "Bad request - empty comment, or replying to a comment on another rating"),
+        (status = 404, description = "Rating not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn create_comment(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateComment>,
+) -> Result<(StatusCode, Json<Comment>), StatusCode> {
+    let workspace_id = current_user.workspace_id;
+    rating_exists(&pool, workspace_id, id).await?;
+    if payload.body.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let comment_id: i32 = sqlx::query_scalar(
+        "INSERT INTO rating_comments (rating_id, parent_id, user_id, body)
+         SELECT $1, $2, $3, $4
+         WHERE $2::int IS NULL OR EXISTS (SELECT 1 FROM rating_comments WHERE id = $2 AND rating_id = $1)
+         RETURNING id"
+    )
+    .bind(id)
+    .bind(payload.parent_id)
+    .bind(current_user.id)
+    .bind(payload.body.trim())
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let data = serde_json::json!({
+        "rating_id": id,
+        "parent_id": payload.parent_id,
+        "body": payload.body.trim(),
+    });
+    record_event(&mut tx, workspace_id, EventKind::CommentCreated, Some(current_user.id), Some(comment_id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let comment = fetch_comments(&pool, id, Some(comment_id)).await?.pop().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/ratings/{id}/comments/{comment_id}",
+    params(
+        ("id" = i32, Path, description = "Rating ID"),
+        ("comment_id" = i32, Path, description = "Comment ID to edit")
+    ),
+    request_body = ModifyComment,
+    responses(
+        (status = 200, description = "Comment edited", body = Comment),
+        (status = 400, description = "Bad request - empty comment"),
+        (status = 403, description = "Forbidden - only the author can edit a comment"),
+        (status = 404, description = "Rating or comment not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn modify_comment(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, comment_id)): Path<(i32, i32)>,
+    Json(payload): Json<ModifyComment>,
+) -> Result<Json<Comment>, StatusCode> {
+    check_author(&pool, current_user, id, comment_id).await?;
+    if payload.body.trim().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    sqlx::query("UPDATE rating_comments SET body = $1, updated_at = NOW() WHERE id = $2")
+        .bind(payload.body.trim())
+        .bind(comment_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Replies are left out, they're only nested when listing the thread
+    fetch_comments(&pool, id, Some(comment_id)).await?.pop()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/ratings/{id}/comments/{comment_id}",
+    params(
+        ("id" = i32, Path, description = "Rating ID"),
+        ("comment_id" = i32, Path, description = "Comment ID to remove, along with its replies")
+    ),
+    responses(
+        (status = 204, description = "Comment deleted"),
+        (status = 403, description = "Forbidden - only the author can delete a comment"),
+        (status = 404, description = "Rating or comment not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn remove_comment(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, comment_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+    check_author(&pool, current_user, id, comment_id).await?;
+
+    sqlx::query("DELETE FROM rating_comments WHERE id = $1")
+        .bind(comment_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
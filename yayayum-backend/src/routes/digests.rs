@@ -0,0 +1,391 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post}, Extension, Router};
+use sqlx::{PgConnection, PgPool, Row};
+use crate::mailer::Mailer;
+use crate::models::{Achievement, AchievementKind, DigestEmail, DigestQuery, DigestRun, EventKind, TeamDigest, UserDigest};
+use crate::models::digest::{digest_period, DigestDish, DigestRating, UnlockedAchievement, WeeklyDish, WeeklyRater, DIGEST_LIST_LIMIT};
+use crate::routes::workspaces::{CurrentWorkspace, WorkspaceAdmin};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/users/{id}/digest", get(get_user_digest))
+        .route("/digests/team", get(get_team_digest))
+        .route("/admin/digests/send", post(send_weekly_digests))
+}
+
+/// The day a digest ends on, yesterday unless asked for.
+async fn digest_ending(conn: &mut PgConnection, ending: Option<chrono::NaiveDate>) -> Result<chrono::NaiveDate, StatusCode> {
+    match ending {
+        Some(ending) => Ok(ending),
+        None => sqlx::query_scalar("SELECT CURRENT_DATE - 1")
+            .fetch_one(conn)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR),
+    }
+}
+
+fn achievement_from_str(achievement: &str) -> Result<Achievement, StatusCode> {
+    let kind: AchievementKind = serde_json::from_str(achievement).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(kind.achievement())
+}
+
+/// Dishes added to the menu during the week that are still on it.
+async fn fetch_new_dishes(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    (start, end): (chrono::NaiveDate, chrono::NaiveDate),
+) -> Result<Vec<DigestDish>, StatusCode> {
+    let kind = serde_json::to_string(&EventKind::DishCreated).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rows = sqlx::query(
+        "SELECT d.id, d.nr, d.name, d.category
+         FROM events e JOIN dishes d ON d.id = e.subject_id
+         WHERE e.workspace_id = $1 AND e.kind = $2 AND DATE(e.created_at) BETWEEN $3 AND $4
+         ORDER BY d.nr, d.id"
+    )
+    .bind(workspace_id)
+    .bind(&kind)
+    .bind(start)
+    .bind(end)
+    .fetch_all(conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    rows.iter()
+        .map(|row| Ok(DigestDish {
+            dish_id: row.get("id"),
+            nr: row.get("nr"),
+            name: row.get("name"),
+            category: serde_json::from_str(&row.get::<String, _>("category"))
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+        }))
+        .collect()
+}
+
+async fn fetch_user_digest(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    user_id: i32,
+    ending: chrono::NaiveDate,
+) -> Result<UserDigest, StatusCode> {
+    let (start, end) = digest_period(ending);
+
+    let user = sqlx::query(
+        "SELECT u.username, w.name AS workspace_name FROM users u JOIN workspaces w ON w.id = u.workspace_id
+         WHERE u.id = $1 AND u.workspace_id = $2"
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let ratings = sqlx::query(
+        "SELECT r.dish_id, d.nr, d.name, r.rating, r.description, DATE(r.date) AS day
+         FROM ratings r JOIN dishes d ON d.id = r.dish_id
+         WHERE r.user_id = $1 AND DATE(r.date) BETWEEN $2 AND $3
+         ORDER BY r.date, r.id"
+    )
+    .bind(user_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|row| DigestRating {
+        dish_id: row.get("dish_id"),
+        nr: row.get("nr"),
+        dish_name: row.get("name"),
+        rating: row.get("rating"),
+        description: row.get("description"),
+        date: row.get("day"),
+    })
+    .collect();
+
+    // Leaderboard places by number of ratings, before and after the week
+    let ranks = sqlx::query(
+        "SELECT previous_rank, rank FROM (
+            SELECT u.id,
+                RANK() OVER (ORDER BY COUNT(r.id) FILTER (WHERE DATE(r.date) < $2) DESC) AS previous_rank,
+                RANK() OVER (ORDER BY COUNT(r.id) FILTER (WHERE DATE(r.date) <= $3) DESC) AS rank
+            FROM users u LEFT JOIN ratings r ON r.user_id = u.id
+            WHERE u.workspace_id = $1
+            GROUP BY u.id
+         ) ranks WHERE id = $4"
+    )
+    .bind(workspace_id)
+    .bind(start)
+    .bind(end)
+    .bind(user_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let achievements = sqlx::query_scalar::<_, String>(
+        "SELECT achievement FROM user_achievements WHERE user_id = $1 AND DATE(unlocked_at) BETWEEN $2 AND $3
+         ORDER BY unlocked_at"
+    )
+    .bind(user_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|achievement| achievement_from_str(achievement))
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(UserDigest {
+        user_id,
+        username: user.get("username"),
+        workspace_name: user.get("workspace_name"),
+        start,
+        end,
+        ratings,
+        rank: ranks.get("rank"),
+        previous_rank: ranks.get("previous_rank"),
+        achievements,
+        new_dishes: fetch_new_dishes(conn, workspace_id, (start, end)).await?,
+    })
+}
+
+async fn fetch_team_digest(conn: &mut PgConnection, workspace_id: i32, ending: chrono::NaiveDate) -> Result<TeamDigest, StatusCode> {
+    let (start, end) = digest_period(ending);
+
+    let totals = sqlx::query(
+        "SELECT w.name, COUNT(r.id) AS rating_count, COUNT(DISTINCT r.user_id) AS active_raters
+         FROM workspaces w LEFT JOIN ratings r ON r.workspace_id = w.id AND DATE(r.date) BETWEEN $2 AND $3
+         WHERE w.id = $1
+         GROUP BY w.id"
+    )
+    .bind(workspace_id)
+    .bind(start)
+    .bind(end)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let top_dishes = sqlx::query(
+        "SELECT d.id, d.nr, d.name, COUNT(r.id) AS rating_count, AVG(r.rating)::float8 AS average_rating
+         FROM ratings r JOIN dishes d ON d.id = r.dish_id
+         WHERE r.workspace_id = $1 AND DATE(r.date) BETWEEN $2 AND $3
+         GROUP BY d.id
+         ORDER BY average_rating DESC, rating_count DESC, d.nr
+         LIMIT $4"
+    )
+    .bind(workspace_id)
+    .bind(start)
+    .bind(end)
+    .bind(DIGEST_LIST_LIMIT)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|row| WeeklyDish {
+        dish_id: row.get("id"),
+        nr: row.get("nr"),
+        name: row.get("name"),
+        rating_count: row.get("rating_count"),
+        average_rating: row.get("average_rating"),
+    })
+    .collect();
+
+    let top_raters = sqlx::query(
+        "SELECT u.id, u.username, COUNT(r.id) AS rating_count
+         FROM ratings r JOIN users u ON u.id = r.user_id
+         WHERE r.workspace_id = $1 AND DATE(r.date) BETWEEN $2 AND $3
+         GROUP BY u.id
+         ORDER BY rating_count DESC, u.username
+         LIMIT $4"
+    )
+    .bind(workspace_id)
+    .bind(start)
+    .bind(end)
+    .bind(DIGEST_LIST_LIMIT)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|row| WeeklyRater {
+        user_id: row.get("id"),
+        username: row.get("username"),
+        rating_count: row.get("rating_count"),
+    })
+    .collect();
+
+    let achievements = sqlx::query(
+        "SELECT u.id, u.username, a.achievement
+         FROM user_achievements a JOIN users u ON u.id = a.user_id
+         WHERE u.workspace_id = $1 AND DATE(a.unlocked_at) BETWEEN $2 AND $3
+         ORDER BY a.unlocked_at"
+    )
+    .bind(workspace_id)
+    .bind(start)
+    .bind(end)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .iter()
+    .map(|row| Ok::<_, StatusCode>(UnlockedAchievement {
+        user_id: row.get("id"),
+        username: row.get("username"),
+        achievement: achievement_from_str(&row.get::<String, _>("achievement"))?,
+    }))
+    .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(TeamDigest {
+        workspace_name: totals.get("name"),
+        start,
+        end,
+        rating_count: totals.get("rating_count"),
+        active_raters: totals.get("active_raters"),
+        top_dishes,
+        top_raters,
+        achievements,
+        new_dishes: fetch_new_dishes(conn, workspace_id, (start, end)).await?,
+    })
+}
+
+/// Emails the team summary and everyone's weekly digest for the week ending
+/// on `ending`. Digests that went out before are skipped, so a run that
+/// failed halfway can simply be repeated.
+pub(crate) async fn send_digests(
+    pool: &PgPool,
+    mailer: &Mailer,
+    workspace_id: i32,
+    ending: chrono::NaiveDate,
+) -> Result<DigestRun, StatusCode> {
+    if !mailer.is_enabled() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // The team summary has no user
+    let team_email: Option<String> = sqlx::query_scalar("SELECT digest_email FROM workspaces WHERE id = $1")
+        .bind(workspace_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let users = sqlx::query(
+        "SELECT id, email FROM users WHERE workspace_id = $1 AND weekly_digest AND email IS NOT NULL ORDER BY id"
+    )
+    .bind(workspace_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let recipients = team_email.map(|email| (None, email)).into_iter()
+        .chain(users.iter().map(|row| (Some(row.get::<i32, _>("id")), row.get::<String, _>("email"))));
+
+    let mut run = DigestRun::default();
+    for (user_id, email) in recipients {
+        let already_sent: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sent_digests WHERE workspace_id = $1 AND user_id IS NOT DISTINCT FROM $2 AND period_end = $3)"
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(ending)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if already_sent {
+            run.skipped += 1;
+            continue;
+        }
+
+        let digest = match user_id {
+            Some(user_id) => fetch_user_digest(&mut conn, workspace_id, user_id, ending).await?.render(),
+            None => fetch_team_digest(&mut conn, workspace_id, ending).await?.render(),
+        }
+        .ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        if mailer.send(&email, &digest.subject, digest.text, digest.html).await.is_err() {
+            run.failed += 1;
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO sent_digests (workspace_id, user_id, email, period_end) VALUES ($1, $2, $3, $4)
+             ON CONFLICT DO NOTHING"
+        )
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(&email)
+        .bind(ending)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        run.sent += 1;
+    }
+
+    Ok(run)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/digest",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        DigestQuery
+    ),
+    responses(
+        (status = 200, description = "The user's weekly digest as it would be emailed", body = DigestEmail),
+        (status = 404, description = "User not found")
+    ),
+    tag = "digests"
+)]
+pub async fn get_user_digest(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+    Query(query): Query<DigestQuery>,
+) -> Result<Json<DigestEmail>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ending = digest_ending(&mut conn, query.ending).await?;
+    let digest = fetch_user_digest(&mut conn, workspace_id, id, ending).await?;
+
+    Ok(Json(digest.render().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/digests/team",
+    params(DigestQuery),
+    responses((status = 200, description = "The weekly team summary as it would be emailed", body = DigestEmail)),
+    tag = "digests"
+)]
+pub async fn get_team_digest(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<DigestQuery>,
+) -> Result<Json<DigestEmail>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ending = digest_ending(&mut conn, query.ending).await?;
+    let digest = fetch_team_digest(&mut conn, workspace_id, ending).await?;
+
+    Ok(Json(digest.render().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/digests/send",
+    params(DigestQuery),
+    responses(
+        (status = 200, description = "Digests emailed to the team address and everyone who opted in", body = DigestRun),
+        (status = 403, description = "Forbidden - only workspace admins can send digests"),
+        (status = 503, description = "Email isn't set up, see SMTP_HOST")
+    ),
+    tag = "digests"
+)]
+pub async fn send_weekly_digests(
+    State(pool): State<PgPool>,
+    Extension(mailer): Extension<Mailer>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Query(query): Query<DigestQuery>,
+) -> Result<Json<DigestRun>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ending = digest_ending(&mut conn, query.ending).await?;
+
+    Ok(Json(send_digests(&pool, &mailer, workspace_id, ending).await?))
+}
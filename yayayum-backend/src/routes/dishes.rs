@@ -1,6 +1,12 @@
-use axum::{routing::post, extract::{State, Path}, http::StatusCode, Json, Router};
-use sqlx::{PgPool, Row};
-use crate::models::{CreateDish, Dish};
+use std::collections::HashMap;
+use axum::{routing::post, extract::{State, Path, Query}, http::StatusCode, Json, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{CreateDish, Dish, DishQuery, EventKind, Ingredient};
+use crate::models::dish::{conflicting_allergens, conflicting_ingredients, diet_compatibility, MAX_SPICINESS};
+use crate::routes::feed::record_event;
+use crate::routes::ingredients::ingredient_from_row;
+use crate::routes::users::user_from_row;
+use crate::routes::workspaces::CurrentWorkspace;
 
 pub fn routes() -> Router<PgPool> {
     Router::new()
@@ -8,92 +14,259 @@ pub fn routes() -> Router<PgPool> {
         .route("/dishes/{id}", axum::routing::put(modify_dish).delete(remove_dish))
 }
 
+const DISH_COLUMNS: &str = "id, restaurant_id, nr, name, description, price_kr, dietary_restrictions, category, allergens, spiciness";
+
+// Enums are stored as JSON strings, parse them back before building the dish
+pub(crate) fn dish_from_row(row: &PgRow, ingredients: Vec<Ingredient>) -> Result<Dish, StatusCode> {
+    let dietary_restrictions: Vec<_> = serde_json::from_str(&row.get::<String, _>("dietary_restrictions"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let category = serde_json::from_str(&row.get::<String, _>("category"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens: Vec<_> = serde_json::from_str(&row.get::<String, _>("allergens"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Dish {
+        id: row.get("id"),
+        restaurant_id: row.get("restaurant_id"),
+        nr: row.get("nr"),
+        name: row.get("name"),
+        description: row.get("description"),
+        price_kr: row.get("price_kr"),
+        diet_compatibility: diet_compatibility(&dietary_restrictions, &ingredients, &allergens),
+        dietary_restrictions,
+        category,
+        ingredients,
+        allergens,
+        spiciness: row.get("spiciness"),
+        suitability: None,
+    })
+}
+
+/// Loads the ingredient lists for the given dishes, keyed by dish id.
+pub(crate) async fn fetch_dish_ingredients(
+    conn: &mut PgConnection,
+    dish_ids: &[i32],
+) -> Result<HashMap<i32, Vec<Ingredient>>, StatusCode> {
+    let rows = sqlx::query(
+        "SELECT di.dish_id, i.id, i.name, i.source, i.allergens
+         FROM dish_ingredients di JOIN ingredients i ON i.id = di.ingredient_id
+         WHERE di.dish_id = ANY($1)
+         ORDER BY i.name"
+    )
+    .bind(dish_ids)
+    .fetch_all(conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut ingredients: HashMap<i32, Vec<Ingredient>> = HashMap::new();
+    for row in rows {
+        ingredients.entry(row.get("dish_id")).or_default().push(ingredient_from_row(&row)?);
+    }
+    Ok(ingredients)
+}
+
+/// Checks the payload and returns the restaurant the dish belongs to along
+/// with the ingredients it refers to. Without a restaurant in the payload the
+/// dish stays at `current_restaurant_id`, or goes to the first restaurant.
+async fn validate_dish(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    payload: &CreateDish,
+    current_restaurant_id: Option<i32>,
+) -> Result<(i32, Vec<Ingredient>), StatusCode> {
+    if payload.spiciness < 0 || payload.spiciness > MAX_SPICINESS {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // The category has to be one of the restaurant's own
+    let restaurant = sqlx::query(
+        "SELECT id, categories FROM restaurants WHERE workspace_id = $1 AND ($2::int IS NULL OR id = $2) ORDER BY id LIMIT 1"
+    )
+    .bind(workspace_id)
+    .bind(payload.restaurant_id.or(current_restaurant_id))
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let categories: Vec<String> = serde_json::from_str(&restaurant.get::<String, _>("categories"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !categories.contains(&payload.category) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let rows = sqlx::query("SELECT id, name, source, allergens FROM ingredients WHERE id = ANY($1) AND workspace_id = $2")
+        .bind(&payload.ingredient_ids)
+        .bind(workspace_id)
+        .fetch_all(conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let ingredients = rows.iter().map(ingredient_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    // Every referenced ingredient has to exist in the workspace
+    if payload.ingredient_ids.iter().any(|id| !ingredients.iter().any(|i| i.id == *id)) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // A dish marked Vegan (or Vegetarian) can't list ingredients or allergens that say otherwise
+    if !conflicting_ingredients(&payload.dietary_restrictions, &ingredients).is_empty()
+        || !conflicting_allergens(&payload.dietary_restrictions, &payload.allergens).is_empty()
+    {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    Ok((restaurant.get("id"), ingredients))
+}
+
+async fn replace_dish_ingredients(conn: &mut PgConnection, dish_id: i32, ingredient_ids: &[i32]) -> Result<(), StatusCode> {
+    sqlx::query("DELETE FROM dish_ingredients WHERE dish_id = $1")
+        .bind(dish_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query(
+        "INSERT INTO dish_ingredients (dish_id, ingredient_id)
+         SELECT $1, UNNEST($2::int[]) ON CONFLICT DO NOTHING"
+    )
+    .bind(dish_id)
+    .bind(ingredient_ids)
+    .execute(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(())
+}
+
 #[utoipa::path(
     post,
     path = "/dishes",
     request_body = CreateDish,
-    responses((status = 201, description = "Dish created", body = Dish)),
+    responses(
+        (status = 201, description = "Dish created", body = Dish),
+        (status = 400, description = "Bad request - unknown restaurant, category or ingredient, or spiciness out of range"),
+        (status = 422, description = "Ingredients contradict the declared dietary restrictions")
+    ),
     tag = "dishes"
 )]
 pub async fn create_dish(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Json(payload): Json<CreateDish>,
 ) -> Result<(StatusCode, Json<Dish>), StatusCode> {
-    // Convert enums to strings for SQLite storage
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let (restaurant_id, mut ingredients) = validate_dish(&mut tx, workspace_id, &payload, None).await?;
+
+    // Convert enums to strings for storage
     let dietary_restrictions_json = serde_json::to_string(&payload.dietary_restrictions)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let category_str = serde_json::to_string(&payload.category)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens_json = serde_json::to_string(&payload.allergens)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let row = sqlx::query(
-        "INSERT INTO dishes (nr, name, description, price_kr, dietary_restrictions, category) 
-         VALUES ($1, $2, $3, $4, $5, $6) 
-         RETURNING id, nr, name, description, price_kr, dietary_restrictions, category"
-    )
+    let row = sqlx::query(&format!(
+        "INSERT INTO dishes (nr, name, description, price_kr, dietary_restrictions, category, allergens, spiciness, restaurant_id, workspace_id)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         RETURNING {DISH_COLUMNS}"
+    ))
     .bind(payload.nr)
     .bind(&payload.name)
     .bind(&payload.description)
     .bind(payload.price_kr)
     .bind(&dietary_restrictions_json)
     .bind(&category_str)
-    .fetch_one(&pool)
+    .bind(&allergens_json)
+    .bind(payload.spiciness)
+    .bind(restaurant_id)
+    .bind(workspace_id)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    // Parse back the JSON strings to enums
-    let dietary_restrictions = serde_json::from_str(&row.get::<String, _>("dietary_restrictions"))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let category = serde_json::from_str(&row.get::<String, _>("category"))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    replace_dish_ingredients(&mut tx, row.get("id"), &payload.ingredient_ids).await?;
 
-    let dish = Dish {
-        id: row.get("id"),
-        nr: row.get("nr"),
-        name: row.get("name"),
-        description: row.get("description"),
-        price_kr: row.get("price_kr"),
-        dietary_restrictions,
-        category,
-    };
+    ingredients.sort_by(|a, b| a.name.cmp(&b.name));
+    let dish = dish_from_row(&row, ingredients)?;
+
+    let data = serde_json::json!({
+        "restaurant_id": dish.restaurant_id,
+        "nr": dish.nr,
+        "name": dish.name,
+    });
+    record_event(&mut tx, workspace_id, EventKind::DishCreated, None, Some(dish.id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok((StatusCode::CREATED, Json(dish)))
 }
 
+/// Lists dishes, optionally checked against a user's dietary preferences.
+pub(crate) async fn list_dishes(pool: &PgPool, workspace_id: i32, query: &DishQuery) -> Result<Vec<Dish>, StatusCode> {
+    let user = match query.for_user {
+        Some(user_id) => {
+            let row = sqlx::query(
+                "SELECT id, username, dietary_restrictions, disliked_ingredients, chat_user_id, email, weekly_digest, rating_reminders, reminder_channel, is_admin
+                 FROM users WHERE id = $1 AND workspace_id = $2"
+            )
+            .bind(user_id)
+            .bind(workspace_id)
+            .fetch_one(pool)
+            .await
+            .map_err(|err| match err {
+                sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            })?;
+            Some(user_from_row(&row)?)
+        }
+        None => None,
+    };
+
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rows = sqlx::query(&format!(
+        "SELECT {DISH_COLUMNS} FROM dishes WHERE workspace_id = $1 AND ($2::int IS NULL OR restaurant_id = $2)"
+    ))
+    .bind(workspace_id)
+    .bind(query.restaurant_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let dish_ids: Vec<i32> = rows.iter().map(|row| row.get("id")).collect();
+    let mut ingredients = fetch_dish_ingredients(&mut conn, &dish_ids).await?;
+
+    let mut dishes = Vec::new();
+    for row in rows {
+        let dish_ingredients = ingredients.remove(&row.get::<i32, _>("id")).unwrap_or_default();
+        dishes.push(dish_from_row(&row, dish_ingredients)?);
+    }
+
+    if let Some(user) = user {
+        for dish in &mut dishes {
+            dish.suitability = Some(dish.suitability_for(&user));
+        }
+        if query.hide_unsuitable {
+            dishes.retain(|dish| dish.suitability.as_ref().is_some_and(|s| s.suitable));
+        }
+    }
+
+    Ok(dishes)
+}
+
 #[utoipa::path(
     get,
     path = "/dishes",
-    responses((status = 200, description = "List dishes", body = [Dish])),
+    params(DishQuery),
+    responses(
+        (status = 200, description = "List dishes", body = [Dish]),
+        (status = 404, description = "User given in for_user not found")
+    ),
     tag = "dishes"
 )]
 pub async fn get_dishes(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<DishQuery>,
 ) -> Result<Json<Vec<Dish>>, StatusCode> {
-    let rows = sqlx::query(
-        "SELECT id, nr, name, description, price_kr, dietary_restrictions, category FROM dishes"
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-    let mut dishes = Vec::new();
-    for row in rows {
-        let dietary_restrictions = serde_json::from_str(&row.get::<String, _>("dietary_restrictions"))
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        let category = serde_json::from_str(&row.get::<String, _>("category"))
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-
-        dishes.push(Dish {
-            id: row.get("id"),
-            nr: row.get("nr"),
-            name: row.get("name"),
-            description: row.get("description"),
-            price_kr: row.get("price_kr"),
-            dietary_restrictions,
-            category,
-        });
-    }
-
-    Ok(Json(dishes))
+    Ok(Json(list_dishes(&pool, workspace_id, &query).await?))
 }
 
 #[utoipa::path(
@@ -105,55 +278,72 @@ pub async fn get_dishes(
     ),
     responses(
         (status = 200, description = "Dish updated successfully", body = Dish),
-        (status = 404, description = "Dish not found")
+        (status = 400, description = "Bad request - unknown restaurant, category or ingredient, or spiciness out of range"),
+        (status = 404, description = "Dish not found"),
+        (status = 422, description = "Ingredients contradict the declared dietary restrictions")
     ),
     tag = "dishes"
 )]
 pub async fn modify_dish(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Path(id): Path<i32>,
     Json(payload): Json<CreateDish>,
 ) -> Result<Json<Dish>, StatusCode> {
-    // Convert enums to strings for SQLite storage
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let current_restaurant_id: i32 = sqlx::query_scalar("SELECT restaurant_id FROM dishes WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let (restaurant_id, mut ingredients) = validate_dish(&mut tx, workspace_id, &payload, Some(current_restaurant_id)).await?;
+
+    // Convert enums to strings for storage
     let dietary_restrictions_json = serde_json::to_string(&payload.dietary_restrictions)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     let category_str = serde_json::to_string(&payload.category)
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens_json = serde_json::to_string(&payload.allergens)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let row = sqlx::query(
-        "UPDATE dishes SET nr = $1, name = $2, description = $3, price_kr = $4, dietary_restrictions = $5, category = $6 
-         WHERE id = $7 
-         RETURNING id, nr, name, description, price_kr, dietary_restrictions, category"
-    )
+    let row = sqlx::query(&format!(
+        "UPDATE dishes SET nr = $1, name = $2, description = $3, price_kr = $4, dietary_restrictions = $5, category = $6,
+            allergens = $7, spiciness = $8, restaurant_id = $9
+         WHERE id = $10 AND workspace_id = $11
+         RETURNING {DISH_COLUMNS}"
+    ))
     .bind(payload.nr)
     .bind(&payload.name)
     .bind(&payload.description)
     .bind(payload.price_kr)
     .bind(&dietary_restrictions_json)
     .bind(&category_str)
+    .bind(&allergens_json)
+    .bind(payload.spiciness)
+    .bind(restaurant_id)
     .bind(id)
-    .fetch_one(&pool)
+    .bind(workspace_id)
+    .fetch_one(&mut *tx)
     .await
     .map_err(|err| match err {
         sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
-    // Parse back the JSON strings to enums
-    let dietary_restrictions = serde_json::from_str(&row.get::<String, _>("dietary_restrictions"))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    let category = serde_json::from_str(&row.get::<String, _>("category"))
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    replace_dish_ingredients(&mut tx, id, &payload.ingredient_ids).await?;
 
-    let dish = Dish {
-        id: row.get("id"),
-        nr: row.get("nr"),
-        name: row.get("name"),
-        description: row.get("description"),
-        price_kr: row.get("price_kr"),
-        dietary_restrictions,
-        category,
-    };
+    ingredients.sort_by(|a, b| a.name.cmp(&b.name));
+    let dish = dish_from_row(&row, ingredients)?;
+
+    let data = serde_json::json!({
+        "restaurant_id": dish.restaurant_id,
+        "nr": dish.nr,
+        "name": dish.name,
+    });
+    record_event(&mut tx, workspace_id, EventKind::DishUpdated, None, Some(dish.id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(dish))
 }
@@ -172,17 +362,25 @@ pub async fn modify_dish(
 )]
 pub async fn remove_dish(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, StatusCode> {
-    let result = sqlx::query("DELETE FROM dishes WHERE id = $1")
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let row = sqlx::query("DELETE FROM dishes WHERE id = $1 AND workspace_id = $2 RETURNING restaurant_id, nr, name")
         .bind(id)
-        .execute(&pool)
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
 
-    if result.rows_affected() == 0 {
-        return Err(StatusCode::NOT_FOUND);
-    }
+    let data = serde_json::json!({
+        "restaurant_id": row.get::<i32, _>("restaurant_id"),
+        "nr": row.get::<i32, _>("nr"),
+        "name": row.get::<String, _>("name"),
+    });
+    record_event(&mut tx, workspace_id, EventKind::DishDeleted, None, Some(id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(StatusCode::NO_CONTENT)
-}
\ No newline at end of file
+}
@@ -0,0 +1,186 @@
+use std::convert::Infallible;
+use axum::{extract::{Query, State}, http::StatusCode, response::{sse, Json, Sse}, routing::get, Extension, Router};
+use futures_util::{stream, Stream};
+use serde::Deserialize;
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use tokio::sync::broadcast::{self, error::RecvError};
+use crate::models::{Event, EventKind, FeedPage, FeedQuery};
+use crate::models::event::{DEFAULT_FEED_LIMIT, MAX_FEED_LIMIT};
+use crate::routes::workspaces::CurrentWorkspace;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/feed", get(get_feed))
+        .route("/feed/stream", get(stream_feed))
+}
+
+/// Postgres channel announcing new events to every instance
+pub(crate) const EVENTS_CHANNEL: &str = "yayayum_events";
+
+/// What goes out on `EVENTS_CHANNEL`. Notifications are capped at 8000 bytes,
+/// so listeners look the event itself up.
+#[derive(Deserialize)]
+pub(crate) struct EventNotification {
+    pub id: i32,
+    pub workspace_id: i32,
+}
+
+/// Events a live stream falls behind by before it skips ahead
+const LIVE_EVENT_BACKLOG: usize = 256;
+
+/// A new event for the live streams, serialized once for all of them.
+#[derive(Clone)]
+pub struct LiveEvent {
+    pub workspace_id: i32,
+    pub id: i32,
+    pub kind: String,
+    pub json: String,
+}
+
+/// Hands new events from the instance's one listener to every open stream,
+/// see `workers::feed::relay_events`.
+#[derive(Clone)]
+pub struct FeedBroadcast(pub broadcast::Sender<LiveEvent>);
+
+impl FeedBroadcast {
+    pub fn new() -> FeedBroadcast {
+        FeedBroadcast(broadcast::channel(LIVE_EVENT_BACKLOG).0)
+    }
+}
+
+pub(crate) const EVENT_SELECT: &str = "SELECT e.id, e.kind, e.user_id, u.username, e.subject_id, e.data, e.created_at
+    FROM events e LEFT JOIN users u ON u.id = e.user_id";
+
+/// Adds an event to the workspace's feed, queues it for the webhooks that
+/// subscribe to it and notifies live listeners. Handlers
+/// call this in the same transaction as the change it describes, so the
+/// notification only goes out once it commits.
+pub(crate) async fn record_event(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    kind: EventKind,
+    user_id: Option<i32>,
+    subject_id: Option<i32>,
+    data: serde_json::Value,
+) -> Result<(), StatusCode> {
+    let kind_str = serde_json::to_string(&kind).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    sqlx::query(
+        "WITH inserted AS (
+            INSERT INTO events (workspace_id, kind, user_id, subject_id, data) VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+         ), queued AS (
+            INSERT INTO webhook_deliveries (webhook_id, event_id)
+            SELECT w.id, inserted.id FROM webhooks w, inserted
+            WHERE w.workspace_id = $1 AND w.active AND w.event_types::jsonb @> jsonb_build_array($2::jsonb)
+         )
+         SELECT pg_notify($6, json_build_object('id', id, 'workspace_id', $1)::text) FROM inserted"
+    )
+    .bind(workspace_id)
+    .bind(&kind_str)
+    .bind(user_id)
+    .bind(subject_id)
+    .bind(data.to_string())
+    .bind(EVENTS_CHANNEL)
+    .execute(conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(())
+}
+
+// The kind and data are stored as JSON strings
+pub(crate) fn event_from_row(row: &PgRow) -> Result<Event, StatusCode> {
+    let kind = serde_json::from_str(&row.get::<String, _>("kind"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let data = serde_json::from_str(&row.get::<String, _>("data"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Event {
+        id: row.get("id"),
+        kind,
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        subject_id: row.get("subject_id"),
+        data,
+        created_at: row.get("created_at"),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/feed",
+    params(FeedQuery),
+    responses((status = 200, description = "What happened recently, newest first, a page at a time", body = FeedPage)),
+    tag = "feed"
+)]
+pub async fn get_feed(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<FeedQuery>,
+) -> Result<Json<FeedPage>, StatusCode> {
+    let limit = query.limit.unwrap_or(DEFAULT_FEED_LIMIT).clamp(1, MAX_FEED_LIMIT);
+    let kind_str = query.kind.map(|kind| serde_json::to_string(&kind))
+        .transpose()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let webhook_only = EventKind::WEBHOOK_ONLY.iter()
+        .map(serde_json::to_string)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Paging by ID rather than offset so new events don't shift the pages
+    let rows = sqlx::query(&format!(
+        "{EVENT_SELECT}
+         WHERE e.workspace_id = $1 AND ($2::int IS NULL OR e.id < $2) AND ($3::text IS NULL OR e.kind = $3)
+           AND ($5::int IS NULL OR e.user_id IN (SELECT followee_id FROM user_follows WHERE follower_id = $5))
+           AND e.kind <> ALL($6)
+         ORDER BY e.id DESC
+         LIMIT $4"
+    ))
+    .bind(workspace_id)
+    .bind(query.before)
+    .bind(&kind_str)
+    .bind(limit + 1)
+    .bind(query.followed_by)
+    .bind(&webhook_only)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut events = rows.iter().map(event_from_row).collect::<Result<Vec<_>, _>>()?;
+    let next_before = if events.len() as i64 > limit {
+        events.truncate(limit as usize);
+        events.last().map(|event| event.id)
+    } else {
+        None
+    };
+
+    Ok(Json(FeedPage { events, next_before }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/feed/stream",
+    responses((status = 200, description = "Server-sent events as they happen, named after the event kind with the event as JSON data", content_type = "text/event-stream", body = Event)),
+    tag = "feed"
+)]
+pub async fn stream_feed(
+    Extension(feed): Extension<FeedBroadcast>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Sse<impl Stream<Item = Result<sse::Event, Infallible>>> {
+    let events = stream::unfold(feed.0.subscribe(), move |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) if event.workspace_id == workspace_id => {
+                    let sse_event = sse::Event::default().id(event.id.to_string()).event(event.kind).data(event.json);
+                    return Some((Ok(sse_event), receiver));
+                }
+                // Other workspaces' events, or ones missed by falling behind
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+
+    Sse::new(events).keep_alive(sse::KeepAlive::default())
+}
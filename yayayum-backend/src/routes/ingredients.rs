@@ -0,0 +1,202 @@
+use axum::{routing::post, extract::{State, Path}, http::StatusCode, Json, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{CreateIngredient, Ingredient};
+use crate::models::dish::conflicting_ingredients;
+use crate::routes::dishes::fetch_dish_ingredients;
+use crate::routes::violates;
+use crate::routes::workspaces::CurrentWorkspace;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/ingredients", post(create_ingredient).get(get_ingredients))
+        .route("/ingredients/{id}", axum::routing::put(modify_ingredient).delete(remove_ingredient))
+}
+
+// Source and allergens are stored as JSON strings
+pub(crate) fn ingredient_from_row(row: &PgRow) -> Result<Ingredient, StatusCode> {
+    let source = serde_json::from_str(&row.get::<String, _>("source"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens = serde_json::from_str(&row.get::<String, _>("allergens"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Ingredient {
+        id: row.get("id"),
+        name: row.get("name"),
+        source,
+        allergens,
+    })
+}
+
+fn unique_name_violation(err: &sqlx::Error) -> bool {
+    violates(err, "idx_ingredients_workspace_name_unique")
+}
+
+#[utoipa::path(
+    post,
+    path = "/ingredients",
+    request_body = CreateIngredient,
+    responses(
+        (status = 201, description = "Ingredient created", body = Ingredient),
+        (status = 409, description = "Conflict - an ingredient with that name already exists")
+    ),
+    tag = "ingredients"
+)]
+pub async fn create_ingredient(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Json(payload): Json<CreateIngredient>,
+) -> Result<(StatusCode, Json<Ingredient>), StatusCode> {
+    let source_str = serde_json::to_string(&payload.source)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens_json = serde_json::to_string(&payload.allergens)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(
+        "INSERT INTO ingredients (name, source, allergens, workspace_id) VALUES ($1, $2, $3, $4)
+         RETURNING id, name, source, allergens"
+    )
+    .bind(payload.name.trim())
+    .bind(&source_str)
+    .bind(&allergens_json)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| {
+        if unique_name_violation(&err) {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Ok((StatusCode::CREATED, Json(ingredient_from_row(&row)?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ingredients",
+    responses((status = 200, description = "List ingredients", body = [Ingredient])),
+    tag = "ingredients"
+)]
+pub async fn get_ingredients(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Ingredient>>, StatusCode> {
+    let rows = sqlx::query("SELECT id, name, source, allergens FROM ingredients WHERE workspace_id = $1 ORDER BY name")
+        .bind(workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let ingredients = rows.iter().map(ingredient_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(ingredients))
+}
+
+#[utoipa::path(
+    put,
+    path = "/ingredients/{id}",
+    request_body = CreateIngredient,
+    params(
+        ("id" = i32, Path, description = "Ingredient ID to modify")
+    ),
+    responses(
+        (status = 200, description = "Ingredient updated successfully", body = Ingredient),
+        (status = 404, description = "Ingredient not found"),
+        (status = 409, description = "Conflict - an ingredient with that name already exists"),
+        (status = 422, description = "Unprocessable - a dish marked Vegan or Vegetarian uses the ingredient")
+    ),
+    tag = "ingredients"
+)]
+pub async fn modify_ingredient(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateIngredient>,
+) -> Result<Json<Ingredient>, StatusCode> {
+    let source_str = serde_json::to_string(&payload.source)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let allergens_json = serde_json::to_string(&payload.allergens)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let row = sqlx::query(
+        "UPDATE ingredients SET name = $1, source = $2, allergens = $3 WHERE id = $4 AND workspace_id = $5
+         RETURNING id, name, source, allergens"
+    )
+    .bind(payload.name.trim())
+    .bind(&source_str)
+    .bind(&allergens_json)
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        ref err if unique_name_violation(err) => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    // Dishes using the ingredient still have to live up to their declared diets
+    let dishes = sqlx::query(
+        "SELECT d.id, d.dietary_restrictions FROM dishes d
+         JOIN dish_ingredients di ON di.dish_id = d.id
+         WHERE di.ingredient_id = $1"
+    )
+    .bind(id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let dish_ids: Vec<i32> = dishes.iter().map(|row| row.get("id")).collect();
+    let mut dish_ingredients = fetch_dish_ingredients(&mut tx, &dish_ids).await?;
+    for dish in &dishes {
+        let declared: Vec<_> = serde_json::from_str(&dish.get::<String, _>("dietary_restrictions"))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let ingredients = dish_ingredients.remove(&dish.get::<i32, _>("id")).unwrap_or_default();
+        if !conflicting_ingredients(&declared, &ingredients).is_empty() {
+            return Err(StatusCode::UNPROCESSABLE_ENTITY);
+        }
+    }
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    Ok(Json(ingredient_from_row(&row)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/ingredients/{id}",
+    params(
+        ("id" = i32, Path, description = "Ingredient ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Ingredient deleted successfully"),
+        (status = 404, description = "Ingredient not found"),
+        (status = 409, description = "Conflict - ingredient is still used by a dish")
+    ),
+    tag = "ingredients"
+)]
+pub async fn remove_ingredient(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query("DELETE FROM ingredients WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&pool)
+        .await
+        .map_err(|err| {
+            // dish_ingredients restricts deleting ingredients that are in use
+            if violates(&err, "dish_ingredients_ingredient_id_fkey") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
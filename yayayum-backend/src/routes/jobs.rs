@@ -0,0 +1,230 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post, put}, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{Job, JobKind, JobRun, JobRunQuery, ModifyJob};
+use crate::models::job::{next_run, DEFAULT_JOB_RUN_LIMIT};
+use crate::routes::enum_json;
+use crate::routes::workspaces::ServerAdmin;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/admin/jobs", get(get_jobs))
+        .route("/admin/jobs/{name}", put(modify_job))
+        .route("/admin/jobs/{name}/run", post(run_job_now))
+        .route("/admin/jobs/{name}/runs", get(get_job_runs))
+}
+
+// The most recent finished run gives the job's last status
+const JOB_SELECT: &str = "SELECT j.name, j.schedule, j.enabled, j.next_run_at, j.last_run_at, j.attempts,
+        COALESCE(j.locked_until > $1, FALSE) AS running, r.status AS last_status
+    FROM jobs j
+    LEFT JOIN LATERAL (
+        SELECT status FROM job_runs WHERE job_name = j.name AND finished_at IS NOT NULL ORDER BY id DESC LIMIT 1
+    ) r ON TRUE";
+
+const JOB_RUN_COLUMNS: &str = "id, job_name, attempt, status, output, started_at, finished_at";
+
+// Job names and statuses are stored as JSON, like other enums
+fn job_from_row(row: &PgRow) -> Result<Job, StatusCode> {
+    let name = serde_json::from_str(&row.get::<String, _>("name"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let last_status = row.get::<Option<String>, _>("last_status")
+        .map(|status| serde_json::from_str(&status))
+        .transpose()
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Job {
+        name,
+        schedule: row.get("schedule"),
+        enabled: row.get("enabled"),
+        next_run_at: row.get("next_run_at"),
+        last_run_at: row.get("last_run_at"),
+        last_status,
+        attempts: row.get("attempts"),
+        running: row.get("running"),
+    })
+}
+
+fn job_run_from_row(row: &PgRow) -> Result<JobRun, StatusCode> {
+    let job = serde_json::from_str(&row.get::<String, _>("job_name"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let status = serde_json::from_str(&row.get::<String, _>("status"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(JobRun {
+        id: row.get("id"),
+        job,
+        attempt: row.get("attempt"),
+        status,
+        output: row.get("output"),
+        started_at: row.get("started_at"),
+        finished_at: row.get("finished_at"),
+    })
+}
+
+/// The stored name of the job called `name` in the URL, like `weekly_digests`.
+fn job_name(name: String) -> Result<String, StatusCode> {
+    let kind: JobKind = serde_json::from_value(serde_json::Value::String(name))
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    enum_json(&kind)
+}
+
+fn now() -> chrono::NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+async fn fetch_job(pool: &PgPool, name: &str) -> Result<Job, StatusCode> {
+    let row = sqlx::query(&format!("{JOB_SELECT} WHERE j.name = $2"))
+        .bind(now())
+        .bind(name)
+        .fetch_optional(pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    job_from_row(&row)
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs",
+    responses(
+        (status = 200, description = "List scheduled jobs", body = [Job]),
+        (status = 401, description = "Unauthorized - missing or wrong admin token")
+    ),
+    security(("admin" = [])),
+    tag = "jobs"
+)]
+pub async fn get_jobs(
+    State(pool): State<PgPool>,
+    _admin: ServerAdmin,
+) -> Result<Json<Vec<Job>>, StatusCode> {
+    let rows = sqlx::query(&format!("{JOB_SELECT} ORDER BY j.name"))
+        .bind(now())
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let jobs = rows.iter().map(job_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(jobs))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/jobs/{name}",
+    request_body = ModifyJob,
+    params(
+        ("name" = String, Path, description = "Job name, like weekly_digests")
+    ),
+    responses(
+        (status = 200, description = "Job rescheduled from now", body = Job),
+        (status = 400, description = "Bad request - not a cron expression with seconds"),
+        (status = 401, description = "Unauthorized - missing or wrong admin token"),
+        (status = 404, description = "Job not found")
+    ),
+    security(("admin" = [])),
+    tag = "jobs"
+)]
+pub async fn modify_job(
+    State(pool): State<PgPool>,
+    _admin: ServerAdmin,
+    Path(name): Path<String>,
+    Json(payload): Json<ModifyJob>,
+) -> Result<Json<Job>, StatusCode> {
+    let name = job_name(name)?;
+    let next_run_at = next_run(&payload.schedule, now()).ok_or(StatusCode::BAD_REQUEST)?;
+
+    let result = sqlx::query("UPDATE jobs SET schedule = $1, enabled = $2, next_run_at = $3, attempts = 0 WHERE name = $4")
+        .bind(&payload.schedule)
+        .bind(payload.enabled)
+        .bind(next_run_at)
+        .bind(&name)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(fetch_job(&pool, &name).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/jobs/{name}/run",
+    params(
+        ("name" = String, Path, description = "Job name, like weekly_digests")
+    ),
+    responses(
+        (status = 200, description = "Job due now, the scheduler starts it within a minute", body = Job),
+        (status = 401, description = "Unauthorized - missing or wrong admin token"),
+        (status = 404, description = "Job not found"),
+        (status = 409, description = "Conflict - the job is running or disabled")
+    ),
+    security(("admin" = [])),
+    tag = "jobs"
+)]
+pub async fn run_job_now(
+    State(pool): State<PgPool>,
+    _admin: ServerAdmin,
+    Path(name): Path<String>,
+) -> Result<Json<Job>, StatusCode> {
+    let name = job_name(name)?;
+    let job = fetch_job(&pool, &name).await?;
+    if job.running || !job.enabled {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    // The schedule takes over again after this run
+    sqlx::query("UPDATE jobs SET next_run_at = $1, attempts = 0 WHERE name = $2")
+        .bind(now())
+        .bind(&name)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_job(&pool, &name).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/jobs/{name}/runs",
+    params(
+        ("name" = String, Path, description = "Job name, like weekly_digests"),
+        JobRunQuery
+    ),
+    responses(
+        (status = 200, description = "Run history, newest first", body = [JobRun]),
+        (status = 401, description = "Unauthorized - missing or wrong admin token"),
+        (status = 404, description = "Job not found")
+    ),
+    security(("admin" = [])),
+    tag = "jobs"
+)]
+pub async fn get_job_runs(
+    State(pool): State<PgPool>,
+    _admin: ServerAdmin,
+    Path(name): Path<String>,
+    Query(query): Query<JobRunQuery>,
+) -> Result<Json<Vec<JobRun>>, StatusCode> {
+    let name = job_name(name)?;
+    fetch_job(&pool, &name).await?;
+    let status = query.status.as_ref().map(enum_json).transpose()?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT {JOB_RUN_COLUMNS} FROM job_runs
+         WHERE job_name = $1 AND ($2::TEXT IS NULL OR status = $2)
+         ORDER BY id DESC LIMIT $3"
+    ))
+    .bind(&name)
+    .bind(status)
+    .bind(query.limit.unwrap_or(DEFAULT_JOB_RUN_LIMIT).max(0))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let runs = rows.iter().map(job_run_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(runs))
+}
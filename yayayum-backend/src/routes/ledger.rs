@@ -0,0 +1,276 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{delete, get, post}, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{Balance, CreatePayment, Debt, Payment, SettleDebt};
+use crate::models::ledger::{balances, net_debts, settle_up};
+use crate::routes::orders::fetch_orders;
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/ledger", get(get_ledger))
+        .route("/ledger/balances", get(get_balances))
+        .route("/ledger/settle-up", get(get_settle_up))
+        .route("/ledger/settle", post(settle_debt))
+        .route("/payments", post(create_payment).get(get_payments))
+        .route("/payments/{id}", delete(remove_payment))
+}
+
+const PAYMENT_COLUMNS: &str = "id, from_user_id, to_user_id, amount_kr, order_id, note, created_at";
+
+fn payment_from_row(row: &PgRow) -> Payment {
+    Payment {
+        id: row.get("id"),
+        from_user_id: row.get("from_user_id"),
+        to_user_id: row.get("to_user_id"),
+        amount_kr: row.get("amount_kr"),
+        order_id: row.get("order_id"),
+        note: row.get("note"),
+        created_at: row.get("created_at"),
+    }
+}
+
+async fn fetch_payments(conn: &mut PgConnection, workspace_id: i32) -> Result<Vec<Payment>, StatusCode> {
+    let rows = sqlx::query(&format!("SELECT {PAYMENT_COLUMNS} FROM payments WHERE workspace_id = $1 ORDER BY created_at DESC"))
+        .bind(workspace_id)
+        .fetch_all(conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(rows.iter().map(payment_from_row).collect())
+}
+
+/// Outstanding debts across all orders after payments.
+async fn fetch_debts(conn: &mut PgConnection, workspace_id: i32) -> Result<Vec<Debt>, StatusCode> {
+    let orders = fetch_orders(conn, workspace_id, None).await?;
+    let payments = fetch_payments(conn, workspace_id).await?;
+
+    Ok(net_debts(&orders, &payments))
+}
+
+/// Only whoever gets the money, or a workspace admin, can say it was paid.
+fn can_confirm_payment(current_user: CurrentUser, to_user_id: i32) -> bool {
+    to_user_id == current_user.id || current_user.is_admin
+}
+
+async fn insert_payment(conn: &mut PgConnection, workspace_id: i32, payload: &CreatePayment) -> Result<Payment, StatusCode> {
+    if payload.amount_kr <= 0 || payload.from_user_id == payload.to_user_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Both users and the order have to be from the caller's workspace
+    let row = sqlx::query(&format!(
+        "INSERT INTO payments (from_user_id, to_user_id, amount_kr, order_id, note, workspace_id)
+         SELECT $1, $2, $3, $4, $5, $6
+         WHERE (SELECT COUNT(*) FROM users WHERE id IN ($1, $2) AND workspace_id = $6) = 2
+           AND ($4::int IS NULL OR EXISTS (SELECT 1 FROM orders WHERE id = $4 AND workspace_id = $6))
+         RETURNING {PAYMENT_COLUMNS}"
+    ))
+    .bind(payload.from_user_id)
+    .bind(payload.to_user_id)
+    .bind(payload.amount_kr)
+    .bind(payload.order_id)
+    .bind(&payload.note)
+    .bind(workspace_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    Ok(payment_from_row(&row))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger",
+    responses((status = 200, description = "Who owes whom after payments, netted per pair of users", body = [Debt])),
+    tag = "ledger"
+)]
+pub async fn get_ledger(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Debt>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_debts(&mut conn, workspace_id).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger/balances",
+    responses((status = 200, description = "Net balance per user, positive when owed money", body = [Balance])),
+    tag = "ledger"
+)]
+pub async fn get_balances(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Balance>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let debts = fetch_debts(&mut conn, workspace_id).await?;
+
+    Ok(Json(balances(&debts)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/ledger/settle-up",
+    responses((status = 200, description = "Suggested transfers that clear every balance", body = [Debt])),
+    tag = "ledger"
+)]
+pub async fn get_settle_up(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Debt>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let debts = fetch_debts(&mut conn, workspace_id).await?;
+
+    Ok(Json(settle_up(&balances(&debts))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/ledger/settle",
+    request_body = SettleDebt,
+    responses(
+        (status = 201, description = "Debt marked as settled by recording a payment", body = Payment),
+        (status = 403, description = "Forbidden - only the person who is owed or a workspace admin can settle a debt"),
+        (status = 404, description = "Order not found, or the user has no share in it"),
+        (status = 409, description = "Conflict - nothing is owed")
+    ),
+    tag = "ledger"
+)]
+pub async fn settle_debt(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Json(payload): Json<SettleDebt>,
+) -> Result<(StatusCode, Json<Payment>), StatusCode> {
+    if !can_confirm_payment(current_user, payload.to_user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let workspace_id = current_user.workspace_id;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Serialise settlements so the same debt can't be paid twice
+    sqlx::query("LOCK TABLE payments IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let amount_kr = match payload.order_id {
+        Some(order_id) => {
+            let order = fetch_orders(&mut tx, workspace_id, Some(&[order_id])).await?.pop().ok_or(StatusCode::NOT_FOUND)?;
+            if order.paid_by != payload.to_user_id {
+                return Err(StatusCode::NOT_FOUND);
+            }
+            let share = order.split().into_iter()
+                .find(|share| share.user_id == payload.from_user_id)
+                .ok_or(StatusCode::NOT_FOUND)?;
+
+            let paid: i64 = sqlx::query_scalar(
+                "SELECT COALESCE(SUM(amount_kr), 0) FROM payments WHERE order_id = $1 AND from_user_id = $2 AND to_user_id = $3"
+            )
+            .bind(order_id)
+            .bind(payload.from_user_id)
+            .bind(payload.to_user_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+            share.total_kr - paid as i32
+        }
+        None => fetch_debts(&mut tx, workspace_id).await?.into_iter()
+            .find(|debt| debt.from_user_id == payload.from_user_id && debt.to_user_id == payload.to_user_id)
+            .map_or(0, |debt| debt.amount_kr),
+    };
+
+    if amount_kr <= 0 {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let payment = insert_payment(&mut tx, workspace_id, &CreatePayment {
+        from_user_id: payload.from_user_id,
+        to_user_id: payload.to_user_id,
+        amount_kr,
+        order_id: payload.order_id,
+        note: Some("Settled".to_string()),
+    }).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(payment)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/payments",
+    request_body = CreatePayment,
+    responses(
+        (status = 201, description = "Payment recorded", body = Payment),
+        (status = 400, description = "Bad request - unknown user or order, non-positive amount or paying yourself"),
+        (status = 403, description = "Forbidden - only the person who was paid or a workspace admin can record a payment")
+    ),
+    tag = "ledger"
+)]
+pub async fn create_payment(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Json(payload): Json<CreatePayment>,
+) -> Result<(StatusCode, Json<Payment>), StatusCode> {
+    if !can_confirm_payment(current_user, payload.to_user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(insert_payment(&mut conn, current_user.workspace_id, &payload).await?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/payments",
+    responses((status = 200, description = "List payments, newest first", body = [Payment])),
+    tag = "ledger"
+)]
+pub async fn get_payments(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Payment>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_payments(&mut conn, workspace_id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/payments/{id}",
+    params(
+        ("id" = i32, Path, description = "Payment ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Payment deleted"),
+        (status = 403, description = "Forbidden - only the person who was paid or a workspace admin can remove a payment"),
+        (status = 404, description = "Payment not found")
+    ),
+    tag = "ledger"
+)]
+pub async fn remove_payment(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let to_user_id: i32 = sqlx::query_scalar("SELECT to_user_id FROM payments WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(current_user.workspace_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !can_confirm_payment(current_user, to_user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    sqlx::query("DELETE FROM payments WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,358 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{get, post, put}, Router};
+use sqlx::{PgConnection, PgPool, Row};
+use crate::models::{CreateLunchSession, JoinLunchSession, LunchParticipant, LunchSession, RatingPrompt};
+use crate::routes::violates;
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/lunch-sessions", post(create_lunch_session))
+        .route("/lunch-sessions/today", get(get_todays_lunch_session))
+        .route("/lunch-sessions/{id}", get(get_lunch_session))
+        .route("/lunch-sessions/{id}/lock", post(lock_lunch_session))
+        .route("/lunch-sessions/{id}/participants/{user_id}", put(join_lunch_session).delete(leave_lunch_session))
+        .route("/users/{user_id}/rating-prompts", get(get_rating_prompts))
+}
+
+// A session counts as locked once someone locks it or the cutoff has passed
+const SESSION_COLUMNS: &str = "id, date, title, created_by, cutoff_at, (locked OR NOW() >= cutoff_at) AS locked";
+
+pub(crate) async fn fetch_lunch_session(conn: &mut PgConnection, workspace_id: i32, id: i32) -> Result<LunchSession, StatusCode> {
+    let row = sqlx::query(&format!("SELECT {SESSION_COLUMNS} FROM lunch_sessions WHERE id = $1 AND workspace_id = $2"))
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    let participant_rows = sqlx::query(
+        "SELECT p.user_id, u.username, p.dish_id, p.joined_at
+         FROM lunch_participants p JOIN users u ON u.id = p.user_id
+         WHERE p.session_id = $1
+         ORDER BY p.joined_at"
+    )
+    .bind(id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let participants = participant_rows.iter()
+        .map(|row| LunchParticipant {
+            user_id: row.get("user_id"),
+            username: row.get("username"),
+            dish_id: row.get("dish_id"),
+            joined_at: row.get("joined_at"),
+        })
+        .collect();
+
+    Ok(LunchSession {
+        id: row.get("id"),
+        date: row.get("date"),
+        title: row.get("title"),
+        created_by: row.get("created_by"),
+        cutoff_at: row.get("cutoff_at"),
+        locked: row.get("locked"),
+        participants,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/lunch-sessions",
+    request_body = CreateLunchSession,
+    responses(
+        (status = 201, description = "Today's lunch session created", body = LunchSession),
+        (status = 400, description = "Bad request - cutoff is not later today, or unknown creator"),
+        (status = 409, description = "Conflict - there is already a session today")
+    ),
+    tag = "lunch"
+)]
+pub async fn create_lunch_session(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Json(payload): Json<CreateLunchSession>,
+) -> Result<(StatusCode, Json<LunchSession>), StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(
+        "INSERT INTO lunch_sessions (date, title, created_by, cutoff_at, workspace_id)
+         SELECT CURRENT_DATE, $1, $2, $3, $4
+         WHERE $3 > NOW() AND $3::date = CURRENT_DATE
+           AND ($2::int IS NULL OR EXISTS (SELECT 1 FROM users WHERE id = $2 AND workspace_id = $4))
+         RETURNING id"
+    )
+    .bind(&payload.title)
+    .bind(payload.created_by)
+    .bind(payload.cutoff_at)
+    .bind(workspace_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|e| {
+        if violates(&e, "idx_lunch_sessions_workspace_date_unique") {
+            StatusCode::CONFLICT
+        } else {
+            match e {
+                sqlx::Error::RowNotFound => StatusCode::BAD_REQUEST,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        }
+    })?;
+
+    let session = fetch_lunch_session(&mut conn, workspace_id, row.get("id")).await?;
+
+    Ok((StatusCode::CREATED, Json(session)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lunch-sessions/today",
+    responses(
+        (status = 200, description = "Today's lunch session", body = LunchSession),
+        (status = 404, description = "No session has been started today")
+    ),
+    tag = "lunch"
+)]
+pub async fn get_todays_lunch_session(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<LunchSession>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query("SELECT id FROM lunch_sessions WHERE date = CURRENT_DATE AND workspace_id = $1")
+        .bind(workspace_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(fetch_lunch_session(&mut conn, workspace_id, row.get("id")).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/lunch-sessions/{id}",
+    params(
+        ("id" = i32, Path, description = "Lunch session ID")
+    ),
+    responses(
+        (status = 200, description = "Lunch session found", body = LunchSession),
+        (status = 404, description = "Lunch session not found")
+    ),
+    tag = "lunch"
+)]
+pub async fn get_lunch_session(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<LunchSession>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_lunch_session(&mut conn, workspace_id, id).await?))
+}
+
+#[utoipa::path(
+    post,
+    path = "/lunch-sessions/{id}/lock",
+    params(
+        ("id" = i32, Path, description = "Lunch session ID to lock")
+    ),
+    responses(
+        (status = 200, description = "Lunch session locked", body = LunchSession),
+        (status = 404, description = "Lunch session not found")
+    ),
+    tag = "lunch"
+)]
+pub async fn lock_lunch_session(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<LunchSession>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let result = sqlx::query("UPDATE lunch_sessions SET locked = TRUE WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    Ok(Json(fetch_lunch_session(&mut conn, workspace_id, id).await?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/lunch-sessions/{id}/participants/{user_id}",
+    request_body = JoinLunchSession,
+    params(
+        ("id" = i32, Path, description = "Lunch session ID"),
+        ("user_id" = i32, Path, description = "User joining or changing their pick")
+    ),
+    responses(
+        (status = 200, description = "User joined or changed dish", body = LunchSession),
+        (status = 400, description = "Bad request - unknown user or dish"),
+        (status = 403, description = "Forbidden - only admins can pick for someone else"),
+        (status = 404, description = "Lunch session not found"),
+        (status = 409, description = "Conflict - session is locked")
+    ),
+    tag = "lunch"
+)]
+pub async fn join_lunch_session(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+    Json(payload): Json<JoinLunchSession>,
+) -> Result<Json<LunchSession>, StatusCode> {
+    if current_user.id != user_id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let workspace_id = current_user.workspace_id;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Hold the session row so it can't be locked halfway through
+    let row = sqlx::query(
+        "SELECT (locked OR NOW() >= cutoff_at) AS locked FROM lunch_sessions WHERE id = $1 AND workspace_id = $2 FOR UPDATE"
+    )
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    if row.get::<bool, _>("locked") {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    // The user and dish have to be from the same workspace as the session
+    let result = sqlx::query(
+        "INSERT INTO lunch_participants (session_id, user_id, dish_id)
+         SELECT $1, $2, $3
+         WHERE EXISTS (SELECT 1 FROM users WHERE id = $2 AND workspace_id = $4)
+           AND ($3::int IS NULL OR EXISTS (SELECT 1 FROM dishes WHERE id = $3 AND workspace_id = $4))
+         ON CONFLICT (session_id, user_id) DO UPDATE SET dish_id = EXCLUDED.dish_id"
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(payload.dish_id)
+    .bind(workspace_id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let session = fetch_lunch_session(&mut tx, workspace_id, id).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(session))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/lunch-sessions/{id}/participants/{user_id}",
+    params(
+        ("id" = i32, Path, description = "Lunch session ID"),
+        ("user_id" = i32, Path, description = "User leaving the session")
+    ),
+    responses(
+        (status = 204, description = "User left the session"),
+        (status = 403, description = "Forbidden - only admins can take someone else out"),
+        (status = 404, description = "User is not in the session"),
+        (status = 409, description = "Conflict - session is locked")
+    ),
+    tag = "lunch"
+)]
+pub async fn leave_lunch_session(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+    if current_user.id != user_id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let row = sqlx::query(
+        "WITH session AS (
+            SELECT (locked OR NOW() >= cutoff_at) AS locked FROM lunch_sessions WHERE id = $1 AND workspace_id = $3
+         ), removed AS (
+            DELETE FROM lunch_participants
+            WHERE session_id = $1 AND user_id = $2 AND NOT (SELECT locked FROM session)
+            RETURNING user_id
+         )
+         SELECT (SELECT locked FROM session) AS locked, (SELECT COUNT(*) FROM removed) AS removed"
+    )
+    .bind(id)
+    .bind(user_id)
+    .bind(current_user.workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if row.get::<Option<bool>, _>("locked").unwrap_or(false) {
+        Err(StatusCode::CONFLICT)
+    } else if row.get::<i64, _>("removed") == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/rating-prompts",
+    params(
+        ("user_id" = i32, Path, description = "User ID")
+    ),
+    responses((status = 200, description = "Dishes from recent locked sessions the user still has to rate", body = [RatingPrompt])),
+    tag = "lunch"
+)]
+pub async fn get_rating_prompts(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Vec<RatingPrompt>>, StatusCode> {
+    // Sessions older than the grace period can't be rated anymore
+    let rows = sqlx::query(
+        "SELECT s.id AS session_id, s.date, p.dish_id, d.name AS dish_name
+         FROM lunch_sessions s
+         JOIN workspaces w ON w.id = s.workspace_id
+         JOIN lunch_participants p ON p.session_id = s.id
+         JOIN dishes d ON d.id = p.dish_id
+         WHERE p.user_id = $1
+           AND s.workspace_id = $2
+           AND (s.locked OR NOW() >= s.cutoff_at)
+           AND s.date >= CURRENT_DATE - w.rating_grace_days
+           AND NOT EXISTS (
+               SELECT 1 FROM ratings r WHERE r.user_id = p.user_id AND DATE(r.date) = s.date
+           )
+         ORDER BY s.date DESC"
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let prompts = rows.iter()
+        .map(|row| RatingPrompt {
+            session_id: row.get("session_id"),
+            date: row.get("date"),
+            dish_id: row.get("dish_id"),
+            dish_name: row.get("dish_name"),
+        })
+        .collect();
+
+    Ok(Json(prompts))
+}
@@ -0,0 +1,218 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{get, post}, Router};
+use sqlx::{PgConnection, PgPool, Row};
+use crate::models::{CreateMeal, EventKind, Meal, MealRating, Rating};
+use crate::routes::feed::record_event;
+use crate::routes::ratings::{insert_rating, open_meal, rate_dish, rating_from_row, RATING_COLUMNS};
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/meals", post(create_meal))
+        .route("/meals/{id}", get(get_meal).delete(remove_meal))
+        .route("/meals/{id}/ratings", post(add_meal_rating))
+        .route("/users/{user_id}/meals", get(get_meals_by_user))
+}
+
+/// Meals with their ratings, newest first. Either one meal by id or all of a
+/// user's meals.
+async fn fetch_meals(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    id: Option<i32>,
+    user_id: Option<i32>,
+) -> Result<Vec<Meal>, StatusCode> {
+    let rows = sqlx::query(
+        "SELECT id, user_id, date, created_at FROM meals
+         WHERE workspace_id = $1 AND ($2::int IS NULL OR id = $2) AND ($3::int IS NULL OR user_id = $3)
+         ORDER BY date DESC, id DESC"
+    )
+    .bind(workspace_id)
+    .bind(id)
+    .bind(user_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let meal_ids: Vec<i32> = rows.iter().map(|row| row.get("id")).collect();
+    let rating_rows = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE meal_id = ANY($1) ORDER BY id"
+    ))
+    .bind(&meal_ids)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut ratings: Vec<Rating> = rating_rows.iter().map(rating_from_row).collect::<Result<_, _>>()?;
+
+    Ok(rows.iter().map(|row| {
+        let id: i32 = row.get("id");
+        Meal {
+            id,
+            user_id: row.get("user_id"),
+            date: row.get("date"),
+            created_at: row.get("created_at"),
+            ratings: ratings.extract_if(.., |rating| rating.meal_id == id).collect(),
+        }
+    }).collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/meals",
+    request_body = CreateMeal,
+    responses(
+        (status = 201, description = "Meal created for the current user with a rating per dish", body = Meal),
+        (status = 400, description = "Bad request - no dishes, a score not on the rating scale, unknown dish, or eaten_at in the future or past the grace period"),
+        (status = 409, description = "Conflict - user already had a meal that day, or the same dish is rated twice")
+    ),
+    tag = "meals"
+)]
+pub async fn create_meal(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Json(payload): Json<CreateMeal>,
+) -> Result<(StatusCode, Json<Meal>), StatusCode> {
+    if payload.ratings.is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let workspace_id = current_user.workspace_id;
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let meal_id = open_meal(&mut tx, workspace_id, current_user.id, payload.eaten_at).await?;
+    for rating in &payload.ratings {
+        insert_rating(&mut tx, workspace_id, meal_id, rating).await?;
+    }
+    let meal = fetch_meals(&mut tx, workspace_id, Some(meal_id), None).await?.pop().ok_or(StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(meal)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/meals/{id}",
+    params(
+        ("id" = i32, Path, description = "Meal ID")
+    ),
+    responses(
+        (status = 200, description = "Meal found", body = Meal),
+        (status = 404, description = "Meal not found")
+    ),
+    tag = "meals"
+)]
+pub async fn get_meal(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Meal>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    fetch_meals(&mut conn, workspace_id, Some(id), None).await?.pop()
+        .map(Json)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+#[utoipa::path(
+    post,
+    path = "/meals/{id}/ratings",
+    params(
+        ("id" = i32, Path, description = "Meal ID")
+    ),
+    request_body = MealRating,
+    responses(
+        (status = 201, description = "Dish rating added to the meal", body = Rating),
+        (status = 400, description = "Bad request - score not on the rating scale, unknown dish, a meal that isn't yours, or the meal is past the grace period"),
+        (status = 409, description = "Conflict - the dish is already rated in the meal")
+    ),
+    tag = "meals"
+)]
+pub async fn add_meal_rating(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<MealRating>,
+) -> Result<(StatusCode, Json<Rating>), StatusCode> {
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rating = rate_dish(&mut tx, current_user.workspace_id, current_user.id, Some(id), None, &payload).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(rating)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/meals/{id}",
+    params(
+        ("id" = i32, Path, description = "Meal ID to remove, along with its ratings")
+    ),
+    responses(
+        (status = 204, description = "Meal deleted"),
+        (status = 403, description = "Forbidden - only the person who ate it or a workspace admin can delete a meal"),
+        (status = 404, description = "Meal not found")
+    ),
+    tag = "meals"
+)]
+pub async fn remove_meal(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let workspace_id = current_user.workspace_id;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let eater: i32 = sqlx::query_scalar("SELECT user_id FROM meals WHERE id = $1 AND workspace_id = $2 FOR UPDATE")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if eater != current_user.id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // The ratings would go with the meal anyway, deleting them first tells
+    // the feed which ones
+    let deleted = sqlx::query("DELETE FROM ratings WHERE meal_id = $1 AND workspace_id = $2 RETURNING id, dish_id, user_id")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    for row in &deleted {
+        let data = serde_json::json!({ "meal_id": id, "dish_id": row.get::<i32, _>("dish_id") });
+        record_event(&mut tx, workspace_id, EventKind::RatingDeleted, Some(row.get("user_id")), Some(row.get("id")), data).await?;
+    }
+
+    let result = sqlx::query("DELETE FROM meals WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/meals",
+    params(
+        ("user_id" = i32, Path, description = "User ID")
+    ),
+    responses((status = 200, description = "The user's meals, newest first", body = [Meal])),
+    tag = "meals"
+)]
+pub async fn get_meals_by_user(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(user_id): Path<i32>,
+) -> Result<Json<Vec<Meal>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_meals(&mut conn, workspace_id, None, Some(user_id)).await?))
+}
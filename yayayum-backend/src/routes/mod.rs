@@ -1,13 +1,58 @@
 pub mod users;
 pub mod dishes;
+pub mod ingredients;
+pub mod lunch;
+pub mod orders;
+pub mod ledger;
+pub mod restaurants;
+pub mod stats;
+pub mod workspaces;
 pub mod ratings;
+pub mod meals;
+pub mod comments;
+pub mod reactions;
+pub mod feed;
+pub mod webhooks;
+pub mod chat;
+pub mod digests;
+pub mod jobs;
+pub mod notifications;
+pub mod reminders;
 
-use axum::Router;
+use axum::{http::StatusCode, Router};
+use serde::Serialize;
 use sqlx::PgPool;
 
+/// Whether a query failed on the given constraint (or unique index).
+pub(crate) fn violates(err: &sqlx::Error, constraint: &str) -> bool {
+    err.as_database_error().and_then(|err| err.constraint()) == Some(constraint)
+}
+
+/// Enums are stored as their JSON text, quotes included, like `"Pending"`.
+pub(crate) fn enum_json<T: Serialize>(value: &T) -> Result<String, StatusCode> {
+    serde_json::to_string(value).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
 pub fn routes() -> Router<PgPool> {
     Router::new()
         .merge(users::routes())
         .merge(dishes::routes())
+        .merge(ingredients::routes())
+        .merge(lunch::routes())
+        .merge(orders::routes())
+        .merge(ledger::routes())
+        .merge(restaurants::routes())
+        .merge(stats::routes())
+        .merge(workspaces::routes())
         .merge(ratings::routes())
+        .merge(meals::routes())
+        .merge(comments::routes())
+        .merge(reactions::routes())
+        .merge(feed::routes())
+        .merge(webhooks::routes())
+        .merge(chat::routes())
+        .merge(digests::routes())
+        .merge(jobs::routes())
+        .merge(notifications::routes())
+        .merge(reminders::routes())
 }
\ No newline at end of file
@@ -0,0 +1,113 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post}, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{Notification, NotificationQuery};
+use crate::models::notification::DEFAULT_NOTIFICATION_LIMIT;
+use crate::routes::workspaces::CurrentUser;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/users/{id}/notifications", get(get_notifications))
+        .route("/users/{id}/notifications/{notification_id}/read", post(read_notification))
+}
+
+const NOTIFICATION_COLUMNS: &str = "id, user_id, message, created_at, read_at";
+
+fn notification_from_row(row: &PgRow) -> Notification {
+    Notification {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        message: row.get("message"),
+        created_at: row.get("created_at"),
+        read_at: row.get("read_at"),
+    }
+}
+
+/// Shows a message to a user in the app.
+pub(crate) async fn notify(conn: &mut PgConnection, workspace_id: i32, user_id: i32, message: &str) -> Result<(), StatusCode> {
+    sqlx::query("INSERT INTO notifications (workspace_id, user_id, message) VALUES ($1, $2, $3)")
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(message)
+        .execute(conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/notifications",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        NotificationQuery
+    ),
+    responses(
+        (status = 200, description = "The user's notifications, newest first", body = [Notification]),
+        (status = 404, description = "User not found, or not the current user")
+    ),
+    tag = "notifications"
+)]
+pub async fn get_notifications(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<Vec<Notification>>, StatusCode> {
+    // Notifications are only for the person they're about
+    if id != current_user.id {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let rows = sqlx::query(&format!(
+        "SELECT {NOTIFICATION_COLUMNS} FROM notifications
+         WHERE user_id = $1 AND (NOT $2 OR read_at IS NULL)
+         ORDER BY id DESC LIMIT $3"
+    ))
+    .bind(id)
+    .bind(query.unread)
+    .bind(query.limit.unwrap_or(DEFAULT_NOTIFICATION_LIMIT).max(0))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rows.iter().map(notification_from_row).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/notifications/{notification_id}/read",
+    params(
+        ("id" = i32, Path, description = "User ID"),
+        ("notification_id" = i32, Path, description = "Notification ID")
+    ),
+    responses(
+        (status = 200, description = "Notification marked as read, keeping the first time it was read", body = Notification),
+        (status = 404, description = "User or notification not found, or not the current user's")
+    ),
+    tag = "notifications"
+)]
+pub async fn read_notification(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, notification_id)): Path<(i32, i32)>,
+) -> Result<Json<Notification>, StatusCode> {
+    if id != current_user.id {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let row = sqlx::query(&format!(
+        "UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+         WHERE id = $1 AND user_id = $2 AND workspace_id = $3
+         RETURNING {NOTIFICATION_COLUMNS}"
+    ))
+    .bind(notification_id)
+    .bind(id)
+    .bind(current_user.workspace_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok(Json(notification_from_row(&row)))
+}
@@ -0,0 +1,430 @@
+use std::collections::HashMap;
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{get, post, put}, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{CreateOrder, CreateOrderItem, Order, OrderItem, OrderShare, OrderSummary};
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/orders", post(create_order).get(get_orders))
+        .route("/orders/{id}", get(get_order).delete(remove_order))
+        .route("/orders/{id}/items", post(add_order_item))
+        .route("/orders/{id}/items/{item_id}", put(modify_order_item).delete(remove_order_item))
+        .route("/orders/{id}/summary", get(get_order_summary))
+        .route("/orders/{id}/split", get(get_order_split))
+}
+
+const ORDER_ITEM_COLUMNS: &str = "id, order_id, user_id, dish_id, dish_nr, dish_name, unit_price_kr, quantity, notes";
+
+fn order_item_from_row(row: &PgRow) -> OrderItem {
+    OrderItem {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        dish_id: row.get("dish_id"),
+        dish_nr: row.get("dish_nr"),
+        dish_name: row.get("dish_name"),
+        unit_price_kr: row.get("unit_price_kr"),
+        quantity: row.get("quantity"),
+        notes: row.get("notes"),
+    }
+}
+
+/// Loads the workspace's orders with their items. `None` loads every order.
+pub(crate) async fn fetch_orders(conn: &mut PgConnection, workspace_id: i32, ids: Option<&[i32]>) -> Result<Vec<Order>, StatusCode> {
+    let rows = sqlx::query(
+        "SELECT id, paid_by, session_id, delivery_fee_kr, created_at FROM orders
+         WHERE workspace_id = $1 AND ($2::int[] IS NULL OR id = ANY($2))
+         ORDER BY created_at DESC"
+    )
+    .bind(workspace_id)
+    .bind(ids)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let order_ids: Vec<i32> = rows.iter().map(|row| row.get("id")).collect();
+    let item_rows = sqlx::query(&format!(
+        "SELECT {ORDER_ITEM_COLUMNS} FROM order_items WHERE order_id = ANY($1) ORDER BY id"
+    ))
+    .bind(&order_ids)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut items: HashMap<i32, Vec<OrderItem>> = HashMap::new();
+    for row in &item_rows {
+        items.entry(row.get("order_id")).or_default().push(order_item_from_row(row));
+    }
+
+    Ok(rows.iter()
+        .map(|row| Order {
+            id: row.get("id"),
+            paid_by: row.get("paid_by"),
+            session_id: row.get("session_id"),
+            delivery_fee_kr: row.get("delivery_fee_kr"),
+            created_at: row.get("created_at"),
+            items: items.remove(&row.get::<i32, _>("id")).unwrap_or_default(),
+        })
+        .collect())
+}
+
+async fn fetch_order(conn: &mut PgConnection, workspace_id: i32, id: i32) -> Result<Order, StatusCode> {
+    fetch_orders(conn, workspace_id, Some(&[id])).await?.pop().ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Anyone can change their own line. The organiser who pays for the order, or
+/// a workspace admin, can also change other people's and shared side orders.
+fn can_change_line(current_user: CurrentUser, order: &Order, user_id: Option<i32>) -> bool {
+    user_id == Some(current_user.id) || order.paid_by == current_user.id || current_user.is_admin
+}
+
+fn map_order_error(e: sqlx::Error) -> StatusCode {
+    match e {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        // Unknown user, or quantity out of range
+        ref e if e.as_database_error().is_some_and(|e| e.is_foreign_key_violation() || e.is_check_violation()) => StatusCode::BAD_REQUEST,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders",
+    request_body = CreateOrder,
+    responses(
+        (status = 201, description = "Order created", body = Order),
+        (status = 400, description = "Bad request - unknown user or lunch session, or negative delivery fee"),
+        (status = 403, description = "Forbidden - only workspace admins can organise an order for someone else")
+    ),
+    tag = "orders"
+)]
+pub async fn create_order(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Json(payload): Json<CreateOrder>,
+) -> Result<(StatusCode, Json<Order>), StatusCode> {
+    if payload.delivery_fee_kr < 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if payload.paid_by != current_user.id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let workspace_id = current_user.workspace_id;
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // The payer and the lunch session have to be from the caller's workspace
+    let row = sqlx::query(
+        "INSERT INTO orders (paid_by, session_id, delivery_fee_kr, workspace_id)
+         SELECT $1, $2, $3, $4
+         WHERE EXISTS (SELECT 1 FROM users WHERE id = $1 AND workspace_id = $4)
+           AND ($2::int IS NULL OR EXISTS (SELECT 1 FROM lunch_sessions WHERE id = $2 AND workspace_id = $4))
+         RETURNING id"
+    )
+    .bind(payload.paid_by)
+    .bind(payload.session_id)
+    .bind(payload.delivery_fee_kr)
+    .bind(workspace_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(map_order_error)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let id: i32 = row.get("id");
+
+    // Everyone who picked a dish in the lunch session gets it on the order
+    if let Some(session_id) = payload.session_id {
+        sqlx::query(
+            "INSERT INTO order_items (order_id, user_id, dish_id, dish_nr, dish_name, unit_price_kr, quantity)
+             SELECT $1, p.user_id, d.id, d.nr, d.name, d.price_kr, 1
+             FROM lunch_participants p JOIN dishes d ON d.id = p.dish_id
+             WHERE p.session_id = $2
+             ORDER BY p.joined_at"
+        )
+        .bind(id)
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+
+    let order = fetch_order(&mut tx, workspace_id, id).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(order)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders",
+    responses((status = 200, description = "List orders, newest first", body = [Order])),
+    tag = "orders"
+)]
+pub async fn get_orders(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Order>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_orders(&mut conn, workspace_id, None).await?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}",
+    params(
+        ("id" = i32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Order found", body = Order),
+        (status = 404, description = "Order not found")
+    ),
+    tag = "orders"
+)]
+pub async fn get_order(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Order>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_order(&mut conn, workspace_id, id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orders/{id}",
+    params(
+        ("id" = i32, Path, description = "Order ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Order deleted"),
+        (status = 403, description = "Forbidden - only the order's organiser or a workspace admin can delete it"),
+        (status = 404, description = "Order not found")
+    ),
+    tag = "orders"
+)]
+pub async fn remove_order(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let paid_by: i32 = sqlx::query_scalar("SELECT paid_by FROM orders WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(current_user.workspace_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if paid_by != current_user.id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    sqlx::query("DELETE FROM orders WHERE id = $1")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    post,
+    path = "/orders/{id}/items",
+    request_body = CreateOrderItem,
+    params(
+        ("id" = i32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 201, description = "Item added", body = Order),
+        (status = 400, description = "Bad request - unknown user or dish, or quantity below 1"),
+        (status = 403, description = "Forbidden - only the order's organiser or a workspace admin can add lines for others or shared side orders"),
+        (status = 404, description = "Order not found")
+    ),
+    tag = "orders"
+)]
+pub async fn add_order_item(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateOrderItem>,
+) -> Result<(StatusCode, Json<Order>), StatusCode> {
+    let workspace_id = current_user.workspace_id;
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let order = fetch_order(&mut conn, workspace_id, id).await?;
+    if !can_change_line(current_user, &order, payload.user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // The dish's current name and price are copied so later menu edits don't change old orders
+    let result = sqlx::query(
+        "INSERT INTO order_items (order_id, user_id, dish_id, dish_nr, dish_name, unit_price_kr, quantity, notes)
+         SELECT $1, $2, d.id, d.nr, d.name, d.price_kr, $4, $5 FROM dishes d
+         WHERE d.id = $3 AND d.workspace_id = $6
+           AND ($2::int IS NULL OR EXISTS (SELECT 1 FROM users WHERE id = $2 AND workspace_id = $6))"
+    )
+    .bind(order.id)
+    .bind(payload.user_id)
+    .bind(payload.dish_id)
+    .bind(payload.quantity)
+    .bind(&payload.notes)
+    .bind(workspace_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(map_order_error)?;
+
+    if result.rows_affected() == 0 {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    Ok((StatusCode::CREATED, Json(fetch_order(&mut conn, workspace_id, id).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/orders/{id}/items/{item_id}",
+    request_body = CreateOrderItem,
+    params(
+        ("id" = i32, Path, description = "Order ID"),
+        ("item_id" = i32, Path, description = "Order item ID to update")
+    ),
+    responses(
+        (status = 200, description = "Item updated", body = Order),
+        (status = 400, description = "Bad request - unknown user or dish, or quantity below 1"),
+        (status = 403, description = "Forbidden - only the order's organiser or a workspace admin can change lines for others or shared side orders"),
+        (status = 404, description = "Order item not found")
+    ),
+    tag = "orders"
+)]
+pub async fn modify_order_item(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, item_id)): Path<(i32, i32)>,
+    Json(payload): Json<CreateOrderItem>,
+) -> Result<Json<Order>, StatusCode> {
+    let workspace_id = current_user.workspace_id;
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let order = fetch_order(&mut conn, workspace_id, id).await?;
+    let item = order.items.iter().find(|item| item.id == item_id).ok_or(StatusCode::NOT_FOUND)?;
+    // Both whose line it was and whose it becomes
+    if !can_change_line(current_user, &order, item.user_id) || !can_change_line(current_user, &order, payload.user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // Unknown dish or user, or one from another workspace
+    let dish = sqlx::query(
+        "SELECT nr, name, price_kr FROM dishes
+         WHERE id = $1 AND workspace_id = $2
+           AND ($3::int IS NULL OR EXISTS (SELECT 1 FROM users WHERE id = $3 AND workspace_id = $2))"
+    )
+    .bind(payload.dish_id)
+    .bind(workspace_id)
+    .bind(payload.user_id)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Keep the recorded price unless the dish itself was swapped
+    sqlx::query(
+        "UPDATE order_items SET user_id = $1, quantity = $2, notes = $3,
+            unit_price_kr = CASE WHEN dish_id IS DISTINCT FROM $4 THEN $5 ELSE unit_price_kr END,
+            dish_nr = $6, dish_name = $7, dish_id = $4
+         WHERE id = $8 AND order_id = $9
+         RETURNING id"
+    )
+    .bind(payload.user_id)
+    .bind(payload.quantity)
+    .bind(&payload.notes)
+    .bind(payload.dish_id)
+    .bind(dish.get::<i32, _>("price_kr"))
+    .bind(dish.get::<i32, _>("nr"))
+    .bind(dish.get::<String, _>("name"))
+    .bind(item_id)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(map_order_error)?;
+
+    Ok(Json(fetch_order(&mut conn, workspace_id, id).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/orders/{id}/items/{item_id}",
+    params(
+        ("id" = i32, Path, description = "Order ID"),
+        ("item_id" = i32, Path, description = "Order item ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Item removed"),
+        (status = 403, description = "Forbidden - only the order's organiser or a workspace admin can remove lines for others or shared side orders"),
+        (status = 404, description = "Order item not found")
+    ),
+    tag = "orders"
+)]
+pub async fn remove_order_item(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, item_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let order = fetch_order(&mut conn, current_user.workspace_id, id).await?;
+    let item = order.items.iter().find(|item| item.id == item_id).ok_or(StatusCode::NOT_FOUND)?;
+    if !can_change_line(current_user, &order, item.user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    sqlx::query("DELETE FROM order_items WHERE id = $1 AND order_id = $2")
+        .bind(item_id)
+        .bind(id)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}/summary",
+    params(
+        ("id" = i32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "Order grouped by menu number, ready to phone in", body = OrderSummary),
+        (status = 404, description = "Order not found")
+    ),
+    tag = "orders"
+)]
+pub async fn get_order_summary(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<OrderSummary>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_order(&mut conn, workspace_id, id).await?.summary()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/orders/{id}/split",
+    params(
+        ("id" = i32, Path, description = "Order ID")
+    ),
+    responses(
+        (status = 200, description = "What each person pays, including shared side orders and delivery", body = [OrderShare]),
+        (status = 404, description = "Order not found")
+    ),
+    tag = "orders"
+)]
+pub async fn get_order_split(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<OrderShare>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(fetch_order(&mut conn, workspace_id, id).await?.split()))
+}
@@ -1,6 +1,13 @@
-use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{get, post}, Router};
-use sqlx::{PgPool, Row};
-use crate::models::{Rating, CreateRating};
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post}, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{Rating, RatingQuery, CreateRating, MealRating, RatingRevision, SubScores};
+use crate::models::{AchievementKind, EventKind};
+use crate::models::achievement::{AchievementStats, BALI_GORENG_NR, SPICY_CHILI_NR, YAYA};
+use crate::models::stats::longest_streak;
+use crate::models::rating::changed_fields;
+use crate::routes::feed::record_event;
+use crate::routes::violates;
+use crate::routes::workspaces::{fetch_rating_scale, CurrentUser, CurrentWorkspace};
 
 pub fn routes() -> Router<PgPool> {
     Router::new()
@@ -8,92 +15,318 @@ pub fn routes() -> Router<PgPool> {
         .route("/ratings/{id}", get(get_rating).put(modify_rating).delete(remove_rating))
         .route("/ratings/dish/{dish_id}", get(get_ratings_by_dish))
         .route("/ratings/user/{user_id}", get(get_ratings_by_user))
+        .route("/ratings/{id}/history", get(get_rating_history))
 }
 
-#[utoipa::path(
-    post,
-    path = "/ratings",
-    request_body = CreateRating,
-    responses(
-        (status = 201, description = "Rating created", body = Rating),
-        (status = 400, description = "Bad request - invalid rating value"),
-        (status = 409, description = "Conflict - user already rated today")
-    ),
-    tag = "ratings"
-)]
-pub async fn create_rating(
-    State(pool): State<PgPool>,
-    Json(payload): Json<CreateRating>,
-) -> Result<(StatusCode, Json<Rating>), StatusCode> {
-    // Validate rating is between 1-5
-    if payload.rating < 1 || payload.rating > 5 {
-        return Err(StatusCode::BAD_REQUEST);
-    }
+pub(crate) const RATING_COLUMNS: &str = "id, meal_id, dish_id, rating, user_id, description, photo, date,
+    taste, portion, spiciness_accuracy, temperature, value, wait_time, updated_at, updated_at IS NOT NULL AS edited,
+    (SELECT COUNT(*) FROM rating_comments c WHERE c.rating_id = ratings.id) AS comment_count,
+    (SELECT COALESCE(json_agg(json_build_object('emoji', emoji, 'count', count) ORDER BY count DESC, emoji), '[]')
+     FROM (SELECT emoji, COUNT(*) AS count FROM rating_reactions WHERE rating_id = ratings.id GROUP BY emoji) r)::text AS reactions";
 
+// Reaction counts come as a JSON array, most popular first
+pub(crate) fn rating_from_row(row: &PgRow) -> Result<Rating, StatusCode> {
+    let reactions = serde_json::from_str(&row.get::<String, _>("reactions"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Rating {
+        id: row.get("id"),
+        meal_id: row.get("meal_id"),
+        dish_id: row.get("dish_id"),
+        rating: row.get("rating"),
+        user_id: row.get("user_id"),
+        description: row.get("description"),
+        photo: row.get("photo"),
+        date: row.get("date"),
+        sub_scores: SubScores {
+            taste: row.get("taste"),
+            portion: row.get("portion"),
+            spiciness_accuracy: row.get("spiciness_accuracy"),
+            temperature: row.get("temperature"),
+            value: row.get("value"),
+            wait_time: row.get("wait_time"),
+        },
+        comment_count: row.get("comment_count"),
+        reactions,
+        edited: row.get("edited"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+/// 404 unless the rating is in the workspace.
+pub(crate) async fn rating_exists(pool: &PgPool, workspace_id: i32, rating_id: i32) -> Result<(), StatusCode> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM ratings WHERE id = $1 AND workspace_id = $2)")
+        .bind(rating_id)
+        .bind(workspace_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if exists { Ok(()) } else { Err(StatusCode::NOT_FOUND) }
+}
+
+/// Starts a meal for the user, dated `eaten_at` or today. A backdated meal has
+/// to be within the workspace's grace period, and unless the workspace allows
+/// several meals a day the user can't have had one that day already.
+pub(crate) async fn open_meal(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    user_id: i32,
+    eaten_at: Option<chrono::NaiveDate>,
+) -> Result<i32, StatusCode> {
+    // Locking the user serialises their meals, so two requests can't both
+    // squeeze in the day's only meal
     let row = sqlx::query(
-        "INSERT INTO ratings (dish_id, rating, user_id, description, photo, date) 
-         VALUES ($1, $2, $3, $4, $5, NOW()) 
-         RETURNING id, dish_id, rating, user_id, description, photo, date"
+        "SELECT COALESCE($3::date, CURRENT_DATE) AS date, w.one_meal_per_day,
+            COALESCE($3::date, CURRENT_DATE) BETWEEN CURRENT_DATE - w.rating_grace_days AND CURRENT_DATE AS in_grace_period
+         FROM users u JOIN workspaces w ON w.id = u.workspace_id
+         WHERE u.id = $1 AND u.workspace_id = $2
+         FOR UPDATE OF u"
     )
+    .bind(user_id)
+    .bind(workspace_id)
+    .bind(eaten_at)
+    .fetch_optional(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+
+    if !row.get::<bool, _>("in_grace_period") {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let date: chrono::NaiveDate = row.get("date");
+
+    if row.get::<bool, _>("one_meal_per_day") {
+        let had_meal: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM meals WHERE user_id = $1 AND date = $2)")
+            .bind(user_id)
+            .bind(date)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        if had_meal {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    sqlx::query_scalar("INSERT INTO meals (workspace_id, user_id, date) VALUES ($1, $2, $3) RETURNING id")
+        .bind(workspace_id)
+        .bind(user_id)
+        .bind(date)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+/// Adds a dish rating to a meal. Scores have to fit the workspace's rating
+/// scale, the dish has to be from the same workspace, and a meal can only be
+/// added to while it's within the grace period.
+pub(crate) async fn insert_rating(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    meal_id: i32,
+    payload: &MealRating,
+) -> Result<Rating, StatusCode> {
+    if !fetch_rating_scale(&mut *conn, workspace_id).await?.accepts_rating(payload) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = sqlx::query(&format!(
+        "INSERT INTO ratings (dish_id, rating, user_id, description, photo, date, workspace_id, meal_id,
+            taste, portion, spiciness_accuracy, temperature, value, wait_time)
+         SELECT $1, $2, m.user_id, $3, $4, m.date + LOCALTIME, m.workspace_id, m.id, $7, $8, $9, $10, $11, $12
+         FROM meals m JOIN workspaces w ON w.id = m.workspace_id
+         WHERE m.id = $5 AND m.workspace_id = $6
+           AND m.date >= CURRENT_DATE - w.rating_grace_days
+           AND EXISTS (SELECT 1 FROM dishes WHERE id = $1 AND workspace_id = $6)
+         RETURNING {RATING_COLUMNS}"
+    ))
     .bind(payload.dish_id)
     .bind(payload.rating)
-    .bind(payload.user_id)
     .bind(&payload.description)
     .bind(&payload.photo)
-    .fetch_one(&pool)
+    .bind(meal_id)
+    .bind(workspace_id)
+    .bind(payload.sub_scores.taste)
+    .bind(payload.sub_scores.portion)
+    .bind(payload.sub_scores.spiciness_accuracy)
+    .bind(payload.sub_scores.temperature)
+    .bind(payload.sub_scores.value)
+    .bind(payload.sub_scores.wait_time)
+    .fetch_optional(&mut *conn)
     .await
     .map_err(|e| {
-        // Check if it's a unique constraint violation (one rating per day)
-        if e.to_string().contains("idx_ratings_user_date_unique") {
-            StatusCode::CONFLICT // 409 Conflict - user already rated today
+        // A dish can only be rated once per meal
+        if violates(&e, "idx_ratings_meal_dish_unique") {
+            StatusCode::CONFLICT
         } else {
             StatusCode::INTERNAL_SERVER_ERROR
         }
-    })?;
+    })?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let rating = rating_from_row(&row)?;
 
-    let rating = Rating {
-        id: row.get("id"),
-        dish_id: row.get("dish_id"),
-        rating: row.get("rating"),
-        user_id: row.get("user_id"),
-        description: row.get("description"),
-        photo: row.get("photo"),
-        date: row.get("date"),
+    let dish_name: String = sqlx::query_scalar("SELECT name FROM dishes WHERE id = $1")
+        .bind(rating.dish_id)
+        .fetch_one(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let data = serde_json::json!({
+        "meal_id": rating.meal_id,
+        "dish_id": rating.dish_id,
+        "dish_name": dish_name,
+        "rating": rating.rating,
+        "description": rating.description,
+    });
+    record_event(&mut *conn, workspace_id, EventKind::RatingCreated, Some(rating.user_id), Some(rating.id), data).await?;
+    unlock_achievements(&mut *conn, workspace_id, rating.user_id).await?;
+
+    Ok(rating)
+}
+
+/// Records the achievements the user's ratings have earned them, with a feed
+/// event for each one that's new.
+async fn unlock_achievements(conn: &mut PgConnection, workspace_id: i32, user_id: i32) -> Result<(), StatusCode> {
+    let row = sqlx::query(
+        "SELECT COUNT(*) AS ratings, COUNT(DISTINCT r.dish_id) AS unique_dishes,
+            (SELECT COUNT(*) FROM dishes WHERE workspace_id = $2) AS total_dishes,
+            COUNT(*) FILTER (WHERE rs.name = $3 AND d.nr = $4) AS bali_goreng,
+            COUNT(*) FILTER (WHERE rs.name = $3 AND d.nr = $5) AS spicy_chili,
+            COALESCE(ARRAY_AGG(DISTINCT DATE(r.date)), '{}') AS days
+         FROM ratings r
+         JOIN dishes d ON d.id = r.dish_id
+         JOIN restaurants rs ON rs.id = d.restaurant_id
+         WHERE r.user_id = $1"
+    )
+    .bind(user_id)
+    .bind(workspace_id)
+    .bind(YAYA)
+    .bind(BALI_GORENG_NR)
+    .bind(SPICY_CHILI_NR)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let days: Vec<chrono::NaiveDate> = row.get("days");
+    let stats = AchievementStats {
+        ratings: row.get("ratings"),
+        unique_dishes: row.get("unique_dishes"),
+        total_dishes: row.get("total_dishes"),
+        longest_streak: longest_streak(&days),
+        bali_goreng: row.get("bali_goreng"),
+        spicy_chili: row.get("spicy_chili"),
     };
 
+    for kind in AchievementKind::ALL.into_iter().filter(|kind| kind.is_unlocked(&stats)) {
+        let kind_str = serde_json::to_string(&kind).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let unlocked = sqlx::query(
+            "INSERT INTO user_achievements (user_id, achievement) VALUES ($1, $2) ON CONFLICT DO NOTHING"
+        )
+        .bind(user_id)
+        .bind(&kind_str)
+        .execute(&mut *conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .rows_affected() > 0;
+
+        if unlocked {
+            let data = serde_json::to_value(kind.achievement()).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            record_event(&mut *conn, workspace_id, EventKind::AchievementUnlocked, Some(user_id), None, data).await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/ratings",
+    request_body = CreateRating,
+    responses(
+        (status = 201, description = "Rating created as the current user, in a new meal unless meal_id is given", body = Rating),
+        (status = 400, description = "Bad request - rating or sub-score not on the rating scale, unknown dish, a meal that isn't yours, or eaten_at in the future, past the grace period or not the meal's day"),
+        (status = 409, description = "Conflict - user already had a meal that day, or the dish is already rated in the meal")
+    ),
+    tag = "ratings"
+)]
+pub async fn create_rating(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Json(payload): Json<CreateRating>,
+) -> Result<(StatusCode, Json<Rating>), StatusCode> {
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let rating = rate_dish(
+        &mut tx,
+        current_user.workspace_id,
+        current_user.id,
+        payload.meal_id,
+        payload.eaten_at,
+        &MealRating::from(&payload),
+    )
+    .await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
     Ok((StatusCode::CREATED, Json(rating)))
 }
 
+/// Rates a dish as the user, adding it to their meal `meal_id` or starting a
+/// new one dated `eaten_at`.
+pub(crate) async fn rate_dish(
+    conn: &mut PgConnection,
+    workspace_id: i32,
+    user_id: i32,
+    meal_id: Option<i32>,
+    eaten_at: Option<chrono::NaiveDate>,
+    rating: &MealRating,
+) -> Result<Rating, StatusCode> {
+    let meal_id = match meal_id {
+        Some(meal_id) => {
+            let belongs: bool = sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM meals WHERE id = $1 AND workspace_id = $2 AND user_id = $3
+                    AND ($4::date IS NULL OR date = $4))"
+            )
+            .bind(meal_id)
+            .bind(workspace_id)
+            .bind(user_id)
+            .bind(eaten_at)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if !belongs {
+                return Err(StatusCode::BAD_REQUEST);
+            }
+            meal_id
+        }
+        None => open_meal(&mut *conn, workspace_id, user_id, eaten_at).await?,
+    };
+
+    insert_rating(&mut *conn, workspace_id, meal_id, rating).await
+}
+
 #[utoipa::path(
     get,
     path = "/ratings",
+    params(RatingQuery),
     responses((status = 200, description = "List ratings", body = [Rating])),
     tag = "ratings"
 )]
 pub async fn get_ratings(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<RatingQuery>,
 ) -> Result<Json<Vec<Rating>>, StatusCode> {
-    let rows = sqlx::query(
-        "SELECT id, dish_id, rating, user_id, description, photo, date FROM ratings ORDER BY date DESC"
-    )
+    let rows = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings
+         WHERE workspace_id = $1
+           AND ($2::int IS NULL OR user_id IN (SELECT followee_id FROM user_follows WHERE follower_id = $2))
+         ORDER BY date DESC"
+    ))
+    .bind(workspace_id)
+    .bind(query.followed_by)
     .fetch_all(&pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let mut ratings = Vec::new();
-    for row in rows {
-        ratings.push(Rating {
-            id: row.get("id"),
-            dish_id: row.get("dish_id"),
-            rating: row.get("rating"),
-            user_id: row.get("user_id"),
-            description: row.get("description"),
-            photo: row.get("photo"),
-            date: row.get("date"),
-        });
-    }
-
-    Ok(Json(ratings))
+    Ok(Json(rows.iter().map(rating_from_row).collect::<Result<Vec<_>, _>>()?))
 }
 
 #[utoipa::path(
@@ -110,12 +343,14 @@ pub async fn get_ratings(
 )]
 pub async fn get_rating(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Path(id): Path<i32>,
 ) -> Result<Json<Rating>, StatusCode> {
-    let row = sqlx::query(
-        "SELECT id, dish_id, rating, user_id, description, photo, date FROM ratings WHERE id = $1"
-    )
+    let row = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE id = $1 AND workspace_id = $2"
+    ))
     .bind(id)
+    .bind(workspace_id)
     .fetch_one(&pool)
     .await
     .map_err(|err| match err {
@@ -123,17 +358,7 @@ pub async fn get_rating(
         _ => StatusCode::INTERNAL_SERVER_ERROR,
     })?;
 
-    let rating = Rating {
-        id: row.get("id"),
-        dish_id: row.get("dish_id"),
-        rating: row.get("rating"),
-        user_id: row.get("user_id"),
-        description: row.get("description"),
-        photo: row.get("photo"),
-        date: row.get("date"),
-    };
-
-    Ok(Json(rating))
+    Ok(Json(rating_from_row(&row)?))
 }
 
 #[utoipa::path(
@@ -147,30 +372,19 @@ pub async fn get_rating(
 )]
 pub async fn get_ratings_by_dish(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Path(dish_id): Path<i32>,
 ) -> Result<Json<Vec<Rating>>, StatusCode> {
-    let rows = sqlx::query(
-        "SELECT id, dish_id, rating, user_id, description, photo, date FROM ratings WHERE dish_id = $1 ORDER BY date DESC"
-    )
+    let rows = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE dish_id = $1 AND workspace_id = $2 ORDER BY date DESC"
+    ))
     .bind(dish_id)
+    .bind(workspace_id)
     .fetch_all(&pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let mut ratings = Vec::new();
-    for row in rows {
-        ratings.push(Rating {
-            id: row.get("id"),
-            dish_id: row.get("dish_id"),
-            rating: row.get("rating"),
-            user_id: row.get("user_id"),
-            description: row.get("description"),
-            photo: row.get("photo"),
-            date: row.get("date"),
-        });
-    }
-
-    Ok(Json(ratings))
+    Ok(Json(rows.iter().map(rating_from_row).collect::<Result<Vec<_>, _>>()?))
 }
 
 #[utoipa::path(
@@ -184,30 +398,19 @@ pub async fn get_ratings_by_dish(
 )]
 pub async fn get_ratings_by_user(
     State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
     Path(user_id): Path<i32>,
 ) -> Result<Json<Vec<Rating>>, StatusCode> {
-    let rows = sqlx::query(
-        "SELECT id, dish_id, rating, user_id, description, photo, date FROM ratings WHERE user_id = $1 ORDER BY date DESC"
-    )
+    let rows = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE user_id = $1 AND workspace_id = $2 ORDER BY date DESC"
+    ))
     .bind(user_id)
+    .bind(workspace_id)
     .fetch_all(&pool)
     .await
     .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    let mut ratings = Vec::new();
-    for row in rows {
-        ratings.push(Rating {
-            id: row.get("id"),
-            dish_id: row.get("dish_id"),
-            rating: row.get("rating"),
-            user_id: row.get("user_id"),
-            description: row.get("description"),
-            photo: row.get("photo"),
-            date: row.get("date"),
-        });
-    }
-
-    Ok(Json(ratings))
+    Ok(Json(rows.iter().map(rating_from_row).collect::<Result<Vec<_>, _>>()?))
 }
 
 #[utoipa::path(
@@ -218,48 +421,106 @@ pub async fn get_ratings_by_user(
     ),
     request_body = CreateRating,
     responses(
-        (status = 200, description = "Rating updated", body = Rating),
-        (status = 404, description = "Rating not found")
+        (status = 200, description = "Rating updated, keeping the previous version in its history", body = Rating),
+        (status = 400, description = "Bad request - rating or sub-score not on the rating scale, unknown dish, or trying to move the rating to another day or meal"),
+        (status = 403, description = "Forbidden - only the rater or a workspace admin can edit a rating"),
+        (status = 404, description = "Rating not found"),
+        (status = 409, description = "Conflict - the dish is already rated in the meal")
     ),
     tag = "ratings"
 )]
 pub async fn modify_rating(
     State(pool): State<PgPool>,
+    current_user: CurrentUser,
     Path(id): Path<i32>,
     Json(payload): Json<CreateRating>,
 ) -> Result<Json<Rating>, StatusCode> {
-    // Validate rating is between 1-5
-    if payload.rating < 1 || payload.rating > 5 {
+    let workspace_id = current_user.workspace_id;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let edit = MealRating::from(&payload);
+    if !fetch_rating_scale(&mut tx, workspace_id).await?.accepts_rating(&edit) {
         return Err(StatusCode::BAD_REQUEST);
     }
 
-    let row = sqlx::query(
-        "UPDATE ratings SET dish_id = $1, rating = $2, user_id = $3, description = $4, photo = $5 
-         WHERE id = $6 
-         RETURNING id, dish_id, rating, user_id, description, photo, date"
+    let existing = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE id = $1 AND workspace_id = $2 FOR UPDATE"
+    ))
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+    let existing = rating_from_row(&existing)?;
+    if existing.user_id != current_user.id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // When the meal was eaten stays with the meal
+    if payload.eaten_at.is_some_and(|day| day != existing.date.date())
+        || payload.meal_id.is_some_and(|meal_id| meal_id != existing.meal_id)
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    // Saving without changes doesn't count as an edit
+    if edit == MealRating::from(&existing) {
+        return Ok(Json(existing));
+    }
+
+    sqlx::query(
+        "INSERT INTO rating_revisions (rating_id, dish_id, rating, description, photo,
+            taste, portion, spiciness_accuracy, temperature, value, wait_time)
+         SELECT id, dish_id, rating, description, photo, taste, portion, spiciness_accuracy, temperature, value, wait_time
+         FROM ratings WHERE id = $1"
     )
+    .bind(id)
+    .execute(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!(
+        "UPDATE ratings SET dish_id = $1, rating = $2, description = $3, photo = $4,
+            taste = $7, portion = $8, spiciness_accuracy = $9, temperature = $10, value = $11, wait_time = $12,
+            updated_at = NOW()
+         WHERE id = $5 AND workspace_id = $6
+           AND EXISTS (SELECT 1 FROM dishes WHERE id = $1 AND workspace_id = $6)
+         RETURNING {RATING_COLUMNS}"
+    ))
     .bind(payload.dish_id)
     .bind(payload.rating)
-    .bind(payload.user_id)
     .bind(&payload.description)
     .bind(&payload.photo)
     .bind(id)
-    .fetch_one(&pool)
+    .bind(workspace_id)
+    .bind(payload.sub_scores.taste)
+    .bind(payload.sub_scores.portion)
+    .bind(payload.sub_scores.spiciness_accuracy)
+    .bind(payload.sub_scores.temperature)
+    .bind(payload.sub_scores.value)
+    .bind(payload.sub_scores.wait_time)
+    .fetch_optional(&mut *tx)
     .await
-    .map_err(|err| match err {
-        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
-        _ => StatusCode::INTERNAL_SERVER_ERROR,
-    })?;
+    .map_err(|err| {
+        if violates(&err, "idx_ratings_meal_dish_unique") {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?
+    .ok_or(StatusCode::BAD_REQUEST)?;
+    let rating = rating_from_row(&row)?;
 
-    let rating = Rating {
-        id: row.get("id"),
-        dish_id: row.get("dish_id"),
-        rating: row.get("rating"),
-        user_id: row.get("user_id"),
-        description: row.get("description"),
-        photo: row.get("photo"),
-        date: row.get("date"),
-    };
+    let data = serde_json::json!({
+        "meal_id": rating.meal_id,
+        "dish_id": rating.dish_id,
+        "rating": rating.rating,
+        "changed": changed_fields(&MealRating::from(&existing), &edit),
+    });
+    record_event(&mut tx, workspace_id, EventKind::RatingUpdated, Some(rating.user_id), Some(rating.id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
     Ok(Json(rating))
 }
@@ -272,23 +533,125 @@ pub async fn modify_rating(
     ),
     responses(
         (status = 204, description = "Rating deleted"),
+        (status = 403, description = "Forbidden - only the rater or a workspace admin can delete a rating"),
         (status = 404, description = "Rating not found")
     ),
     tag = "ratings"
 )]
 pub async fn remove_rating(
     State(pool): State<PgPool>,
+    current_user: CurrentUser,
     Path(id): Path<i32>,
 ) -> Result<StatusCode, StatusCode> {
-    let result = sqlx::query("DELETE FROM ratings WHERE id = $1")
+    let workspace_id = current_user.workspace_id;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rater: i32 = sqlx::query_scalar("SELECT user_id FROM ratings WHERE id = $1 AND workspace_id = $2 FOR UPDATE")
         .bind(id)
-        .execute(&pool)
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if rater != current_user.id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    // A meal goes away with its last rating, so the day is free again
+    let row = sqlx::query(
+        "WITH deleted AS (DELETE FROM ratings WHERE id = $1 AND workspace_id = $2 RETURNING meal_id, dish_id, user_id),
+         emptied AS (
+            DELETE FROM meals m USING deleted d
+            WHERE m.id = d.meal_id AND NOT EXISTS (SELECT 1 FROM ratings r WHERE r.meal_id = m.id AND r.id <> $1)
+         )
+         SELECT meal_id, dish_id, user_id FROM deleted"
+    )
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    let data = serde_json::json!({
+        "meal_id": row.get::<i32, _>("meal_id"),
+        "dish_id": row.get::<i32, _>("dish_id"),
+    });
+    record_event(&mut tx, workspace_id, EventKind::RatingDeleted, Some(row.get("user_id")), Some(id), data).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    get,
+    path = "/ratings/{id}/history",
+    params(
+        ("id" = i32, Path, description = "Rating ID")
+    ),
+    responses(
+        (status = 200, description = "Earlier versions of the rating, oldest first, with what each edit changed", body = [RatingRevision]),
+        (status = 404, description = "Rating not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn get_rating_history(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<RatingRevision>>, StatusCode> {
+    let current = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM ratings WHERE id = $1 AND workspace_id = $2"
+    ))
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+    let current = rating_from_row(&current)?;
+
+    let rows = sqlx::query(
+        "SELECT id, rating_id, dish_id, rating, description, photo, replaced_at,
+            taste, portion, spiciness_accuracy, temperature, value, wait_time
+         FROM rating_revisions WHERE rating_id = $1
+         ORDER BY replaced_at, id"
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    if result.rows_affected() == 0 {
-        Err(StatusCode::NOT_FOUND)
-    } else {
-        Ok(StatusCode::NO_CONTENT)
+    let mut revisions: Vec<RatingRevision> = rows.iter()
+        .map(|row| RatingRevision {
+            id: row.get("id"),
+            rating_id: row.get("rating_id"),
+            dish_id: row.get("dish_id"),
+            rating: row.get("rating"),
+            description: row.get("description"),
+            photo: row.get("photo"),
+            sub_scores: SubScores {
+                taste: row.get("taste"),
+                portion: row.get("portion"),
+                spiciness_accuracy: row.get("spiciness_accuracy"),
+                temperature: row.get("temperature"),
+                value: row.get("value"),
+                wait_time: row.get("wait_time"),
+            },
+            replaced_at: row.get("replaced_at"),
+            changed: Vec::new(),
+        })
+        .collect();
+
+    // Each revision was replaced by the next one, the last by the rating as it is now
+    let mut after = MealRating::from(&current);
+    for revision in revisions.iter_mut().rev() {
+        let before = MealRating::from(&*revision);
+        revision.changed = changed_fields(&before, &after);
+        after = before;
     }
-}
\ No newline at end of file
+
+    Ok(Json(revisions))
+}
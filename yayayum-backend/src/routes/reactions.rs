@@ -0,0 +1,177 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::get, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{CreateReaction, MostReactedQuery, Rating, Reaction};
+use crate::models::reaction::DEFAULT_MOST_REACTED_LIMIT;
+use crate::routes::ratings::{rating_exists, rating_from_row, RATING_COLUMNS};
+use crate::routes::violates;
+use crate::routes::workspaces::{CurrentUser, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/ratings/{id}/reactions", get(get_reactions).post(create_reaction).delete(remove_reaction))
+        .route("/ratings/most-reacted", get(get_most_reacted))
+}
+
+fn reaction_from_row(row: &PgRow) -> Reaction {
+    Reaction {
+        rating_id: row.get("rating_id"),
+        user_id: row.get("user_id"),
+        username: row.get("username"),
+        emoji: row.get("emoji"),
+        created_at: row.get("created_at"),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/ratings/{id}/reactions",
+    params(
+        ("id" = i32, Path, description = "Rating ID")
+    ),
+    responses(
+        (status = 200, description = "Who reacted to the rating with what, oldest first", body = [Reaction]),
+        (status = 404, description = "Rating not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn get_reactions(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<Reaction>>, StatusCode> {
+    rating_exists(&pool, workspace_id, id).await?;
+
+    let rows = sqlx::query(
+        "SELECT r.rating_id, r.user_id, u.username, r.emoji, r.created_at
+         FROM rating_reactions r JOIN users u ON u.id = r.user_id
+         WHERE r.rating_id = $1
+         ORDER BY r.created_at"
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rows.iter().map(reaction_from_row).collect()))
+}
+
+#[utoipa::path(
+    post,
+    path = "/ratings/{id}/reactions",
+    params(
+        ("id" = i32, Path, description = "Rating ID")
+    ),
+    request_body = CreateReaction,
+    responses(
+        (status = 201, description = "Reaction added by the current user", body = Reaction),
+        (status = 400, description = "Bad request - not an emoji"),
+        (status = 404, description = "Rating not found"),
+        (status = 409, description = "Conflict - user already reacted with that emoji")
+    ),
+    tag = "ratings"
+)]
+pub async fn create_reaction(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateReaction>,
+) -> Result<(StatusCode, Json<Reaction>), StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    rating_exists(&pool, current_user.workspace_id, id).await?;
+
+    let row = sqlx::query(
+        "WITH inserted AS (
+            INSERT INTO rating_reactions (rating_id, user_id, emoji) VALUES ($1, $2, $3)
+            RETURNING rating_id, user_id, emoji, created_at
+         )
+         SELECT i.rating_id, i.user_id, u.username, i.emoji, i.created_at
+         FROM inserted i JOIN users u ON u.id = i.user_id"
+    )
+    .bind(id)
+    .bind(current_user.id)
+    .bind(&payload.emoji)
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        // One reaction per emoji per user
+        if violates(&e, "rating_reactions_pkey") {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+
+    Ok((StatusCode::CREATED, Json(reaction_from_row(&row))))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/ratings/{id}/reactions",
+    params(
+        ("id" = i32, Path, description = "Rating ID"),
+        CreateReaction
+    ),
+    responses(
+        (status = 204, description = "The current user's reaction removed"),
+        (status = 404, description = "Rating or reaction not found")
+    ),
+    tag = "ratings"
+)]
+pub async fn remove_reaction(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+    Query(query): Query<CreateReaction>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query(
+        "DELETE FROM rating_reactions
+         WHERE rating_id = (SELECT id FROM ratings WHERE id = $1 AND workspace_id = $2) AND user_id = $3 AND emoji = $4"
+    )
+    .bind(id)
+    .bind(current_user.workspace_id)
+    .bind(current_user.id)
+    .bind(&query.emoji)
+    .execute(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/ratings/most-reacted",
+    params(MostReactedQuery),
+    responses((status = 200, description = "Ratings with the most reactions, ties broken by newest", body = [Rating])),
+    tag = "ratings"
+)]
+pub async fn get_most_reacted(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<MostReactedQuery>,
+) -> Result<Json<Vec<Rating>>, StatusCode> {
+    let rows = sqlx::query(&format!(
+        "SELECT {RATING_COLUMNS} FROM (
+            SELECT ratings.*, COUNT(*) AS reaction_total
+            FROM ratings JOIN rating_reactions r ON r.rating_id = ratings.id
+            WHERE ratings.workspace_id = $1 AND ($2::text IS NULL OR r.emoji = $2)
+            GROUP BY ratings.id
+         ) AS ratings
+         ORDER BY reaction_total DESC, date DESC
+         LIMIT $3"
+    ))
+    .bind(workspace_id)
+    .bind(&query.emoji)
+    .bind(query.limit.unwrap_or(DEFAULT_MOST_REACTED_LIMIT).max(0))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rows.iter().map(rating_from_row).collect::<Result<Vec<_>, _>>()?))
+}
@@ -0,0 +1,198 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::Json, routing::{delete, get}, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::mailer::Mailer;
+use crate::models::{CreateHoliday, EventKind, Holiday, ReminderChannel};
+use crate::models::reminder::{reminder_html, reminder_message, REMINDER_SUBJECT};
+use crate::routes::feed::record_event;
+use crate::routes::notifications::notify;
+use crate::routes::violates;
+use crate::routes::workspaces::WorkspaceAdmin;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/admin/holidays", get(get_holidays).post(create_holiday))
+        .route("/admin/holidays/{id}", delete(remove_holiday))
+}
+
+fn holiday_from_row(row: &PgRow) -> Holiday {
+    Holiday {
+        id: row.get("id"),
+        date: row.get("date"),
+        name: row.get("name"),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/holidays",
+    request_body = CreateHoliday,
+    responses(
+        (status = 201, description = "Holiday added, nobody is reminded to rate that day", body = Holiday),
+        (status = 400, description = "Bad request - the name is empty"),
+        (status = 403, description = "Forbidden - only workspace admins can manage holidays"),
+        (status = 409, description = "Conflict - there's already a holiday on that date")
+    ),
+    tag = "reminders"
+)]
+pub async fn create_holiday(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Json(payload): Json<CreateHoliday>,
+) -> Result<(StatusCode, Json<Holiday>), StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let row = sqlx::query("INSERT INTO holidays (workspace_id, date, name) VALUES ($1, $2, $3) RETURNING id, date, name")
+        .bind(workspace_id)
+        .bind(payload.date)
+        .bind(payload.name.trim())
+        .fetch_one(&pool)
+        .await
+        .map_err(|err| {
+            if violates(&err, "holidays_workspace_id_date_key") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    Ok((StatusCode::CREATED, Json(holiday_from_row(&row))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/holidays",
+    responses(
+        (status = 200, description = "List holidays by date", body = [Holiday]),
+        (status = 403, description = "Forbidden - only workspace admins can manage holidays")
+    ),
+    tag = "reminders"
+)]
+pub async fn get_holidays(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+) -> Result<Json<Vec<Holiday>>, StatusCode> {
+    let rows = sqlx::query("SELECT id, date, name FROM holidays WHERE workspace_id = $1 ORDER BY date")
+        .bind(workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(rows.iter().map(holiday_from_row).collect()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/holidays/{id}",
+    params(
+        ("id" = i32, Path, description = "Holiday ID to delete")
+    ),
+    responses(
+        (status = 204, description = "Holiday deleted"),
+        (status = 403, description = "Forbidden - only workspace admins can manage holidays"),
+        (status = 404, description = "Holiday not found")
+    ),
+    tag = "reminders"
+)]
+pub async fn remove_holiday(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query("DELETE FROM holidays WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Reminds everyone who hasn't rated today's lunch once their workspace's
+/// reminder time has passed, skipping weekends, holidays and people who opted
+/// out. Each person is reminded at most once a day, so a retry only reaches
+/// the ones that failed.
+pub(crate) async fn send_due_reminders(pool: &PgPool, mailer: &Mailer) -> Result<String, String> {
+    // "Today" is the server's date, like rating dates
+    let rows = sqlx::query(
+        "SELECT u.id, u.workspace_id, u.username, u.email, u.chat_user_id, u.reminder_channel, CURRENT_DATE AS today,
+                EXISTS (SELECT 1 FROM webhooks wh
+                        WHERE wh.workspace_id = u.workspace_id AND wh.active
+                          AND wh.event_types::jsonb @> jsonb_build_array($1::jsonb)) AS webhook_subscribed
+         FROM users u
+         JOIN workspaces w ON w.id = u.workspace_id
+         WHERE u.rating_reminders
+           AND w.reminder_time IS NOT NULL AND LOCALTIME >= w.reminder_time
+           AND EXTRACT(ISODOW FROM CURRENT_DATE) < 6
+           AND NOT EXISTS (SELECT 1 FROM holidays h WHERE h.workspace_id = u.workspace_id AND h.date = CURRENT_DATE)
+           AND NOT EXISTS (SELECT 1 FROM ratings r WHERE r.user_id = u.id AND r.date::date = CURRENT_DATE)
+           AND NOT EXISTS (SELECT 1 FROM sent_reminders s WHERE s.user_id = u.id AND s.date = CURRENT_DATE)
+         ORDER BY u.id"
+    )
+    .bind(serde_json::to_string(&EventKind::RatingReminder).map_err(|err| err.to_string())?)
+    .fetch_all(pool)
+    .await
+    .map_err(|err| err.to_string())?;
+
+    let mut sent = 0;
+    let mut failed = 0;
+    for row in &rows {
+        match remind(pool, mailer, row).await {
+            Ok(()) => sent += 1,
+            Err(_) => failed += 1,
+        }
+    }
+
+    let summary = format!("{sent} reminded, {failed} failed");
+    if failed > 0 { Err(summary) } else { Ok(summary) }
+}
+
+async fn remind(pool: &PgPool, mailer: &Mailer, row: &PgRow) -> Result<(), StatusCode> {
+    let user_id: i32 = row.get("id");
+    let workspace_id: i32 = row.get("workspace_id");
+    let today: chrono::NaiveDate = row.get("today");
+    let message = reminder_message(&row.get::<String, _>("username"));
+    let mut channel: ReminderChannel = serde_json::from_str(&row.get::<String, _>("reminder_channel"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if channel == ReminderChannel::Email && !mailer.is_enabled() {
+        channel = ReminderChannel::InApp;
+    }
+    // Nothing would deliver the event, so it would never reach them
+    if channel == ReminderChannel::Webhook && !row.get::<bool, _>("webhook_subscribed") {
+        channel = ReminderChannel::InApp;
+    }
+
+    // Recorded with the reminder itself, or after the email went out
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    match channel {
+        ReminderChannel::InApp => notify(&mut tx, workspace_id, user_id, &message).await?,
+        ReminderChannel::Email => {
+            let email: String = row.get::<Option<String>, _>("email").ok_or(StatusCode::BAD_REQUEST)?;
+            mailer.send(&email, REMINDER_SUBJECT, message.clone(), reminder_html(&message)).await?;
+        }
+        ReminderChannel::Webhook => {
+            let data = serde_json::json!({
+                "date": today,
+                "chat_user_id": row.get::<Option<String>, _>("chat_user_id"),
+                "message": message,
+            });
+            record_event(&mut tx, workspace_id, EventKind::RatingReminder, Some(user_id), Some(user_id), data).await?;
+        }
+    }
+
+    sqlx::query("INSERT INTO sent_reminders (user_id, date, channel) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING")
+        .bind(user_id)
+        .bind(today)
+        .bind(serde_json::to_string(&channel).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
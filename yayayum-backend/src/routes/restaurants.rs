@@ -0,0 +1,234 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post}, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{CreateRestaurant, Dish, DishQuery, Restaurant};
+use crate::routes::dishes::list_dishes;
+use crate::routes::violates;
+use crate::routes::workspaces::CurrentWorkspace;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/restaurants", post(create_restaurant).get(get_restaurants))
+        .route("/restaurants/{id}", get(get_restaurant).put(modify_restaurant).delete(remove_restaurant))
+        .route("/restaurants/{id}/dishes", get(get_restaurant_dishes))
+}
+
+const RESTAURANT_COLUMNS: &str = "id, name, address, opening_hours, categories";
+
+// Opening hours and categories are stored as JSON strings
+fn restaurant_from_row(row: &PgRow) -> Result<Restaurant, StatusCode> {
+    let opening_hours = serde_json::from_str(&row.get::<String, _>("opening_hours"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let categories = serde_json::from_str(&row.get::<String, _>("categories"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Restaurant {
+        id: row.get("id"),
+        name: row.get("name"),
+        address: row.get("address"),
+        opening_hours,
+        categories,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/restaurants",
+    request_body = CreateRestaurant,
+    responses((status = 201, description = "Restaurant created", body = Restaurant)),
+    tag = "restaurants"
+)]
+pub async fn create_restaurant(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Json(payload): Json<CreateRestaurant>,
+) -> Result<(StatusCode, Json<Restaurant>), StatusCode> {
+    let opening_hours_json = serde_json::to_string(&payload.opening_hours)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let categories_json = serde_json::to_string(&payload.categories)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!(
+        "INSERT INTO restaurants (name, address, opening_hours, categories, workspace_id) VALUES ($1, $2, $3, $4, $5)
+         RETURNING {RESTAURANT_COLUMNS}"
+    ))
+    .bind(&payload.name)
+    .bind(&payload.address)
+    .bind(&opening_hours_json)
+    .bind(&categories_json)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(restaurant_from_row(&row)?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurants",
+    responses((status = 200, description = "List restaurants", body = [Restaurant])),
+    tag = "restaurants"
+)]
+pub async fn get_restaurants(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Vec<Restaurant>>, StatusCode> {
+    let rows = sqlx::query(&format!("SELECT {RESTAURANT_COLUMNS} FROM restaurants WHERE workspace_id = $1 ORDER BY id"))
+        .bind(workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let restaurants = rows.iter().map(restaurant_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(restaurants))
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurants/{id}",
+    params(
+        ("id" = i32, Path, description = "Restaurant ID")
+    ),
+    responses(
+        (status = 200, description = "Restaurant found", body = Restaurant),
+        (status = 404, description = "Restaurant not found")
+    ),
+    tag = "restaurants"
+)]
+pub async fn get_restaurant(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<Json<Restaurant>, StatusCode> {
+    let row = sqlx::query(&format!("SELECT {RESTAURANT_COLUMNS} FROM restaurants WHERE id = $1 AND workspace_id = $2"))
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(restaurant_from_row(&row)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/restaurants/{id}",
+    request_body = CreateRestaurant,
+    params(
+        ("id" = i32, Path, description = "Restaurant ID to modify")
+    ),
+    responses(
+        (status = 200, description = "Restaurant updated successfully", body = Restaurant),
+        (status = 404, description = "Restaurant not found")
+    ),
+    tag = "restaurants"
+)]
+pub async fn modify_restaurant(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateRestaurant>,
+) -> Result<Json<Restaurant>, StatusCode> {
+    let opening_hours_json = serde_json::to_string(&payload.opening_hours)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let categories_json = serde_json::to_string(&payload.categories)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!(
+        "UPDATE restaurants SET name = $1, address = $2, opening_hours = $3, categories = $4 WHERE id = $5 AND workspace_id = $6
+         RETURNING {RESTAURANT_COLUMNS}"
+    ))
+    .bind(&payload.name)
+    .bind(&payload.address)
+    .bind(&opening_hours_json)
+    .bind(&categories_json)
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    Ok(Json(restaurant_from_row(&row)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/restaurants/{id}",
+    params(
+        ("id" = i32, Path, description = "Restaurant ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Restaurant deleted successfully"),
+        (status = 404, description = "Restaurant not found"),
+        (status = 409, description = "Conflict - restaurant still has dishes")
+    ),
+    tag = "restaurants"
+)]
+pub async fn remove_restaurant(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query("DELETE FROM restaurants WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&pool)
+        .await
+        .map_err(|err| {
+            // Dishes restrict deleting a restaurant that still has a menu
+            if violates(&err, "dishes_restaurant_id_fkey") {
+                StatusCode::CONFLICT
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        })?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/restaurants/{id}/dishes",
+    params(
+        ("id" = i32, Path, description = "Restaurant ID"),
+        ("for_user" = Option<i32>, Query, description = "Check dishes against this user's dietary preferences"),
+        ("hide_unsuitable" = Option<bool>, Query, description = "Leave out dishes the user can't or won't eat instead of flagging them")
+    ),
+    responses(
+        (status = 200, description = "The restaurant's dishes", body = [Dish]),
+        (status = 404, description = "Restaurant or user not found")
+    ),
+    tag = "restaurants"
+)]
+pub async fn get_restaurant_dishes(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(id): Path<i32>,
+    Query(mut query): Query<DishQuery>,
+) -> Result<Json<Vec<Dish>>, StatusCode> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM restaurants WHERE id = $1 AND workspace_id = $2)")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    query.restaurant_id = Some(id);
+
+    Ok(Json(list_dishes(&pool, workspace_id, &query).await?))
+}
@@ -0,0 +1,418 @@
+use std::collections::HashMap;
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::get, Router};
+use sqlx::{PgPool, Row};
+use crate::models::{CategoryRanking, ControversialDish, ControversialQuery, DishOpinion, DishQuery, DishStats, DishStatsQuery, LeaderboardEntry, RankedDish, RankingMethod, RatingScale, Recommendation, RecommendationQuery, StatsQuery, SubScoreAverages, TopDishes, TopDishesQuery};
+use crate::models::recommendation::{predict_scores, DEFAULT_RECOMMENDATION_LIMIT};
+use crate::models::stats::{bayesian_score, longest_streak, wilson_score, Prior, DEFAULT_MIN_REVIEWS, DEFAULT_PRIOR_WEIGHT};
+use crate::routes::dishes::list_dishes;
+use crate::routes::workspaces::{fetch_rating_scale, CurrentWorkspace};
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/stats/dishes", get(get_dish_stats))
+        .route("/stats/leaderboard", get(get_leaderboard))
+        .route("/stats/top-dishes", get(get_top_dishes))
+        .route("/stats/controversial", get(get_controversial_dishes))
+        .route("/users/{user_id}/recommendations", get(get_recommendations))
+}
+
+// The workspace's ratings ($1) with the score to use for each. When
+// normalising ($2) that's a z-score against the rater's own ratings, and
+// raters who always give the same score land on 0.
+const SCORED_RATINGS: &str = "scored_ratings AS (
+    SELECT r.id, r.dish_id, r.user_id,
+        CASE WHEN $2 THEN COALESCE((r.rating - AVG(r.rating) OVER rater) / NULLIF(STDDEV_POP(r.rating) OVER rater, 0), 0)
+             ELSE r.rating END AS score
+    FROM ratings r
+    WHERE r.workspace_id = $1
+    WINDOW rater AS (PARTITION BY r.user_id)
+)";
+
+#[utoipa::path(
+    get,
+    path = "/stats/dishes",
+    params(DishStatsQuery),
+    responses((status = 200, description = "Rating count and average per dish, best first", body = [DishStats])),
+    tag = "stats"
+)]
+pub async fn get_dish_stats(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<DishStatsQuery>,
+) -> Result<Json<Vec<DishStats>>, StatusCode> {
+    let rows = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT d.id, d.restaurant_id, d.nr, d.name, d.category,
+                COUNT(r.id) AS rating_count, AVG(s.score)::float8 AS average_rating, MAX(r.date) AS last_rated_at,
+                AVG(r.taste)::float8 AS taste, AVG(r.portion)::float8 AS portion,
+                AVG(r.spiciness_accuracy)::float8 AS spiciness_accuracy, AVG(r.temperature)::float8 AS temperature,
+                AVG(r.value)::float8 AS value, AVG(r.wait_time)::float8 AS wait_time
+         FROM dishes d
+         LEFT JOIN ratings r ON r.dish_id = d.id
+         LEFT JOIN scored_ratings s ON s.id = r.id
+         WHERE d.workspace_id = $1 AND ($3::int IS NULL OR d.restaurant_id = $3)
+         GROUP BY d.id
+         ORDER BY average_rating DESC NULLS LAST, rating_count DESC, d.nr"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(query.restaurant_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut stats = Vec::new();
+    for row in rows {
+        let category = serde_json::from_str(&row.get::<String, _>("category"))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+        stats.push(DishStats {
+            dish_id: row.get("id"),
+            restaurant_id: row.get("restaurant_id"),
+            nr: row.get("nr"),
+            name: row.get("name"),
+            category,
+            rating_count: row.get("rating_count"),
+            average_rating: row.get("average_rating"),
+            last_rated_at: row.get("last_rated_at"),
+            sub_score_averages: SubScoreAverages {
+                taste: row.get("taste"),
+                portion: row.get("portion"),
+                spiciness_accuracy: row.get("spiciness_accuracy"),
+                temperature: row.get("temperature"),
+                value: row.get("value"),
+                wait_time: row.get("wait_time"),
+            },
+        });
+    }
+
+    Ok(Json(stats))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/leaderboard",
+    params(StatsQuery),
+    responses((status = 200, description = "Users ranked by number of reviews, then unique dishes", body = [LeaderboardEntry])),
+    tag = "stats"
+)]
+pub async fn get_leaderboard(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<StatsQuery>,
+) -> Result<Json<Vec<LeaderboardEntry>>, StatusCode> {
+    let rows = sqlx::query(
+        "SELECT u.id, u.username, COUNT(r.id) AS total_reviews, COUNT(DISTINCT r.dish_id) AS unique_dishes,
+                AVG(r.rating)::float8 AS average_rating
+         FROM users u
+         LEFT JOIN ratings r ON r.user_id = u.id
+             AND r.dish_id IN (SELECT id FROM dishes WHERE $1::int IS NULL OR restaurant_id = $1)
+         WHERE u.workspace_id = $2
+         GROUP BY u.id
+         ORDER BY total_reviews DESC, unique_dishes DESC, u.username"
+    )
+    .bind(query.restaurant_id)
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let date_rows = sqlx::query(
+        "SELECT DISTINCT r.user_id, DATE(r.date) AS day
+         FROM ratings r JOIN dishes d ON d.id = r.dish_id
+         WHERE d.workspace_id = $2 AND ($1::int IS NULL OR d.restaurant_id = $1)
+         ORDER BY r.user_id, day"
+    )
+    .bind(query.restaurant_id)
+    .bind(workspace_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut days: HashMap<i32, Vec<chrono::NaiveDate>> = HashMap::new();
+    for row in date_rows {
+        days.entry(row.get("user_id")).or_default().push(row.get("day"));
+    }
+
+    let leaderboard = rows.iter()
+        .map(|row| {
+            let user_id: i32 = row.get("id");
+            LeaderboardEntry {
+                user_id,
+                username: row.get("username"),
+                total_reviews: row.get("total_reviews"),
+                unique_dishes: row.get("unique_dishes"),
+                average_rating: row.get("average_rating"),
+                longest_streak: days.get(&user_id).map_or(0, |d| longest_streak(d)),
+            }
+        })
+        .collect();
+
+    Ok(Json(leaderboard))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/top-dishes",
+    params(TopDishesQuery),
+    responses(
+        (status = 200, description = "Rated dishes ranked by a confidence-adjusted score, overall and per category", body = TopDishes),
+        (status = 400, description = "Bad request - negative prior weight, prior mean off the rating scale, or Wilson on normalized scores")
+    ),
+    tag = "stats"
+)]
+pub async fn get_top_dishes(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<TopDishesQuery>,
+) -> Result<Json<TopDishes>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let scale = fetch_rating_scale(&mut conn, workspace_id).await?;
+
+    // Normalised scores have no bounds, so only the scale-free Bayesian average applies
+    let bounded_scale = (!query.normalized).then_some(&scale);
+    let prior_weight = query.prior_weight.unwrap_or(DEFAULT_PRIOR_WEIGHT);
+    let prior_mean_in_range = query.prior_mean.is_none_or(|mean| match bounded_scale {
+        Some(scale) => (RatingScale::MIN..=f64::from(scale.max)).contains(&mean),
+        None => mean.is_finite(),
+    });
+    let wilson_on_normalized = query.normalized && query.method == RankingMethod::Wilson;
+    if prior_weight < 0.0 || !prior_mean_in_range || wilson_on_normalized {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let rows = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT d.id, d.restaurant_id, d.nr, d.name, d.category, COUNT(s.id) AS rating_count,
+                AVG(s.score)::float8 AS average_rating, VAR_POP(s.score)::float8 AS variance
+         FROM dishes d JOIN scored_ratings s ON s.dish_id = d.id
+         WHERE d.workspace_id = $1 AND ($3::int IS NULL OR d.restaurant_id = $3)
+         GROUP BY d.id"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(query.restaurant_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // The prior is based on every rating the ranking covers
+    let overall = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT AVG(s.score)::float8 AS mean, VAR_POP(s.score)::float8 AS variance
+         FROM scored_ratings s JOIN dishes d ON d.id = s.dish_id
+         WHERE $3::int IS NULL OR d.restaurant_id = $3"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(query.restaurant_id)
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let default_mean = if query.normalized { 0.0 } else { (RatingScale::MIN + f64::from(scale.max)) / 2.0 };
+    let prior = Prior {
+        mean: query.prior_mean
+            .or(overall.get("mean"))
+            .unwrap_or(default_mean),
+        weight: prior_weight,
+        variance: overall.get::<Option<f64>, _>("variance").unwrap_or(0.0),
+    };
+
+    let mut ranked = Vec::new();
+    for row in rows {
+        let category = serde_json::from_str(&row.get::<String, _>("category"))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let rating_count: i64 = row.get("rating_count");
+        let average_rating: f64 = row.get("average_rating");
+
+        let (adjusted_score, confidence_interval) = match query.method {
+            RankingMethod::Bayesian => bayesian_score(rating_count, average_rating, row.get("variance"), &prior, bounded_scale),
+            RankingMethod::Wilson => wilson_score(rating_count, average_rating, &scale),
+        };
+
+        ranked.push(RankedDish {
+            dish_id: row.get("id"),
+            restaurant_id: row.get("restaurant_id"),
+            nr: row.get("nr"),
+            name: row.get("name"),
+            category,
+            rating_count,
+            average_rating,
+            adjusted_score,
+            confidence_interval,
+        });
+    }
+    ranked.sort_by(|a, b| {
+        b.adjusted_score.total_cmp(&a.adjusted_score)
+            .then(b.rating_count.cmp(&a.rating_count))
+            .then(a.nr.cmp(&b.nr))
+    });
+
+    let mut by_category: Vec<CategoryRanking> = Vec::new();
+    for dish in &ranked {
+        match by_category.iter_mut().find(|c| c.category == dish.category) {
+            Some(ranking) => ranking.dishes.push(dish.clone()),
+            None => by_category.push(CategoryRanking { category: dish.category.clone(), dishes: vec![dish.clone()] }),
+        }
+    }
+    by_category.sort_by(|a, b| a.category.cmp(&b.category));
+
+    Ok(Json(TopDishes {
+        method: query.method,
+        normalized: query.normalized,
+        prior_mean: prior.mean,
+        prior_weight: prior.weight,
+        overall: ranked,
+        by_category,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{user_id}/recommendations",
+    params(
+        ("user_id" = i32, Path, description = "User to recommend dishes to"),
+        RecommendationQuery
+    ),
+    responses(
+        (status = 200, description = "Dishes the user hasn't rated and can eat, best predicted first", body = [Recommendation]),
+        (status = 404, description = "User not found")
+    ),
+    tag = "stats"
+)]
+pub async fn get_recommendations(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Path(user_id): Path<i32>,
+    Query(query): Query<RecommendationQuery>,
+) -> Result<Json<Vec<Recommendation>>, StatusCode> {
+    // Only dishes that fit the user's dietary preferences are worth suggesting
+    let dishes = list_dishes(&pool, workspace_id, &DishQuery {
+        restaurant_id: query.restaurant_id,
+        for_user: Some(user_id),
+        hide_unsuitable: true,
+    }).await?;
+
+    let rows = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT user_id, dish_id, AVG(score)::float8 AS score FROM scored_ratings
+         WHERE NOT $3 OR user_id = $4 OR user_id IN (SELECT followee_id FROM user_follows WHERE follower_id = $4)
+         GROUP BY user_id, dish_id"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(query.following_only)
+    .bind(user_id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let scores: Vec<(i32, i32, f64)> = rows.iter()
+        .map(|row| (row.get("user_id"), row.get("dish_id"), row.get("score")))
+        .collect();
+    let mut predictions = predict_scores(user_id, &scores);
+
+    let mut recommendations: Vec<Recommendation> = dishes.into_iter()
+        .filter_map(|dish| {
+            let (predicted_score, based_on) = predictions.remove(&dish.id)?;
+            Some(Recommendation { dish, predicted_score, based_on })
+        })
+        .collect();
+    recommendations.sort_by(|a, b| {
+        b.predicted_score.total_cmp(&a.predicted_score).then(b.based_on.cmp(&a.based_on))
+    });
+    recommendations.truncate(query.limit.unwrap_or(DEFAULT_RECOMMENDATION_LIMIT));
+
+    Ok(Json(recommendations))
+}
+
+#[utoipa::path(
+    get,
+    path = "/stats/controversial",
+    params(ControversialQuery),
+    responses((status = 200, description = "Dishes that split the team, most disagreement first", body = [ControversialDish])),
+    tag = "stats"
+)]
+pub async fn get_controversial_dishes(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+    Query(query): Query<ControversialQuery>,
+) -> Result<Json<Vec<ControversialDish>>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let rows = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT d.id, d.restaurant_id, d.nr, d.name, d.category, COUNT(s.id) AS rating_count,
+                AVG(s.score)::float8 AS average_rating, VAR_POP(s.score)::float8 AS variance
+         FROM dishes d JOIN scored_ratings s ON s.dish_id = d.id
+         WHERE d.workspace_id = $1 AND ($3::int IS NULL OR d.restaurant_id = $3)
+         GROUP BY d.id
+         HAVING COUNT(s.id) >= $4
+         ORDER BY variance DESC, rating_count DESC, d.nr"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(query.restaurant_id)
+    .bind(query.min_reviews.unwrap_or(DEFAULT_MIN_REVIEWS))
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let dish_ids: Vec<i32> = rows.iter().map(|row| row.get("id")).collect();
+    let opinion_rows = sqlx::query(&format!(
+        "WITH {SCORED_RATINGS}
+         SELECT s.dish_id, u.id AS user_id, u.username, AVG(s.score)::float8 AS score
+         FROM scored_ratings s JOIN users u ON u.id = s.user_id
+         WHERE s.dish_id = ANY($3)
+         GROUP BY s.dish_id, u.id
+         ORDER BY score DESC, u.username"
+    ))
+    .bind(workspace_id)
+    .bind(query.normalized)
+    .bind(&dish_ids)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut opinions: HashMap<i32, Vec<DishOpinion>> = HashMap::new();
+    for row in opinion_rows {
+        opinions.entry(row.get("dish_id")).or_default().push(DishOpinion {
+            user_id: row.get("user_id"),
+            username: row.get("username"),
+            score: row.get("score"),
+        });
+    }
+
+    let mut dishes = Vec::new();
+    for row in rows {
+        let category = serde_json::from_str(&row.get::<String, _>("category"))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let average_rating: f64 = row.get("average_rating");
+        let variance: f64 = row.get("variance");
+
+        // Opinions come sorted fondest first, haters are listed harshest first
+        let (lovers, mut haters): (Vec<_>, Vec<_>) = opinions.remove(&row.get::<i32, _>("id"))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|opinion| opinion.score != average_rating)
+            .partition(|opinion| opinion.score > average_rating);
+        haters.reverse();
+
+        dishes.push(ControversialDish {
+            dish_id: row.get("id"),
+            restaurant_id: row.get("restaurant_id"),
+            nr: row.get("nr"),
+            name: row.get("name"),
+            category,
+            rating_count: row.get("rating_count"),
+            average_rating,
+            variance,
+            standard_deviation: variance.sqrt(),
+            lovers,
+            haters,
+        });
+    }
+
+    Ok(Json(dishes))
+}
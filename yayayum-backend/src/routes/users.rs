@@ -1,83 +1,310 @@
-use axum::{routing::post, extract::{State, Path}, http::StatusCode, Json, Router};
-use sqlx::PgPool;
-use crate::models::{CreateUser, User};
+use axum::{routing::{get, post}, extract::{State, Path}, http::StatusCode, Json, Router};
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use crate::models::{AccessToken, CreateUser, EventKind, UpdateUser, User};
+use crate::routes::feed::record_event;
+use crate::routes::violates;
+use crate::routes::workspaces::{issue_token, CurrentUser, ServerAdmin, WorkspaceAdmin};
 
 pub fn routes() -> Router<PgPool> {
     Router::new()
         .route("/users", post(create_user).get(get_users))
+        .route("/users/me", get(get_current_user))
         .route("/users/{id}", axum::routing::put(modify_user).delete(remove_user))
+        .route("/users/{id}/token", post(create_user_token))
+        .route("/admin/users/{id}/token", post(create_admin_token))
+        .route("/users/{id}/followers", get(get_followers))
+        .route("/users/{id}/following", get(get_following))
+        .route("/users/{id}/following/{followee_id}", axum::routing::put(follow_user).delete(unfollow_user))
+}
+
+const USER_COLUMNS: &str = "u.id, u.username, u.dietary_restrictions, u.disliked_ingredients, u.chat_user_id,
+    u.email, u.weekly_digest, u.rating_reminders, u.reminder_channel, u.is_admin";
+
+async fn user_exists(pool: &PgPool, workspace_id: i32, id: i32) -> Result<(), StatusCode> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM users WHERE id = $1 AND workspace_id = $2)")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if exists { Ok(()) } else { Err(StatusCode::NOT_FOUND) }
+}
+
+/// Users listed to the viewer, without anyone else's email address.
+fn users_seen_by(rows: &[PgRow], viewer_id: i32) -> Result<Vec<User>, StatusCode> {
+    rows.iter().map(|row| Ok(user_from_row(row)?.seen_by(viewer_id))).collect()
+}
+
+// Dietary restrictions, disliked ingredients and the reminder channel are stored as JSON strings
+pub(crate) fn user_from_row(row: &PgRow) -> Result<User, StatusCode> {
+    let dietary_restrictions = serde_json::from_str(&row.get::<String, _>("dietary_restrictions"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disliked_ingredients = serde_json::from_str(&row.get::<String, _>("disliked_ingredients"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let reminder_channel = serde_json::from_str(&row.get::<String, _>("reminder_channel"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(User {
+        id: row.get("id"),
+        username: row.get("username"),
+        dietary_restrictions,
+        disliked_ingredients,
+        chat_user_id: row.get("chat_user_id"),
+        email: row.get("email"),
+        weekly_digest: row.get("weekly_digest"),
+        rating_reminders: row.get("rating_reminders"),
+        reminder_channel,
+        is_admin: row.get("is_admin"),
+    })
 }
 
 #[utoipa::path(
     post,
     path = "/users",
     request_body = CreateUser,
-    responses((status = 201, description = "User created", body = User)),
+    responses(
+        (status = 201, description = "User created, see POST /users/{id}/token to let them sign in", body = User),
+        (status = 400, description = "Bad request - invalid email, or a weekly digest or email reminders without an email"),
+        (status = 403, description = "Forbidden - only workspace admins can add users"),
+        (status = 409, description = "Conflict - the chat user is already linked to someone else")
+    ),
     tag = "users"
 )]
 pub async fn create_user(
     State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
     Json(payload): Json<CreateUser>,
 ) -> Result<(StatusCode, Json<User>), StatusCode> {
-    let row = sqlx::query_as::<_, User>(
-        "INSERT INTO users (username) VALUES ($1) RETURNING id, username"
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let user = insert_user(&mut tx, workspace_id, &payload, false).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(user)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/users/{id}/token",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "New session token for the user, their old one stops working", body = AccessToken),
+        (status = 403, description = "Forbidden - only workspace admins can hand out tokens"),
+        (status = 404, description = "User not found")
+    ),
+    tag = "users"
+)]
+pub async fn create_user_token(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+) -> Result<Json<AccessToken>, StatusCode> {
+    let mut conn = pool.acquire().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let token = issue_token(&mut conn, Some(workspace_id), id).await?;
+
+    Ok(Json(AccessToken { user_id: id, token }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/users/{id}/token",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "New session token for the user, who is made an admin of their workspace", body = AccessToken),
+        (status = 401, description = "Unauthorized - missing or wrong admin token"),
+        (status = 404, description = "User not found")
+    ),
+    security(("admin" = [])),
+    tag = "users"
+)]
+pub async fn create_admin_token(
+    State(pool): State<PgPool>,
+    _admin: ServerAdmin,
+    Path(id): Path<i32>,
+) -> Result<Json<AccessToken>, StatusCode> {
+    // For operators to hand out the first admin of a workspace that has none,
+    // like the one everything before workspaces ended up in
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let token = issue_token(&mut tx, None, id).await?;
+    sqlx::query("UPDATE users SET is_admin = TRUE WHERE id = $1")
+        .bind(id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(AccessToken { user_id: id, token }))
+}
+
+pub(crate) async fn insert_user(conn: &mut PgConnection, workspace_id: i32, payload: &CreateUser, is_admin: bool) -> Result<User, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let dietary_restrictions_json = serde_json::to_string(&payload.dietary_restrictions)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disliked_ingredients_json = serde_json::to_string(&payload.disliked_ingredients)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let reminder_channel_json = serde_json::to_string(&payload.reminder_channel)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(
+        "INSERT INTO users (workspace_id, username, dietary_restrictions, disliked_ingredients, chat_user_id, email, weekly_digest,
+             rating_reminders, reminder_channel, is_admin)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+         RETURNING id, username, dietary_restrictions, disliked_ingredients, chat_user_id, email, weekly_digest,
+             rating_reminders, reminder_channel, is_admin"
     )
+    .bind(workspace_id)
     .bind(&payload.username)
-    .fetch_one(&pool)
+    .bind(&dietary_restrictions_json)
+    .bind(&disliked_ingredients_json)
+    .bind(&payload.chat_user_id)
+    .bind(&payload.email)
+    .bind(payload.weekly_digest)
+    .bind(payload.rating_reminders)
+    .bind(&reminder_channel_json)
+    .bind(is_admin)
+    .fetch_one(&mut *conn)
     .await
-    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    .map_err(|err| {
+        // Each chat user can only be linked to one user per workspace
+        if violates(&err, "idx_users_workspace_chat_user_unique") {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    })?;
+    let user = user_from_row(&row)?;
+
+    let data = serde_json::json!({ "username": user.username });
+    record_event(conn, workspace_id, EventKind::UserJoined, Some(user.id), Some(user.id), data).await?;
 
-    Ok((StatusCode::CREATED, Json(row)))
+    Ok(user)
 }
 
 #[utoipa::path(
     get,
     path = "/users",
-    responses((status = 200, description = "List users", body = [User])),
+    responses((status = 200, description = "List users, only showing the caller's own email", body = [User])),
     tag = "users"
 )]
 pub async fn get_users(
     State(pool): State<PgPool>,
+    current_user: CurrentUser,
 ) -> Result<Json<Vec<User>>, StatusCode> {
-    let rows = sqlx::query_as::<_, User>("SELECT id, username FROM users")
+    let rows = sqlx::query(&format!("SELECT {USER_COLUMNS} FROM users u WHERE u.workspace_id = $1"))
+        .bind(current_user.workspace_id)
         .fetch_all(&pool)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(rows))
+    Ok(Json(users_seen_by(&rows, current_user.id)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/me",
+    responses((status = 200, description = "The caller's own record, including their email", body = User)),
+    tag = "users"
+)]
+pub async fn get_current_user(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+) -> Result<Json<User>, StatusCode> {
+    let row = sqlx::query(&format!("SELECT {USER_COLUMNS} FROM users u WHERE u.id = $1"))
+        .bind(current_user.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(user_from_row(&row)?))
 }
 
 #[utoipa::path(
     put,
     path = "/users/{id}",
-    request_body = CreateUser,
+    request_body = UpdateUser,
     params(
         ("id" = i64, Path, description = "User ID to modify")
     ),
     responses(
-        (status = 200, description = "User updated successfully", body = User),
-        (status = 404, description = "User not found")
+        (status = 200, description = "User updated, keeping anything left out", body = User),
+        (status = 400, description = "Bad request - invalid email, or a weekly digest or email reminders without an email"),
+        (status = 403, description = "Forbidden - only admins can change someone else or the chat user, others link it from the chat"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Conflict - the chat user is already linked to someone else")
     ),
     tag = "users"
 )]
 pub async fn modify_user(
     State(pool): State<PgPool>,
+    current_user: CurrentUser,
     Path(id): Path<i32>,
-    Json(payload): Json<CreateUser>,
+    Json(payload): Json<UpdateUser>,
 ) -> Result<Json<User>, StatusCode> {
-    let row = sqlx::query_as::<_, User>(
-        "UPDATE users SET username = $1 WHERE id = $2 RETURNING id, username"
+    if current_user.id != id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let workspace_id = current_user.workspace_id;
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let row = sqlx::query(&format!("SELECT {USER_COLUMNS} FROM users u WHERE u.id = $1 AND u.workspace_id = $2 FOR UPDATE"))
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let user = user_from_row(&row)?;
+
+    // Otherwise anyone could take over someone's chat account, see POST /chat/link-code
+    if !current_user.is_admin && payload.chat_user_id.as_ref().is_some_and(|chat_user_id| *chat_user_id != user.chat_user_id) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let payload = payload.apply_to(user);
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let dietary_restrictions_json = serde_json::to_string(&payload.dietary_restrictions)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let disliked_ingredients_json = serde_json::to_string(&payload.disliked_ingredients)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let reminder_channel_json = serde_json::to_string(&payload.reminder_channel)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(
+        "UPDATE users SET username = $1, dietary_restrictions = $2, disliked_ingredients = $3, chat_user_id = $6,
+            email = $7, weekly_digest = $8, rating_reminders = $9, reminder_channel = $10
+         WHERE id = $4 AND workspace_id = $5
+         RETURNING id, username, dietary_restrictions, disliked_ingredients, chat_user_id, email, weekly_digest,
+             rating_reminders, reminder_channel, is_admin"
     )
     .bind(&payload.username)
+    .bind(&dietary_restrictions_json)
+    .bind(&disliked_ingredients_json)
     .bind(id)
-    .fetch_one(&pool)
+    .bind(workspace_id)
+    .bind(&payload.chat_user_id)
+    .bind(&payload.email)
+    .bind(payload.weekly_digest)
+    .bind(payload.rating_reminders)
+    .bind(&reminder_channel_json)
+    .fetch_one(&mut *tx)
     .await
-    .map_err(|err| match err {
-        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
-        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    .map_err(|err| {
+        if violates(&err, "idx_users_workspace_chat_user_unique") {
+            StatusCode::CONFLICT
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
     })?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
 
-    Ok(Json(row))
+    Ok(Json(user_from_row(&row)?.seen_by(current_user.id)))
 }
 
 #[utoipa::path(
@@ -88,16 +315,161 @@ pub async fn modify_user(
     ),
     responses(
         (status = 204, description = "User deleted successfully"),
+        (status = 403, description = "Forbidden - only admins can remove someone else"),
         (status = 404, description = "User not found")
     ),
     tag = "users"
 )]
 pub async fn remove_user(
     State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    // Anyone can leave, only admins can remove others
+    if current_user.id != id && !current_user.is_admin {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let result = sqlx::query("DELETE FROM users WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(current_user.workspace_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/followers",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Users following this user", body = [User]),
+        (status = 404, description = "User not found")
+    ),
+    tag = "users"
+)]
+pub async fn get_followers(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
     Path(id): Path<i32>,
+) -> Result<Json<Vec<User>>, StatusCode> {
+    user_exists(&pool, current_user.workspace_id, id).await?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT {USER_COLUMNS} FROM user_follows f JOIN users u ON u.id = f.follower_id
+         WHERE f.followee_id = $1 ORDER BY u.username"
+    ))
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(users_seen_by(&rows, current_user.id)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/users/{id}/following",
+    params(
+        ("id" = i32, Path, description = "User ID")
+    ),
+    responses(
+        (status = 200, description = "Users this user follows", body = [User]),
+        (status = 404, description = "User not found")
+    ),
+    tag = "users"
+)]
+pub async fn get_following(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<User>>, StatusCode> {
+    user_exists(&pool, current_user.workspace_id, id).await?;
+
+    let rows = sqlx::query(&format!(
+        "SELECT {USER_COLUMNS} FROM user_follows f JOIN users u ON u.id = f.followee_id
+         WHERE f.follower_id = $1 ORDER BY u.username"
+    ))
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(users_seen_by(&rows, current_user.id)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/users/{id}/following/{followee_id}",
+    params(
+        ("id" = i32, Path, description = "User who follows"),
+        ("followee_id" = i32, Path, description = "User to follow")
+    ),
+    responses(
+        (status = 204, description = "Following the user, also if they already were"),
+        (status = 400, description = "Bad request - users can't follow themselves"),
+        (status = 403, description = "Forbidden - users can only choose who they follow themselves"),
+        (status = 404, description = "User not found")
+    ),
+    tag = "users"
+)]
+pub async fn follow_user(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, followee_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, StatusCode> {
+    if current_user.id != id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if id == followee_id {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    user_exists(&pool, current_user.workspace_id, followee_id).await?;
+
+    sqlx::query("INSERT INTO user_follows (follower_id, followee_id) VALUES ($1, $2) ON CONFLICT DO NOTHING")
+        .bind(id)
+        .bind(followee_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[utoipa::path(
+    delete,
+    path = "/users/{id}/following/{followee_id}",
+    params(
+        ("id" = i32, Path, description = "User who follows"),
+        ("followee_id" = i32, Path, description = "User to stop following")
+    ),
+    responses(
+        (status = 204, description = "No longer following the user"),
+        (status = 403, description = "Forbidden - users can only choose who they follow themselves"),
+        (status = 404, description = "User not found, or not following them")
+    ),
+    tag = "users"
+)]
+pub async fn unfollow_user(
+    State(pool): State<PgPool>,
+    current_user: CurrentUser,
+    Path((id, followee_id)): Path<(i32, i32)>,
 ) -> Result<StatusCode, StatusCode> {
-    let result = sqlx::query("DELETE FROM users WHERE id = $1")
+    if current_user.id != id {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let result = sqlx::query("DELETE FROM user_follows WHERE follower_id = $1 AND followee_id = $2")
         .bind(id)
+        .bind(followee_id)
         .execute(&pool)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
@@ -107,4 +479,4 @@ pub async fn remove_user(
     } else {
         Ok(StatusCode::NO_CONTENT)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,344 @@
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Json, routing::{get, post}, Extension, Router};
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::{CreateWebhook, DeliveryQuery, DeliveryStatus, Webhook, WebhookDelivery};
+use crate::models::webhook::DEFAULT_DELIVERY_LIMIT;
+use crate::routes::enum_json;
+use crate::routes::workspaces::WorkspaceAdmin;
+use crate::workers::webhooks::WebhookTargets;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/admin/webhooks", post(create_webhook).get(get_webhooks))
+        .route("/admin/webhooks/{id}", get(get_webhook).put(modify_webhook).delete(remove_webhook))
+        .route("/admin/webhooks/{id}/deliveries", get(get_webhook_deliveries))
+        .route("/admin/webhooks/{id}/deliveries/{delivery_id}/retry", post(retry_webhook_delivery))
+}
+
+const WEBHOOK_COLUMNS: &str = "id, url, event_types, active, created_at";
+
+const DELIVERY_SELECT: &str = "SELECT d.id, d.webhook_id, d.event_id, e.kind AS event_type, d.status, d.attempts,
+        d.next_attempt_at, d.last_status_code, d.last_error, d.created_at, d.delivered_at
+    FROM webhook_deliveries d JOIN events e ON e.id = d.event_id";
+
+// Event types are stored as a JSON list. The secret is left out, it's only
+// shown once when the webhook is created.
+fn webhook_from_row(row: &PgRow) -> Result<Webhook, StatusCode> {
+    let event_types = serde_json::from_str(&row.get::<String, _>("event_types"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Webhook {
+        id: row.get("id"),
+        url: row.get("url"),
+        event_types,
+        secret: None,
+        active: row.get("active"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn delivery_from_row(row: &PgRow) -> Result<WebhookDelivery, StatusCode> {
+    let event_type = serde_json::from_str(&row.get::<String, _>("event_type"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let status = serde_json::from_str(&row.get::<String, _>("status"))
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(WebhookDelivery {
+        id: row.get("id"),
+        webhook_id: row.get("webhook_id"),
+        event_id: row.get("event_id"),
+        event_type,
+        status,
+        attempts: row.get("attempts"),
+        next_attempt_at: row.get("next_attempt_at"),
+        last_status_code: row.get("last_status_code"),
+        last_error: row.get("last_error"),
+        created_at: row.get("created_at"),
+        delivered_at: row.get("delivered_at"),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks",
+    request_body = CreateWebhook,
+    responses(
+        (status = 201, description = "Webhook created, with its secret (generated unless one was given). This is the only time the secret is shown.", body = Webhook),
+        (status = 400, description = "Bad request - not an http(s) URL, not a public address, no event types or an empty secret"),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks")
+    ),
+    tag = "webhooks"
+)]
+pub async fn create_webhook(
+    State(pool): State<PgPool>,
+    Extension(targets): Extension<WebhookTargets>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Json(payload): Json<CreateWebhook>,
+) -> Result<(StatusCode, Json<Webhook>), StatusCode> {
+    if !payload.is_valid() || targets.check(&payload.url).await.is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let event_types_json = serde_json::to_string(&payload.event_types)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!(
+        "INSERT INTO webhooks (url, event_types, secret, active, workspace_id)
+         VALUES ($1, $2, COALESCE($3, replace(gen_random_uuid()::text, '-', '')), $4, $5)
+         RETURNING {WEBHOOK_COLUMNS}, secret"
+    ))
+    .bind(&payload.url)
+    .bind(&event_types_json)
+    .bind(&payload.secret)
+    .bind(payload.active)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let webhook = Webhook { secret: Some(row.get("secret")), ..webhook_from_row(&row)? };
+
+    Ok((StatusCode::CREATED, Json(webhook)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks",
+    responses(
+        (status = 200, description = "List webhooks", body = [Webhook]),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks")
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhooks(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+) -> Result<Json<Vec<Webhook>>, StatusCode> {
+    let rows = sqlx::query(&format!("SELECT {WEBHOOK_COLUMNS} FROM webhooks WHERE workspace_id = $1 ORDER BY id"))
+        .bind(workspace_id)
+        .fetch_all(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let webhooks = rows.iter().map(webhook_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(webhooks))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/{id}",
+    params(
+        ("id" = i32, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Webhook found", body = Webhook),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks"),
+        (status = 404, description = "Webhook not found")
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhook(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+) -> Result<Json<Webhook>, StatusCode> {
+    let row = sqlx::query(&format!("SELECT {WEBHOOK_COLUMNS} FROM webhooks WHERE id = $1 AND workspace_id = $2"))
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(webhook_from_row(&row)?))
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/webhooks/{id}",
+    request_body = CreateWebhook,
+    params(
+        ("id" = i32, Path, description = "Webhook ID to modify")
+    ),
+    responses(
+        (status = 200, description = "Webhook updated, keeping the secret unless a new one was given", body = Webhook),
+        (status = 400, description = "Bad request - not an http(s) URL, not a public address, no event types or an empty secret"),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks"),
+        (status = 404, description = "Webhook not found")
+    ),
+    tag = "webhooks"
+)]
+pub async fn modify_webhook(
+    State(pool): State<PgPool>,
+    Extension(targets): Extension<WebhookTargets>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateWebhook>,
+) -> Result<Json<Webhook>, StatusCode> {
+    if !payload.is_valid() || targets.check(&payload.url).await.is_err() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let event_types_json = serde_json::to_string(&payload.event_types)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!(
+        "UPDATE webhooks SET url = $1, event_types = $2, secret = COALESCE($3, secret), active = $4
+         WHERE id = $5 AND workspace_id = $6
+         RETURNING {WEBHOOK_COLUMNS}"
+    ))
+    .bind(&payload.url)
+    .bind(&event_types_json)
+    .bind(&payload.secret)
+    .bind(payload.active)
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|err| match err {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    })?;
+
+    Ok(Json(webhook_from_row(&row)?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/webhooks/{id}",
+    params(
+        ("id" = i32, Path, description = "Webhook ID to remove")
+    ),
+    responses(
+        (status = 204, description = "Webhook and its delivery log deleted"),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks"),
+        (status = 404, description = "Webhook not found")
+    ),
+    tag = "webhooks"
+)]
+pub async fn remove_webhook(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    let result = sqlx::query("DELETE FROM webhooks WHERE id = $1 AND workspace_id = $2")
+        .bind(id)
+        .bind(workspace_id)
+        .execute(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if result.rows_affected() == 0 {
+        Err(StatusCode::NOT_FOUND)
+    } else {
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/webhooks/{id}/deliveries",
+    params(
+        ("id" = i32, Path, description = "Webhook ID"),
+        DeliveryQuery
+    ),
+    responses(
+        (status = 200, description = "Delivery log, newest first", body = [WebhookDelivery]),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks"),
+        (status = 404, description = "Webhook not found")
+    ),
+    tag = "webhooks"
+)]
+pub async fn get_webhook_deliveries(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path(id): Path<i32>,
+    Query(query): Query<DeliveryQuery>,
+) -> Result<Json<Vec<WebhookDelivery>>, StatusCode> {
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM webhooks WHERE id = $1 AND workspace_id = $2)")
+        .bind(id)
+        .bind(workspace_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !exists {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let status_json = query.status.as_ref().map(enum_json).transpose()?;
+
+    let rows = sqlx::query(&format!(
+        "{DELIVERY_SELECT}
+         WHERE d.webhook_id = $1 AND ($2::text IS NULL OR d.status = $2)
+         ORDER BY d.id DESC
+         LIMIT $3"
+    ))
+    .bind(id)
+    .bind(&status_json)
+    .bind(query.limit.unwrap_or(DEFAULT_DELIVERY_LIMIT).max(1))
+    .fetch_all(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let deliveries = rows.iter().map(delivery_from_row).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Json(deliveries))
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/webhooks/{id}/deliveries/{delivery_id}/retry",
+    params(
+        ("id" = i32, Path, description = "Webhook ID"),
+        ("delivery_id" = i32, Path, description = "Delivery ID to send again")
+    ),
+    responses(
+        (status = 200, description = "Delivery queued again with a fresh set of attempts", body = WebhookDelivery),
+        (status = 403, description = "Forbidden - only workspace admins can manage webhooks"),
+        (status = 404, description = "Webhook or delivery not found"),
+        (status = 409, description = "Conflict - the delivery is still pending")
+    ),
+    tag = "webhooks"
+)]
+pub async fn retry_webhook_delivery(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+) -> Result<Json<WebhookDelivery>, StatusCode> {
+    let pending = enum_json(&DeliveryStatus::Pending)?;
+
+    let status: String = sqlx::query_scalar(
+        "SELECT d.status FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+         WHERE d.id = $1 AND d.webhook_id = $2 AND w.workspace_id = $3"
+    )
+    .bind(delivery_id)
+    .bind(id)
+    .bind(workspace_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)?;
+
+    if status == pending {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    sqlx::query(
+        "UPDATE webhook_deliveries SET status = $1, attempts = 0, next_attempt_at = NOW(), delivered_at = NULL
+         WHERE id = $2"
+    )
+    .bind(&pending)
+    .bind(delivery_id)
+    .execute(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let row = sqlx::query(&format!("{DELIVERY_SELECT} WHERE d.id = $1"))
+        .bind(delivery_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(delivery_from_row(&row)?))
+}
@@ -0,0 +1,450 @@
+use axum::{extract::{FromRef, FromRequestParts, Path, Query, State}, http::{header::AUTHORIZATION, request::Parts, StatusCode}, response::Json, routing::{get, post}, Router};
+use serde::Deserialize;
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Row};
+use subtle::ConstantTimeEq;
+use crate::models::{CreateUser, DishCategory, ModifyWorkspace, NewWorkspace, RatingScale, Workspace, WorkspaceInvite, WorkspaceMembership};
+use crate::routes::users::insert_user;
+
+pub fn routes() -> Router<PgPool> {
+    Router::new()
+        .route("/workspaces", post(create_workspace))
+        .route("/workspaces/current", get(get_current_workspace).put(modify_current_workspace))
+        .route("/workspaces/current/invite", post(renew_invite_code))
+        .route("/invites/{code}", get(get_invite))
+        .route("/invites/{code}/join", post(join_workspace))
+}
+
+/// Session tokens are two random UUIDs, so they can't be guessed. Only their
+/// hash is stored.
+const NEW_TOKEN: &str = "replace(gen_random_uuid()::text, '-', '') || replace(gen_random_uuid()::text, '-', '')";
+
+pub(crate) fn token_hash(token: &str) -> String {
+    format!("encode(sha256(convert_to({token}, 'UTF8')), 'hex')")
+}
+
+/// The user a request acts as, identified by the session token they got when
+/// joining, sent as `Authorization: Bearer <token>`. Clients that can't set
+/// headers, like a browser's EventSource, can use an `access_token` query
+/// parameter instead.
+#[derive(Clone, Copy)]
+pub struct CurrentUser {
+    pub id: i32,
+    pub workspace_id: i32,
+    pub is_admin: bool,
+}
+
+/// The workspace of the user a request acts as, see [`CurrentUser`].
+pub struct CurrentWorkspace(pub i32);
+
+/// The workspace of a request made by one of its admins, 403 for anyone else.
+pub struct WorkspaceAdmin(pub i32);
+
+#[derive(Deserialize)]
+struct TokenParam {
+    access_token: Option<String>,
+}
+
+fn session_token(parts: &Parts) -> Result<String, StatusCode> {
+    if let Some(value) = parts.headers.get(AUTHORIZATION) {
+        return value.to_str().ok()
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string())
+            .ok_or(StatusCode::UNAUTHORIZED);
+    }
+
+    Query::<TokenParam>::try_from_uri(&parts.uri)
+        .map_err(|_| StatusCode::BAD_REQUEST)?
+        .0
+        .access_token
+        .ok_or(StatusCode::UNAUTHORIZED)
+}
+
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        // Handlers often take both the user and the workspace
+        if let Some(user) = parts.extensions.get::<CurrentUser>() {
+            return Ok(*user);
+        }
+
+        let token = session_token(parts)?;
+        let pool = PgPool::from_ref(state);
+        let row = sqlx::query(&format!("SELECT id, workspace_id, is_admin FROM users WHERE token_hash = {}", token_hash("$1")))
+            .bind(&token)
+            .fetch_optional(&pool)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        let user = CurrentUser {
+            id: row.get("id"),
+            workspace_id: row.get("workspace_id"),
+            is_admin: row.get("is_admin"),
+        };
+        parts.extensions.insert(user);
+        Ok(user)
+    }
+}
+
+impl<S> FromRequestParts<S> for CurrentWorkspace
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = CurrentUser::from_request_parts(parts, state).await?;
+        Ok(CurrentWorkspace(user.workspace_id))
+    }
+}
+
+impl<S> FromRequestParts<S> for WorkspaceAdmin
+where
+    PgPool: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let user = CurrentUser::from_request_parts(parts, state).await?;
+        if user.is_admin { Ok(WorkspaceAdmin(user.workspace_id)) } else { Err(StatusCode::FORBIDDEN) }
+    }
+}
+
+/// The server operator's `ADMIN_TOKEN` secret. Without it the operator
+/// endpoints can't be used.
+#[derive(Clone)]
+pub struct AdminToken(pub Option<String>);
+
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+/// A request made by the server operator, who sends the `ADMIN_TOKEN` secret
+/// in the `X-Admin-Token` header. Operators manage things that aren't tied to
+/// a workspace, like background jobs.
+pub struct ServerAdmin;
+
+impl<S> FromRequestParts<S> for ServerAdmin
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let expected = parts.extensions.get::<AdminToken>()
+            .and_then(|token| token.0.as_deref())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+        let given = parts.headers.get(ADMIN_TOKEN_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(StatusCode::UNAUTHORIZED)?;
+
+        if bool::from(given.as_bytes().ct_eq(expected.as_bytes())) { Ok(ServerAdmin) } else { Err(StatusCode::UNAUTHORIZED) }
+    }
+}
+
+/// Gives a user a new session token, which replaces the old one. Only
+/// workspace members are found when `workspace_id` is set.
+pub(crate) async fn issue_token(conn: &mut PgConnection, workspace_id: Option<i32>, user_id: i32) -> Result<String, StatusCode> {
+    sqlx::query_scalar(&format!(
+        "WITH new AS (SELECT {NEW_TOKEN} AS token)
+         UPDATE users SET token_hash = {} FROM new
+         WHERE users.id = $1 AND ($2::int IS NULL OR users.workspace_id = $2)
+         RETURNING new.token",
+        token_hash("new.token")
+    ))
+    .bind(user_id)
+    .bind(workspace_id)
+    .fetch_optional(conn)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    .ok_or(StatusCode::NOT_FOUND)
+}
+
+// The chat signing secret can be set but never read back
+const WORKSPACE_COLUMNS: &str = "id, name, invite_code, created_at, rating_max, rating_half_steps, rating_grace_days, one_meal_per_day,
+    chat_signing_secret IS NOT NULL AS chat_enabled, digest_email, reminder_time";
+
+// Invite codes come from gen_random_uuid() so they can't be guessed
+const NEW_INVITE_CODE: &str = "replace(gen_random_uuid()::text, '-', '')";
+
+fn workspace_from_row(row: &PgRow) -> Workspace {
+    Workspace {
+        id: row.get("id"),
+        name: row.get("name"),
+        invite_code: row.get("invite_code"),
+        created_at: row.get("created_at"),
+        rating_scale: RatingScale {
+            max: row.get("rating_max"),
+            half_steps: row.get("rating_half_steps"),
+        },
+        rating_grace_days: row.get("rating_grace_days"),
+        one_meal_per_day: row.get("one_meal_per_day"),
+        chat_enabled: row.get("chat_enabled"),
+        digest_email: row.get("digest_email"),
+        reminder_time: row.get("reminder_time"),
+    }
+}
+
+pub(crate) async fn fetch_rating_scale(conn: &mut PgConnection, workspace_id: i32) -> Result<RatingScale, StatusCode> {
+    let row = sqlx::query("SELECT rating_max, rating_half_steps FROM workspaces WHERE id = $1")
+        .bind(workspace_id)
+        .fetch_one(conn)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(RatingScale {
+        max: row.get("rating_max"),
+        half_steps: row.get("rating_half_steps"),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces",
+    request_body = NewWorkspace,
+    responses(
+        (status = 201, description = "Workspace created with its first admin and the Yaya restaurant, see GET /workspaces/current for the invite code", body = WorkspaceMembership),
+        (status = 400, description = "Bad request - rating scale must go up to between 2 and 10, grace days can't be negative, chat signing secret can't be empty, invalid digest email, or an invalid admin")
+    ),
+    security(()),
+    tag = "workspaces"
+)]
+pub async fn create_workspace(
+    State(pool): State<PgPool>,
+    Json(new_workspace): Json<NewWorkspace>,
+) -> Result<(StatusCode, Json<WorkspaceMembership>), StatusCode> {
+    let payload = &new_workspace.workspace;
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let workspace_id: i32 = sqlx::query_scalar(&format!(
+        "INSERT INTO workspaces (name, invite_code, rating_max, rating_half_steps, rating_grace_days, one_meal_per_day, chat_signing_secret,
+            digest_email, reminder_time)
+         VALUES ($1, {NEW_INVITE_CODE}, $2, $3, $4, $5, $6, $7, $8)
+         RETURNING id"
+    ))
+    .bind(&payload.name)
+    .bind(payload.rating_scale.max)
+    .bind(payload.rating_scale.half_steps)
+    .bind(payload.rating_grace_days)
+    .bind(payload.one_meal_per_day)
+    .bind(&payload.chat_signing_secret)
+    .bind(&payload.digest_email)
+    .bind(payload.reminder_time)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    // Every workspace starts out with Yaya, so dishes have somewhere to go
+    let yaya_categories = serde_json::to_string(&DishCategory::ALL).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    sqlx::query("INSERT INTO restaurants (workspace_id, name, categories) VALUES ($1, 'Yaya', $2)")
+        .bind(workspace_id)
+        .bind(&yaya_categories)
+        .execute(&mut *tx)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let user = insert_user(&mut tx, workspace_id, &new_workspace.admin, true).await?;
+    let token = issue_token(&mut tx, Some(workspace_id), user.id).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(WorkspaceMembership { workspace_id, user, token })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/workspaces/current",
+    responses(
+        (status = 200, description = "The caller's workspace", body = Workspace),
+        (status = 404, description = "Workspace not found")
+    ),
+    tag = "workspaces"
+)]
+pub async fn get_current_workspace(
+    State(pool): State<PgPool>,
+    CurrentWorkspace(workspace_id): CurrentWorkspace,
+) -> Result<Json<Workspace>, StatusCode> {
+    let row = sqlx::query(&format!("SELECT {WORKSPACE_COLUMNS} FROM workspaces WHERE id = $1"))
+        .bind(workspace_id)
+        .fetch_one(&pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(workspace_from_row(&row)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/workspaces/current",
+    request_body = ModifyWorkspace,
+    responses(
+        (status = 200, description = "Workspace updated, keeping anything left out", body = Workspace),
+        (status = 400, description = "Bad request - rating scale must go up to between 2 and 10, grace days can't be negative, chat signing secret can't be empty, invalid digest email"),
+        (status = 403, description = "Forbidden - only workspace admins can change settings"),
+        (status = 409, description = "Conflict - existing ratings don't fit the new scale")
+    ),
+    tag = "workspaces"
+)]
+pub async fn modify_current_workspace(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+    Json(payload): Json<ModifyWorkspace>,
+) -> Result<Json<Workspace>, StatusCode> {
+    if !payload.is_valid() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    let scale = payload.rating_scale.as_ref();
+
+    // The optional settings are only touched when they're in the request,
+    // so null can turn them off
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let row = sqlx::query(&format!(
+        "UPDATE workspaces SET name = COALESCE($1, name), rating_max = COALESCE($2, rating_max),
+            rating_half_steps = COALESCE($3, rating_half_steps), rating_grace_days = COALESCE($5, rating_grace_days),
+            one_meal_per_day = COALESCE($6, one_meal_per_day),
+            chat_signing_secret = CASE WHEN $7 THEN $8 ELSE chat_signing_secret END,
+            digest_email = CASE WHEN $9 THEN $10 ELSE digest_email END,
+            reminder_time = CASE WHEN $11 THEN $12 ELSE reminder_time END
+         WHERE id = $4
+         RETURNING {WORKSPACE_COLUMNS}"
+    ))
+    .bind(&payload.name)
+    .bind(scale.map(|scale| scale.max))
+    .bind(scale.map(|scale| scale.half_steps))
+    .bind(workspace_id)
+    .bind(payload.rating_grace_days)
+    .bind(payload.one_meal_per_day)
+    .bind(payload.chat_signing_secret.is_some())
+    .bind(payload.chat_signing_secret.clone().flatten())
+    .bind(payload.digest_email.is_some())
+    .bind(payload.digest_email.clone().flatten())
+    .bind(payload.reminder_time.is_some())
+    .bind(payload.reminder_time.flatten())
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let workspace = workspace_from_row(&row);
+    let scale = workspace.rating_scale;
+
+    // Shrinking the scale or dropping half steps can't strand old ratings
+    let scores: Vec<Option<f64>> = sqlx::query_scalar(
+        "SELECT DISTINCT UNNEST(ARRAY[rating, taste, portion, spiciness_accuracy, temperature, value, wait_time])
+         FROM ratings WHERE workspace_id = $1"
+    )
+    .bind(workspace_id)
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if !scores.into_iter().flatten().all(|score| scale.accepts(score)) {
+        return Err(StatusCode::CONFLICT);
+    }
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(workspace))
+}
+
+#[utoipa::path(
+    post,
+    path = "/workspaces/current/invite",
+    responses(
+        (status = 200, description = "New invite code, the old one stops working", body = Workspace),
+        (status = 403, description = "Forbidden - only workspace admins can renew the invite code")
+    ),
+    tag = "workspaces"
+)]
+pub async fn renew_invite_code(
+    State(pool): State<PgPool>,
+    WorkspaceAdmin(workspace_id): WorkspaceAdmin,
+) -> Result<Json<Workspace>, StatusCode> {
+    let row = sqlx::query(&format!(
+        "UPDATE workspaces SET invite_code = {NEW_INVITE_CODE} WHERE id = $1 RETURNING {WORKSPACE_COLUMNS}"
+    ))
+    .bind(workspace_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok(Json(workspace_from_row(&row)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/invites/{code}",
+    params(
+        ("code" = String, Path, description = "Invite code")
+    ),
+    responses(
+        (status = 200, description = "Workspace the invite is for", body = WorkspaceInvite),
+        (status = 404, description = "Invite code not valid")
+    ),
+    security(()),
+    tag = "workspaces"
+)]
+pub async fn get_invite(
+    State(pool): State<PgPool>,
+    Path(code): Path<String>,
+) -> Result<Json<WorkspaceInvite>, StatusCode> {
+    let row = sqlx::query("SELECT id, name FROM workspaces WHERE invite_code = $1")
+        .bind(&code)
+        .fetch_one(&pool)
+        .await
+        .map_err(|err| match err {
+            sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        })?;
+
+    Ok(Json(WorkspaceInvite {
+        workspace_id: row.get("id"),
+        workspace_name: row.get("name"),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/invites/{code}/join",
+    request_body = CreateUser,
+    params(
+        ("code" = String, Path, description = "Invite code")
+    ),
+    responses(
+        (status = 201, description = "User created in the invited workspace, with their session token", body = WorkspaceMembership),
+        (status = 400, description = "Bad request - invalid email, or a weekly digest or email reminders without an email"),
+        (status = 403, description = "Forbidden - chat accounts are linked from the chat, not when joining"),
+        (status = 404, description = "Invite code not valid")
+    ),
+    security(()),
+    tag = "workspaces"
+)]
+pub async fn join_workspace(
+    State(pool): State<PgPool>,
+    Path(code): Path<String>,
+    Json(payload): Json<CreateUser>,
+) -> Result<(StatusCode, Json<WorkspaceMembership>), StatusCode> {
+    if payload.chat_user_id.is_some() {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    let workspace_id: i32 = sqlx::query_scalar("SELECT id FROM workspaces WHERE invite_code = $1")
+        .bind(&code)
+        .fetch_optional(&pool)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let user = insert_user(&mut tx, workspace_id, &payload, false).await?;
+    let token = issue_token(&mut tx, Some(workspace_id), user.id).await?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((StatusCode::CREATED, Json(WorkspaceMembership { workspace_id, user, token })))
+}
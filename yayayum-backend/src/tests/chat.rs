@@ -0,0 +1,62 @@
+use axum::{body::{to_bytes, Body}, http::{header::CONTENT_TYPE, Request, StatusCode}, Router};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use tower::ServiceExt;
+use super::{create_dish, create_workspace, send, test_app};
+
+const CHAT_TOKEN: &str = "mattermost-token";
+
+/// Runs `/yaya <text>` as the chat user, the way Mattermost posts it.
+async fn slash_command(app: &Router, workspace_id: i64, chat_user_id: &str, text: &str) -> String {
+    let form = serde_urlencoded::to_string([("user_id", chat_user_id), ("text", text), ("token", CHAT_TOKEN)]).unwrap();
+    let request = Request::builder()
+        .method("POST")
+        .uri(format!("/chat/{workspace_id}/command"))
+        .header(CONTENT_TYPE, "application/x-www-form-urlencoded")
+        .body(Body::from(form))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let reply: Value = serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    reply["text"].as_str().unwrap().to_string()
+}
+
+#[sqlx::test(migrations = false)]
+async fn chat_accounts_are_linked_with_a_one_time_code(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let (status, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "chat_signing_secret": CHAT_TOKEN }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let workspace_id = workspace["id"].as_i64().unwrap();
+
+    // Knowing the username isn't enough
+    let reply = slash_command(&app, workspace_id, "U_MALLORY", "link alice").await;
+    assert!(reply.contains("doesn't work"), "{reply}");
+
+    let (status, link) = send(&app, "POST", "/chat/link-code", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let code = link["code"].as_str().unwrap();
+
+    let reply = slash_command(&app, workspace_id, "U_ALICE", &format!("link {}", code.to_lowercase())).await;
+    assert!(reply.contains("You're alice"), "{reply}");
+
+    let reply = slash_command(&app, workspace_id, "U_MALLORY", &format!("link {code}")).await;
+    assert!(reply.contains("doesn't work"), "{reply}");
+}
+
+#[sqlx::test(migrations = false)]
+async fn chat_ratings_explain_scores_off_the_scale(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, token) = create_workspace(&app, "A", "alice").await;
+    let (_, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "chat_signing_secret": CHAT_TOKEN }))).await;
+    let workspace_id = workspace["id"].as_i64().unwrap();
+    send(&app, "PUT", &format!("/users/{alice}"), Some(&token), Some(json!({ "chat_user_id": "U_ALICE" }))).await;
+    create_dish(&app, &token, 7, "Bali Goreng").await;
+
+    let reply = slash_command(&app, workspace_id, "U_ALICE", "rate 7 9").await;
+    assert_eq!(reply, "9 isn't on the rating scale, use 1 to 5 in half steps");
+
+    let reply = slash_command(&app, workspace_id, "U_ALICE", "rate 7 4,5 crispy").await;
+    assert_eq!(reply, "alice rated #7 Bali Goreng 4.5: crispy");
+}
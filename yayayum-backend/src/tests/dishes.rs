@@ -0,0 +1,68 @@
+use axum::{http::StatusCode, Router};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use super::{create_workspace, send, test_app};
+
+/// Dish number `nr`, declared with `diets`, made of `ingredient_ids` and with
+/// `allergens` of its own.
+fn dish(nr: i32, diets: Value, ingredient_ids: Value, allergens: Value) -> Value {
+    json!({
+        "nr": nr,
+        "name": "Tofu Curry",
+        "description": "With rice",
+        "price_kr": 129,
+        "dietary_restrictions": diets,
+        "category": "WokWithNoodles",
+        "ingredient_ids": ingredient_ids,
+        "allergens": allergens,
+    })
+}
+
+async fn create_ingredient(app: &Router, token: &str, ingredient: Value) -> i64 {
+    let (status, ingredient) = send(app, "POST", "/ingredients", Some(token), Some(ingredient)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    ingredient["id"].as_i64().unwrap()
+}
+
+#[sqlx::test(migrations = false)]
+async fn animal_allergens_rule_out_vegan_and_fish_rules_out_vegetarian(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+
+    for (diets, allergens) in [
+        (json!(["Vegan"]), json!(["Milk"])),
+        (json!(["Vegan"]), json!(["Eggs"])),
+        (json!(["Vegetarian"]), json!(["Fish"])),
+        (json!(["Vegetarian"]), json!(["Crustaceans"])),
+    ] {
+        let (status, _) = send(&app, "POST", "/dishes", Some(&token), Some(dish(1, diets.clone(), json!([]), allergens.clone()))).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY, "{diets} with {allergens}");
+    }
+
+    let (status, _) = send(&app, "POST", "/dishes", Some(&token), Some(dish(1, json!(["Vegetarian"]), json!([]), json!(["Milk", "Eggs"])))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, _) = send(&app, "POST", "/dishes", Some(&token), Some(dish(2, json!(["Vegan"]), json!([]), json!(["Soybeans", "Gluten"])))).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
+
+#[sqlx::test(migrations = false)]
+async fn ingredients_have_to_fit_the_dishes_diets(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let tofu = create_ingredient(&app, &token, json!({ "name": "Tofu", "source": "Plant", "allergens": ["Soybeans"] })).await;
+    let egg = create_ingredient(&app, &token, json!({ "name": "Egg", "source": "Egg", "allergens": ["Eggs"] })).await;
+
+    let (status, _) = send(&app, "POST", "/dishes", Some(&token), Some(dish(1, json!(["Vegan"]), json!([tofu, egg]), json!([])))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, "POST", "/dishes", Some(&token), Some(dish(1, json!(["Vegan"]), json!([tofu]), json!([])))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // The Vegan dish holds its ingredients to it, also after the dish was saved
+    let tofu_uri = format!("/ingredients/{tofu}");
+    let (status, _) = send(&app, "PUT", &tofu_uri, Some(&token), Some(json!({ "name": "Tofu", "source": "Dairy" }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, "PUT", &tofu_uri, Some(&token), Some(json!({ "name": "Tofu", "source": "Plant", "allergens": ["Milk"] }))).await;
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+    let (status, _) = send(&app, "PUT", &tofu_uri, Some(&token), Some(json!({ "name": "Silken tofu", "source": "Plant", "allergens": ["Soybeans"] }))).await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,89 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use super::{create_dish, create_workspace, send, test_app};
+
+const DISH_NAME: &str = "Secret Noodles";
+
+/// Rates a dish in the workspace and returns the rating's ID.
+async fn rate_dish(app: &axum::Router, token: &str) -> i64 {
+    let dish_id = create_dish(app, token, 1, DISH_NAME).await;
+    let (status, rating) = send(app, "POST", "/ratings", Some(token), Some(json!({
+        "dish_id": dish_id,
+        "rating": 4.0,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    rating["id"].as_i64().unwrap()
+}
+
+#[sqlx::test(migrations = false)]
+async fn requests_without_a_valid_token_are_rejected(pool: PgPool) {
+    let app = test_app(pool).await;
+    create_workspace(&app, "A", "alice").await;
+
+    assert_eq!(send(&app, "GET", "/ratings", None, None).await.0, StatusCode::UNAUTHORIZED);
+    assert_eq!(send(&app, "GET", "/ratings", Some("not-a-token"), None).await.0, StatusCode::UNAUTHORIZED);
+}
+
+#[sqlx::test(migrations = false)]
+async fn workspaces_cant_see_each_others_ratings(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, token_a) = create_workspace(&app, "A", "alice").await;
+    let (_, token_b) = create_workspace(&app, "B", "bob").await;
+    let rating_id = rate_dish(&app, &token_a).await;
+
+    let (status, own) = send(&app, "GET", "/ratings", Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(own.as_array().unwrap().len(), 1);
+
+    let (status, others) = send(&app, "GET", "/ratings", Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(others, json!([]));
+
+    let rating = format!("/ratings/{rating_id}");
+    assert_eq!(send(&app, "GET", &rating, Some(&token_a), None).await.0, StatusCode::OK);
+    assert_eq!(send(&app, "GET", &rating, Some(&token_b), None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, "DELETE", &rating, Some(&token_b), None).await.0, StatusCode::NOT_FOUND);
+
+    let (status, by_user) = send(&app, "GET", &format!("/ratings/user/{alice}"), Some(&token_b), None).await;
+    assert!(status == StatusCode::NOT_FOUND || by_user == json!([]), "{status} {by_user}");
+}
+
+#[sqlx::test(migrations = false)]
+async fn workspaces_cant_see_each_others_stats(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token_a) = create_workspace(&app, "A", "alice").await;
+    let (_, token_b) = create_workspace(&app, "B", "bob").await;
+    rate_dish(&app, &token_a).await;
+
+    for uri in ["/stats/dishes", "/stats/leaderboard", "/stats/top-dishes", "/stats/controversial?min_reviews=1"] {
+        let (status, own) = send(&app, "GET", uri, Some(&token_a), None).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+        let (status, others) = send(&app, "GET", uri, Some(&token_b), None).await;
+        assert_eq!(status, StatusCode::OK, "{uri}");
+
+        if uri != "/stats/controversial?min_reviews=1" {
+            assert!(own.to_string().contains(DISH_NAME) || own.to_string().contains("alice"), "{uri}: {own}");
+        }
+        assert!(!others.to_string().contains(DISH_NAME), "{uri}: {others}");
+        assert!(!others.to_string().contains("alice"), "{uri}: {others}");
+    }
+}
+
+#[sqlx::test(migrations = false)]
+async fn workspaces_cant_see_each_others_feed(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, token_a) = create_workspace(&app, "A", "alice").await;
+    let (_, token_b) = create_workspace(&app, "B", "bob").await;
+    rate_dish(&app, &token_a).await;
+
+    let (status, own) = send(&app, "GET", "/feed", Some(&token_a), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(own.to_string().contains("alice"), "{own}");
+
+    let (status, others) = send(&app, "GET", "/feed", Some(&token_b), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!others.to_string().contains("alice"), "{others}");
+    assert!(others["events"].as_array().unwrap().iter().all(|event| event["user_id"] != json!(alice)));
+}
@@ -0,0 +1,92 @@
+//! End-to-end tests against the full router. They need a Postgres server in
+//! `DATABASE_URL`, `#[sqlx::test]` gives each test its own database.
+
+mod chat;
+mod dishes;
+mod isolation;
+mod orders;
+mod ratings;
+mod reminders;
+mod users;
+mod webhooks;
+mod workspaces;
+
+use std::collections::BTreeMap;
+use axum::{body::{to_bytes, Body}, http::{header::{AUTHORIZATION, CONTENT_TYPE}, Request, StatusCode}, Router};
+use serde_json::{json, Value};
+use shuttle_runtime::SecretStore;
+use sqlx::PgPool;
+use tower::ServiceExt;
+use crate::mailer::Mailer;
+use crate::routes::feed::FeedBroadcast;
+use crate::routes::workspaces::AdminToken;
+use crate::workers::webhooks::WebhookTargets;
+
+pub(crate) async fn test_app(pool: PgPool) -> Router {
+    test_app_with_targets(pool, WebhookTargets { allow_private: false }).await
+}
+
+/// Like `test_app`, for tests that need webhooks to reach a local receiver.
+pub(crate) async fn test_app_with_targets(pool: PgPool, targets: WebhookTargets) -> Router {
+    crate::create_schema(&pool).await;
+    let mailer = Mailer::from_secrets(&SecretStore::new(BTreeMap::new()));
+    crate::app(pool, mailer, AdminToken(None), targets, FeedBroadcast::new())
+}
+
+/// Sends a request as the user with `token`, returning the status and the
+/// JSON body, or Null for empty bodies.
+pub(crate) async fn send(app: &Router, method: &str, uri: &str, token: Option<&str>, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(AUTHORIZATION, format!("Bearer {token}"));
+    }
+    let request = match body {
+        Some(body) => request.header(CONTENT_TYPE, "application/json").body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap_or(Value::Null))
+}
+
+/// Creates a workspace and returns its admin's user ID and session token.
+pub(crate) async fn create_workspace(app: &Router, name: &str, admin: &str) -> (i64, String) {
+    let (status, membership) = send(app, "POST", "/workspaces", None, Some(json!({
+        "name": name,
+        "admin": { "username": admin },
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    (membership["user"]["id"].as_i64().unwrap(), membership["token"].as_str().unwrap().to_string())
+}
+
+/// Adds a member to the admin's workspace and returns their user ID and
+/// session token.
+pub(crate) async fn add_member(app: &Router, admin_token: &str, username: &str) -> (i64, String) {
+    let (status, user) = send(app, "POST", "/users", Some(admin_token), Some(json!({ "username": username }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, token) = send(app, "POST", &format!("/users/{}/token", user["id"]), Some(admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    (user["id"].as_i64().unwrap(), token["token"].as_str().unwrap().to_string())
+}
+
+/// Adds a dish to the workspace and returns its ID.
+pub(crate) async fn create_dish(app: &Router, token: &str, nr: i32, name: &str) -> i64 {
+    let (status, dish) = send(app, "POST", "/dishes", Some(token), Some(json!({
+        "nr": nr,
+        "name": name,
+        "description": "Only for this workspace",
+        "price_kr": 129,
+        "dietary_restrictions": [],
+        "category": "WokWithNoodles",
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    dish["id"].as_i64().unwrap()
+}
@@ -0,0 +1,84 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use super::{add_member, create_dish, create_workspace, send, test_app};
+
+#[sqlx::test(migrations = false)]
+async fn only_the_organiser_changes_other_peoples_lines(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let (carol, carol_token) = add_member(&app, &admin_token, "carol").await;
+    let dish_id = create_dish(&app, &admin_token, 7, "Bali Goreng").await;
+
+    let (status, _) = send(&app, "POST", "/orders", Some(&carol_token), Some(json!({ "paid_by": bob }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, order) = send(&app, "POST", "/orders", Some(&bob_token), Some(json!({ "paid_by": bob }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let items_uri = format!("/orders/{}/items", order["id"]);
+
+    // Carol can only order for herself
+    let (status, _) = send(&app, "POST", &items_uri, Some(&carol_token), Some(json!({ "user_id": bob, "dish_id": dish_id }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, "POST", &items_uri, Some(&carol_token), Some(json!({ "dish_id": dish_id }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, order) = send(&app, "POST", &items_uri, Some(&carol_token), Some(json!({ "user_id": carol, "dish_id": dish_id }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let carols_line = format!("{items_uri}/{}", order["items"][0]["id"]);
+
+    // The organiser adds a shared side order, which only they can change
+    let (_, order) = send(&app, "POST", &items_uri, Some(&bob_token), Some(json!({ "dish_id": dish_id }))).await;
+    let shared_line = format!("{items_uri}/{}", order["items"][1]["id"]);
+    assert_eq!(send(&app, "DELETE", &shared_line, Some(&carol_token), None).await.0, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, "PUT", &carols_line, Some(&carol_token), Some(json!({ "user_id": bob, "dish_id": dish_id }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, order) = send(&app, "PUT", &carols_line, Some(&carol_token), Some(json!({ "user_id": carol, "dish_id": dish_id, "quantity": 2 }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(order["items"][0]["quantity"], 2);
+    assert_eq!(send(&app, "DELETE", &carols_line, Some(&bob_token), None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(send(&app, "DELETE", &format!("/orders/{}", order["id"]), Some(&carol_token), None).await.0, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = false)]
+async fn only_whoever_was_paid_records_it(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let (carol, carol_token) = add_member(&app, &admin_token, "carol").await;
+    let payment = json!({ "from_user_id": carol, "to_user_id": bob, "amount_kr": 129 });
+
+    assert_eq!(send(&app, "POST", "/payments", Some(&carol_token), Some(payment.clone())).await.0, StatusCode::FORBIDDEN);
+    let (status, recorded) = send(&app, "POST", "/payments", Some(&bob_token), Some(payment)).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let payment_uri = format!("/payments/{}", recorded["id"]);
+    assert_eq!(send(&app, "DELETE", &payment_uri, Some(&carol_token), None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, "DELETE", &payment_uri, Some(&admin_token), None).await.0, StatusCode::NO_CONTENT);
+
+    let (status, _) = send(&app, "POST", "/ledger/settle", Some(&carol_token), Some(json!({ "from_user_id": carol, "to_user_id": bob }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+}
+
+#[sqlx::test(migrations = false)]
+async fn lunch_picks_are_made_for_yourself(pool: PgPool) {
+    let app = test_app(pool.clone()).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let dish_id = create_dish(&app, &admin_token, 7, "Bali Goreng").await;
+    let cutoff_at: String = sqlx::query_scalar("SELECT to_char(CURRENT_DATE + time '23:59:59', 'YYYY-MM-DD\"T\"HH24:MI:SS')")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    let (status, session) = send(&app, "POST", "/lunch-sessions", Some(&bob_token), Some(json!({ "title": "Thai", "created_by": bob, "cutoff_at": cutoff_at }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    let pick = json!({ "dish_id": dish_id });
+
+    let alices_pick = format!("/lunch-sessions/{}/participants/{alice}", session["id"]);
+    assert_eq!(send(&app, "PUT", &alices_pick, Some(&bob_token), Some(pick.clone())).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, "PUT", &alices_pick, Some(&admin_token), Some(pick.clone())).await.0, StatusCode::OK);
+    assert_eq!(send(&app, "DELETE", &alices_pick, Some(&bob_token), None).await.0, StatusCode::FORBIDDEN);
+
+    let bobs_pick = format!("/lunch-sessions/{}/participants/{bob}", session["id"]);
+    assert_eq!(send(&app, "PUT", &bobs_pick, Some(&bob_token), Some(pick)).await.0, StatusCode::OK);
+    assert_eq!(send(&app, "DELETE", &bobs_pick, Some(&admin_token), None).await.0, StatusCode::NO_CONTENT);
+}
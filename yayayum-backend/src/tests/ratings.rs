@@ -0,0 +1,161 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use super::{add_member, create_dish, create_workspace, send, test_app};
+
+#[sqlx::test(migrations = false)]
+async fn ratings_are_by_the_current_user(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let (_, carol_token) = add_member(&app, &admin_token, "carol").await;
+    let dish_id = create_dish(&app, &admin_token, 1, "Bali Goreng").await;
+
+    // Naming someone else in the body doesn't rate as them
+    let (status, rating) = send(&app, "POST", "/ratings", Some(&bob_token), Some(json!({
+        "dish_id": dish_id,
+        "rating": 4.0,
+        "user_id": alice,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(rating["user_id"], bob);
+    let rating_uri = format!("/ratings/{}", rating["id"]);
+    let edit = json!({ "dish_id": dish_id, "rating": 2.0 });
+
+    assert_eq!(send(&app, "PUT", &rating_uri, Some(&carol_token), Some(edit.clone())).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, "DELETE", &rating_uri, Some(&carol_token), None).await.0, StatusCode::FORBIDDEN);
+    let meal_uri = format!("/meals/{}", rating["meal_id"]);
+    assert_eq!(send(&app, "DELETE", &meal_uri, Some(&carol_token), None).await.0, StatusCode::FORBIDDEN);
+    let other_dish = create_dish(&app, &admin_token, 2, "Spicy Chili").await;
+    let (status, _) = send(&app, "POST", &format!("{meal_uri}/ratings"), Some(&carol_token), Some(json!({
+        "dish_id": other_dish,
+        "rating": 1.0,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, edited) = send(&app, "PUT", &rating_uri, Some(&bob_token), Some(edit)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(edited["rating"], 2.0);
+    // Admins can take down anyone's rating
+    assert_eq!(send(&app, "DELETE", &rating_uri, Some(&admin_token), None).await.0, StatusCode::NO_CONTENT);
+}
+
+#[sqlx::test(migrations = false)]
+async fn comments_can_only_be_changed_by_their_author(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let dish_id = create_dish(&app, &admin_token, 1, "Bali Goreng").await;
+    let (_, rating) = send(&app, "POST", "/ratings", Some(&admin_token), Some(json!({ "dish_id": dish_id, "rating": 4.0 }))).await;
+    let comments_uri = format!("/ratings/{}/comments", rating["id"]);
+
+    let (status, comment) = send(&app, "POST", &comments_uri, Some(&bob_token), Some(json!({
+        "body": "Was it crispy?",
+        "user_id": alice,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(comment["user_id"], bob);
+    let comment_uri = format!("{comments_uri}/{}", comment["id"]);
+
+    let edit = json!({ "body": "It wasn't", "user_id": bob });
+    assert_eq!(send(&app, "PUT", &comment_uri, Some(&admin_token), Some(edit)).await.0, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, "DELETE", &format!("{comment_uri}?user_id={bob}"), Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, edited) = send(&app, "PUT", &comment_uri, Some(&bob_token), Some(json!({ "body": "Was it spicy?" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(edited["body"], "Was it spicy?");
+    assert_eq!(send(&app, "DELETE", &comment_uri, Some(&bob_token), None).await.0, StatusCode::NO_CONTENT);
+}
+
+#[sqlx::test(migrations = false)]
+async fn reactions_are_by_the_current_user(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let dish_id = create_dish(&app, &admin_token, 1, "Bali Goreng").await;
+    let (_, rating) = send(&app, "POST", "/ratings", Some(&admin_token), Some(json!({ "dish_id": dish_id, "rating": 4.0 }))).await;
+    let reactions_uri = format!("/ratings/{}/reactions", rating["id"]);
+
+    let (status, reaction) = send(&app, "POST", &reactions_uri, Some(&bob_token), Some(json!({ "emoji": "🔥", "user_id": alice }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(reaction["user_id"], bob);
+    let (status, _) = send(&app, "POST", &reactions_uri, Some(&bob_token), Some(json!({ "emoji": "🔥" }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Removing only ever takes back your own reaction
+    let remove_uri = format!("{reactions_uri}?emoji=%F0%9F%94%A5&user_id={bob}");
+    assert_eq!(send(&app, "DELETE", &remove_uri, Some(&admin_token), None).await.0, StatusCode::NOT_FOUND);
+    assert_eq!(send(&app, "DELETE", &remove_uri, Some(&bob_token), None).await.0, StatusCode::NO_CONTENT);
+}
+
+/// The database's date, which ratings are dated by.
+async fn days_ago(pool: &PgPool, days: i32) -> String {
+    let day: chrono::NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE - $1").bind(days).fetch_one(pool).await.unwrap();
+    day.to_string()
+}
+
+#[sqlx::test(migrations = false)]
+async fn ratings_can_only_be_backdated_within_the_grace_period(pool: PgPool) {
+    let app = test_app(pool.clone()).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let (_, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "rating_grace_days": 3 }))).await;
+    assert_eq!(workspace["rating_grace_days"], 3);
+    let dish_id = create_dish(&app, &token, 1, "Bali Goreng").await;
+    let rating_on = |eaten_at: String| json!({ "dish_id": dish_id, "rating": 4.0, "eaten_at": eaten_at });
+
+    let (status, _) = send(&app, "POST", "/ratings", Some(&token), Some(rating_on(days_ago(&pool, 4).await))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, _) = send(&app, "POST", "/ratings", Some(&token), Some(rating_on(days_ago(&pool, -1).await))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, rating) = send(&app, "POST", "/ratings", Some(&token), Some(rating_on(days_ago(&pool, 3).await))).await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(rating["date"].as_str().unwrap().starts_with(&days_ago(&pool, 3).await), "{rating}");
+
+    // Once the meal's day falls out of the grace period it's closed
+    sqlx::query("UPDATE meals SET date = date - 1").execute(&pool).await.unwrap();
+    let other_dish = create_dish(&app, &token, 2, "Spicy Chili").await;
+    let (status, _) = send(&app, "POST", &format!("/meals/{}/ratings", rating["meal_id"]), Some(&token), Some(json!({
+        "dish_id": other_dish,
+        "rating": 3.0,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[sqlx::test(migrations = false)]
+async fn one_meal_a_day_takes_several_dishes(pool: PgPool) {
+    let app = test_app(pool.clone()).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let bali_goreng = create_dish(&app, &token, 1, "Bali Goreng").await;
+    let spring_rolls = create_dish(&app, &token, 2, "Spring Rolls").await;
+
+    let (status, meal) = send(&app, "POST", "/meals", Some(&token), Some(json!({
+        "ratings": [{ "dish_id": bali_goreng, "rating": 4.0 }, { "dish_id": spring_rolls, "rating": 3.5 }],
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(meal["ratings"].as_array().unwrap().len(), 2);
+
+    // The day's meal is taken, though another day is fine
+    let (status, _) = send(&app, "POST", "/ratings", Some(&token), Some(json!({ "dish_id": bali_goreng, "rating": 2.0 }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let yesterday = days_ago(&pool, 1).await;
+    let (status, _) = send(&app, "POST", "/ratings", Some(&token), Some(json!({ "dish_id": bali_goreng, "rating": 2.0, "eaten_at": yesterday }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // A dish is only rated once per meal, but more dishes can join it
+    let meal_ratings = format!("/meals/{}/ratings", meal["id"]);
+    let (status, _) = send(&app, "POST", &meal_ratings, Some(&token), Some(json!({ "dish_id": spring_rolls, "rating": 5.0 }))).await;
+    assert_eq!(status, StatusCode::CONFLICT);
+    let dumplings = create_dish(&app, &token, 3, "Dumplings").await;
+    let (status, _) = send(&app, "POST", &meal_ratings, Some(&token), Some(json!({ "dish_id": dumplings, "rating": 5.0 }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "one_meal_per_day": false }))).await;
+    let (status, _) = send(&app, "POST", "/ratings", Some(&token), Some(json!({ "dish_id": bali_goreng, "rating": 2.0 }))).await;
+    assert_eq!(status, StatusCode::CREATED);
+}
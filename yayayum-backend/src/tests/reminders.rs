@@ -0,0 +1,69 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use shuttle_runtime::SecretStore;
+use sqlx::PgPool;
+use crate::mailer::Mailer;
+use crate::routes::reminders::send_due_reminders;
+use super::{add_member, create_workspace, send, test_app};
+
+#[sqlx::test(migrations = false)]
+async fn webhook_reminders_fall_back_to_in_app_without_a_subscriber(pool: PgPool) {
+    let app = test_app(pool.clone()).await;
+    let (alice, token) = create_workspace(&app, "A", "alice").await;
+    let (status, _) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "reminder_time": "00:00:00" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&app, "PUT", &format!("/users/{alice}"), Some(&token), Some(json!({ "reminder_channel": "Webhook" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    // Only listens for dishes, so nothing would deliver the reminder
+    sqlx::query(
+        "INSERT INTO webhooks (workspace_id, url, event_types, secret)
+         SELECT workspace_id, 'https://example.com/hook', '[\"dish.created\"]', 'secret' FROM users WHERE id = $1"
+    )
+    .bind(alice as i32)
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    // Nobody is reminded at the weekend
+    let weekday: bool = sqlx::query_scalar("SELECT EXTRACT(ISODOW FROM CURRENT_DATE) < 6").fetch_one(&pool).await.unwrap();
+    if !weekday {
+        return;
+    }
+
+    let mailer = Mailer::from_secrets(&SecretStore::new(Default::default()));
+    assert_eq!(send_due_reminders(&pool, &mailer).await, Ok("1 reminded, 0 failed".to_string()));
+
+    let channel: String = sqlx::query_scalar("SELECT channel FROM sent_reminders WHERE user_id = $1")
+        .bind(alice as i32)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(channel, "\"InApp\"");
+    let (_, notifications) = send(&app, "GET", &format!("/users/{alice}/notifications"), Some(&token), None).await;
+    assert_eq!(notifications.as_array().unwrap().len(), 1, "{notifications}");
+}
+
+#[sqlx::test(migrations = false)]
+async fn notifications_are_only_for_their_owner(pool: PgPool) {
+    let app = test_app(pool.clone()).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (_, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let mut conn = pool.acquire().await.unwrap();
+    let workspace_id: i32 = sqlx::query_scalar("SELECT workspace_id FROM users WHERE id = $1")
+        .bind(alice as i32)
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap();
+    crate::routes::notifications::notify(&mut conn, workspace_id, alice as i32, "Time to rate lunch").await.unwrap();
+
+    let notifications_uri = format!("/users/{alice}/notifications");
+    assert_eq!(send(&app, "GET", &notifications_uri, Some(&bob_token), None).await.0, StatusCode::NOT_FOUND);
+    let (status, notifications) = send(&app, "GET", &notifications_uri, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let read_uri = format!("{notifications_uri}/{}/read", notifications[0]["id"]);
+    assert_eq!(send(&app, "POST", &read_uri, Some(&bob_token), None).await.0, StatusCode::NOT_FOUND);
+
+    let (status, read) = send(&app, "POST", &read_uri, Some(&admin_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!read["read_at"].is_null());
+}
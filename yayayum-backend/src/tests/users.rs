@@ -0,0 +1,93 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use super::{add_member, create_workspace, send, test_app};
+
+#[sqlx::test(migrations = false)]
+async fn emails_are_only_shown_to_their_owner(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, token) = create_workspace(&app, "A", "alice").await;
+    let (status, bob) = send(&app, "POST", "/users", Some(&token), Some(json!({
+        "username": "bob",
+        "email": "bob@example.com",
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    let (status, bob_token) = send(&app, "POST", &format!("/users/{}/token", bob["id"]), Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let bob_token = bob_token["token"].as_str().unwrap();
+
+    let (status, users) = send(&app, "GET", "/users", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!users.to_string().contains("bob@example.com"), "{users}");
+
+    send(&app, "PUT", &format!("/users/{}/following/{alice}", bob["id"]), Some(bob_token), None).await;
+    let (_, followers) = send(&app, "GET", &format!("/users/{alice}/followers"), Some(&token), None).await;
+    assert_eq!(followers[0]["username"], "bob");
+    assert!(followers[0].get("email").is_none(), "{followers}");
+
+    let (status, me) = send(&app, "GET", "/users/me", Some(bob_token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(me["email"], "bob@example.com");
+    let (_, users) = send(&app, "GET", "/users", Some(bob_token), None).await;
+    assert!(users.to_string().contains("bob@example.com"), "{users}");
+}
+
+#[sqlx::test(migrations = false)]
+async fn updates_keep_what_they_leave_out(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, token) = create_workspace(&app, "A", "alice").await;
+    let (_, bob) = send(&app, "POST", "/users", Some(&token), Some(json!({
+        "username": "bob",
+        "dietary_restrictions": ["Vegan"],
+        "email": "bob@example.com",
+        "weekly_digest": true,
+    })))
+    .await;
+    let (_, bob_token) = send(&app, "POST", &format!("/users/{}/token", bob["id"]), Some(&token), None).await;
+    let bob_token = bob_token["token"].as_str().unwrap();
+    let bob_uri = format!("/users/{}", bob["id"]);
+
+    let (status, updated) = send(&app, "PUT", &bob_uri, Some(bob_token), Some(json!({ "disliked_ingredients": ["coriander"] }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["username"], "bob");
+    assert_eq!(updated["dietary_restrictions"], json!(["Vegan"]));
+    assert_eq!(updated["disliked_ingredients"], json!(["coriander"]));
+    assert_eq!(updated["email"], "bob@example.com");
+    assert_eq!(updated["weekly_digest"], true);
+
+    // The digest needs an email
+    let (status, _) = send(&app, "PUT", &bob_uri, Some(bob_token), Some(json!({ "email": null }))).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let (status, updated) = send(&app, "PUT", &bob_uri, Some(bob_token), Some(json!({ "email": null, "weekly_digest": false }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(updated.get("email").is_none());
+
+    let (status, _) = send(&app, "PUT", &format!("/users/{alice}"), Some(bob_token), Some(json!({ "username": "mallory" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(&app, "PUT", &bob_uri, Some(bob_token), Some(json!({ "chat_user_id": "U_ALICE" }))).await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, updated) = send(&app, "PUT", &bob_uri, Some(&token), Some(json!({ "chat_user_id": "U_BOB" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(updated["chat_user_id"], "U_BOB");
+    assert_eq!(updated["disliked_ingredients"], json!(["coriander"]));
+}
+
+#[sqlx::test(migrations = false)]
+async fn users_only_choose_who_they_follow(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (alice, admin_token) = create_workspace(&app, "A", "alice").await;
+    let (bob, bob_token) = add_member(&app, &admin_token, "bob").await;
+    let (carol, _) = add_member(&app, &admin_token, "carol").await;
+
+    let bob_follows_carol = format!("/users/{bob}/following/{carol}");
+    assert_eq!(send(&app, "PUT", &bob_follows_carol, Some(&admin_token), None).await.0, StatusCode::FORBIDDEN);
+    assert_eq!(send(&app, "PUT", &bob_follows_carol, Some(&bob_token), None).await.0, StatusCode::NO_CONTENT);
+    assert_eq!(send(&app, "DELETE", &bob_follows_carol, Some(&admin_token), None).await.0, StatusCode::FORBIDDEN);
+    let (_, following) = send(&app, "GET", &format!("/users/{bob}/following"), Some(&bob_token), None).await;
+    assert_eq!(following[0]["id"], carol);
+
+    assert_eq!(send(&app, "DELETE", &bob_follows_carol, Some(&bob_token), None).await.0, StatusCode::NO_CONTENT);
+    let bob_follows_alice = format!("/users/{bob}/following/{alice}");
+    assert_eq!(send(&app, "DELETE", &bob_follows_alice, Some(&bob_token), None).await.0, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,179 @@
+use std::sync::{Arc, Mutex};
+use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::post, Router};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use crate::models::webhook::{sign, MAX_DELIVERY_ATTEMPTS};
+use crate::workers::webhooks::{deliver_due, WebhookTargets};
+use super::{create_workspace, send, test_app, test_app_with_targets};
+
+const SECRET: &str = "receiver-secret";
+
+const LOCAL: WebhookTargets = WebhookTargets { allow_private: true };
+
+/// What the receiver got, and how it answers.
+#[derive(Clone)]
+struct Receiver {
+    requests: Arc<Mutex<Vec<(HeaderMap, String)>>>,
+    status: StatusCode,
+}
+
+async fn receive(State(receiver): State<Receiver>, headers: HeaderMap, body: String) -> StatusCode {
+    receiver.requests.lock().unwrap().push((headers, body));
+    receiver.status
+}
+
+/// Starts a webhook receiver on a free local port and returns its URL.
+async fn start_receiver(status: StatusCode) -> (String, Receiver) {
+    let receiver = Receiver { requests: Arc::default(), status };
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hook", listener.local_addr().unwrap());
+    let app = Router::new().route("/hook", post(receive)).with_state(receiver.clone());
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+    (url, receiver)
+}
+
+/// Subscribes a webhook to new dishes, adds one and returns the webhook's ID.
+async fn queue_delivery(app: &Router, token: &str, url: &str) -> i64 {
+    let (status, webhook) = send(app, "POST", "/admin/webhooks", Some(token), Some(json!({
+        "url": url,
+        "event_types": ["dish.created"],
+        "secret": SECRET,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let (status, _) = send(app, "POST", "/dishes", Some(token), Some(json!({
+        "nr": 7,
+        "name": "Bali Goreng",
+        "description": "Fried noodles",
+        "price_kr": 129,
+        "dietary_restrictions": [],
+        "category": "WokWithNoodles",
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    webhook["id"].as_i64().unwrap()
+}
+
+async fn deliveries(app: &Router, token: &str, webhook_id: i64) -> Vec<Value> {
+    let (status, deliveries) = send(app, "GET", &format!("/admin/webhooks/{webhook_id}/deliveries"), Some(token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    deliveries.as_array().unwrap().clone()
+}
+
+#[sqlx::test(migrations = false)]
+async fn deliveries_are_signed_with_the_webhook_secret(pool: PgPool) {
+    let app = test_app_with_targets(pool.clone(), LOCAL).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let (url, receiver) = start_receiver(StatusCode::OK).await;
+    let webhook_id = queue_delivery(&app, &token, &url).await;
+
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+
+    let requests = receiver.requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 1);
+    let (headers, body) = &requests[0];
+    let header = |name: &str| headers.get(name).unwrap().to_str().unwrap().to_string();
+    let timestamp: i64 = header("x-yayayum-timestamp").parse().unwrap();
+    assert_eq!(header("x-yayayum-signature"), format!("sha256={}", sign(SECRET, timestamp, body)));
+    assert_eq!(header("x-yayayum-event"), "dish.created");
+    assert_eq!(serde_json::from_str::<Value>(body).unwrap()["kind"], "dish.created");
+
+    let deliveries = deliveries(&app, &token, webhook_id).await;
+    assert_eq!(deliveries[0]["status"], "Succeeded");
+    assert_eq!(deliveries[0]["last_status_code"], 200);
+}
+
+#[sqlx::test(migrations = false)]
+async fn failed_deliveries_back_off_then_give_up(pool: PgPool) {
+    let app = test_app_with_targets(pool.clone(), LOCAL).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let (url, receiver) = start_receiver(StatusCode::INTERNAL_SERVER_ERROR).await;
+    let webhook_id = queue_delivery(&app, &token, &url).await;
+
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+
+    let delivery = &deliveries(&app, &token, webhook_id).await[0];
+    assert_eq!(delivery["status"], "Pending");
+    assert_eq!(delivery["attempts"], 1);
+    assert_eq!(delivery["last_status_code"], 500);
+    let wait: i64 = sqlx::query_scalar("SELECT EXTRACT(EPOCH FROM next_attempt_at - NOW())::BIGINT FROM webhook_deliveries")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert!((25..=30).contains(&wait), "retried in {wait}s");
+
+    // Not due yet, so nothing is sent
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+    assert_eq!(receiver.requests.lock().unwrap().len(), 1);
+
+    sqlx::query("UPDATE webhook_deliveries SET attempts = $1, next_attempt_at = NOW()")
+        .bind(MAX_DELIVERY_ATTEMPTS - 1)
+        .execute(&pool)
+        .await
+        .unwrap();
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+
+    let delivery = &deliveries(&app, &token, webhook_id).await[0];
+    assert_eq!(delivery["status"], "Failed");
+    assert_eq!(delivery["attempts"], MAX_DELIVERY_ATTEMPTS);
+    assert_eq!(receiver.requests.lock().unwrap().len(), 2);
+}
+
+#[sqlx::test(migrations = false)]
+async fn deactivated_webhooks_hold_their_deliveries(pool: PgPool) {
+    let app = test_app_with_targets(pool.clone(), LOCAL).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+    let (url, receiver) = start_receiver(StatusCode::OK).await;
+    let webhook_id = queue_delivery(&app, &token, &url).await;
+    let webhook_uri = format!("/admin/webhooks/{webhook_id}");
+    let webhook = |active: bool| json!({ "url": url, "event_types": ["dish.created"], "active": active });
+
+    let (status, _) = send(&app, "PUT", &webhook_uri, Some(&token), Some(webhook(false))).await;
+    assert_eq!(status, StatusCode::OK);
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+    assert!(receiver.requests.lock().unwrap().is_empty());
+    let delivery = &deliveries(&app, &token, webhook_id).await[0];
+    assert_eq!(delivery["status"], "Pending");
+    assert_eq!(delivery["attempts"], 0);
+
+    send(&app, "PUT", &webhook_uri, Some(&token), Some(webhook(true))).await;
+    deliver_due(&pool, &LOCAL.client(), LOCAL).await.unwrap();
+    assert_eq!(receiver.requests.lock().unwrap().len(), 1);
+}
+
+#[sqlx::test(migrations = false)]
+async fn webhooks_cant_target_private_addresses(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+
+    for url in ["http://127.0.0.1:8000/hook", "http://localhost/hook", "http://169.254.169.254/latest", "http://[::1]/hook", "http://10.0.0.1/"] {
+        let (status, _) = send(&app, "POST", "/admin/webhooks", Some(&token), Some(json!({
+            "url": url,
+            "event_types": ["dish.created"],
+        })))
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{url}");
+    }
+}
+
+#[sqlx::test(migrations = false)]
+async fn the_secret_is_only_shown_on_create(pool: PgPool) {
+    let app = test_app_with_targets(pool, LOCAL).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+
+    let (status, created) = send(&app, "POST", "/admin/webhooks", Some(&token), Some(json!({
+        "url": "http://127.0.0.1:8000/hook",
+        "event_types": ["dish.created"],
+    })))
+    .await;
+    assert_eq!(status, StatusCode::CREATED);
+    assert!(created["secret"].is_string());
+
+    let (_, listed) = send(&app, "GET", "/admin/webhooks", Some(&token), None).await;
+    assert!(listed[0].get("secret").is_none());
+    let (_, fetched) = send(&app, "GET", &format!("/admin/webhooks/{}", created["id"]), Some(&token), None).await;
+    assert!(fetched.get("secret").is_none());
+}
@@ -0,0 +1,40 @@
+use axum::http::StatusCode;
+use serde_json::json;
+use sqlx::PgPool;
+use super::{create_workspace, send, test_app};
+
+#[sqlx::test(migrations = false)]
+async fn settings_left_out_are_kept(pool: PgPool) {
+    let app = test_app(pool).await;
+    let (_, token) = create_workspace(&app, "A", "alice").await;
+
+    let (status, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({
+        "chat_signing_secret": "slack-secret",
+        "digest_email": "team@example.com",
+        "reminder_time": "13:30:00",
+        "rating_grace_days": 5,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(workspace["chat_enabled"], true);
+    assert!(workspace.get("chat_signing_secret").is_none());
+
+    let (status, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({ "name": "Lunch club" }))).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(workspace["name"], "Lunch club");
+    assert_eq!(workspace["chat_enabled"], true);
+    assert_eq!(workspace["digest_email"], "team@example.com");
+    assert_eq!(workspace["reminder_time"], "13:30:00");
+    assert_eq!(workspace["rating_grace_days"], 5);
+    assert_eq!(workspace["rating_scale"], json!({ "max": 5, "half_steps": true }));
+
+    let (status, workspace) = send(&app, "PUT", "/workspaces/current", Some(&token), Some(json!({
+        "chat_signing_secret": null,
+        "reminder_time": null,
+    })))
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(workspace["chat_enabled"], false);
+    assert_eq!(workspace["reminder_time"], json!(null));
+    assert_eq!(workspace["digest_email"], "team@example.com");
+}
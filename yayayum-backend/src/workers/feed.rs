@@ -0,0 +1,55 @@
+use std::time::Duration;
+use sqlx::{postgres::PgListener, PgPool};
+use crate::models::EventKind;
+use crate::routes::feed::{event_from_row, EventNotification, FeedBroadcast, LiveEvent, EVENTS_CHANNEL, EVENT_SELECT};
+use crate::workers::WorkerError;
+
+/// How long to wait before listening again after losing the connection
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Listens for new events on one connection per instance and passes them on
+/// to every open feed stream, which pick out their own workspace's.
+pub async fn relay_events(pool: PgPool, feed: FeedBroadcast) {
+    loop {
+        if let Err(err) = listen(&pool, &feed).await {
+            tracing::warn!("Lost the feed listener: {err}");
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn listen(pool: &PgPool, feed: &FeedBroadcast) -> Result<(), WorkerError> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(EVENTS_CHANNEL).await?;
+
+    loop {
+        let notification = listener.recv().await?;
+        let Ok(notification) = serde_json::from_str::<EventNotification>(notification.payload()) else {
+            continue;
+        };
+        // Nobody is streaming, so there's no need to look the event up
+        if feed.0.receiver_count() == 0 {
+            continue;
+        }
+
+        let Some(row) = sqlx::query(&format!("{EVENT_SELECT} WHERE e.id = $1"))
+            .bind(notification.id)
+            .fetch_optional(pool)
+            .await? else {
+            continue;
+        };
+        let event = event_from_row(&row).map_err(|_| WorkerError(format!("Couldn't read event {}", notification.id)))?;
+        if EventKind::WEBHOOK_ONLY.contains(&event.kind) {
+            continue;
+        }
+        let kind = serde_json::to_value(event.kind)?;
+
+        // Sending only fails when every stream has closed in the meantime
+        let _ = feed.0.send(LiveEvent {
+            workspace_id: notification.workspace_id,
+            id: event.id,
+            kind: kind.as_str().unwrap_or_default().to_string(),
+            json: serde_json::to_string(&event)?,
+        });
+    }
+}
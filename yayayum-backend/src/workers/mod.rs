@@ -0,0 +1,28 @@
+pub mod feed;
+pub mod scheduler;
+pub mod webhooks;
+
+use std::fmt;
+
+/// Why a background worker couldn't finish a pass. Workers log it and try
+/// again on their next tick.
+#[derive(Debug)]
+pub struct WorkerError(pub String);
+
+impl fmt::Display for WorkerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl From<sqlx::Error> for WorkerError {
+    fn from(err: sqlx::Error) -> Self {
+        WorkerError(err.to_string())
+    }
+}
+
+impl From<serde_json::Error> for WorkerError {
+    fn from(err: serde_json::Error) -> Self {
+        WorkerError(err.to_string())
+    }
+}
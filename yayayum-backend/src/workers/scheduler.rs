@@ -0,0 +1,211 @@
+use std::convert::Infallible;
+use std::time::Duration;
+use sqlx::{PgPool, Row};
+use crate::mailer::Mailer;
+use crate::models::{DigestRun, JobKind, JobRunStatus};
+use crate::models::job::{job_lease, job_retry_delay, next_run, MAX_JOB_ATTEMPTS};
+use crate::routes::digests::send_digests;
+use crate::routes::reminders::send_due_reminders;
+use crate::workers::WorkerError;
+
+/// How often the scheduler looks for due jobs
+const SCHEDULER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often a running job's lock is renewed, well within `job_lease`
+const LEASE_RENEWAL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Runs due jobs until the server stops. Every instance runs a scheduler, and
+/// a job is locked while it runs so only one of them picks it up.
+pub async fn run_scheduler(pool: PgPool, mailer: Mailer) {
+    let mut interval = tokio::time::interval(SCHEDULER_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        // One job at a time until nothing is due. A run that couldn't be
+        // recorded stays locked and is retried once its lease runs out.
+        loop {
+            match claim_due_job(&pool).await {
+                Ok(Some(claimed)) => {
+                    if let Err(err) = run_claimed(&pool, &mailer, claimed).await {
+                        tracing::warn!("Couldn't record a job run: {err}");
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    tracing::warn!("Couldn't claim a job: {err}");
+                    break;
+                }
+            }
+        }
+    }
+}
+
+struct ClaimedJob {
+    kind: JobKind,
+    name: String,
+    schedule: String,
+    attempt: i32,
+    run_id: i32,
+    started_at: chrono::NaiveDateTime,
+}
+
+/// Locks the job that has been due the longest and records the start of a run.
+async fn claim_due_job(pool: &PgPool) -> Result<Option<ClaimedJob>, WorkerError> {
+    let started_at = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    let Some(row) = sqlx::query(
+        "UPDATE jobs SET attempts = attempts + 1, locked_until = $2
+         WHERE name = (
+             SELECT name FROM jobs
+             WHERE enabled AND next_run_at <= $1 AND (locked_until IS NULL OR locked_until <= $1)
+             ORDER BY next_run_at
+             LIMIT 1
+             FOR UPDATE SKIP LOCKED
+         )
+         RETURNING name, schedule, attempts"
+    )
+    .bind(started_at)
+    .bind(started_at + job_lease())
+    .fetch_optional(&mut *tx)
+    .await? else {
+        return Ok(None);
+    };
+    let name: String = row.get("name");
+
+    // Nothing else holds the lock, so a run still marked as running was cut short
+    sqlx::query("UPDATE job_runs SET status = $1, output = 'Stopped before finishing', finished_at = $2 WHERE job_name = $3 AND status = $4")
+        .bind(serde_json::to_string(&JobRunStatus::Failed)?)
+        .bind(started_at)
+        .bind(&name)
+        .bind(serde_json::to_string(&JobRunStatus::Running)?)
+        .execute(&mut *tx)
+        .await?;
+
+    let run_id: i32 = sqlx::query_scalar(
+        "INSERT INTO job_runs (job_name, attempt, status, started_at) VALUES ($1, $2, $3, $4) RETURNING id"
+    )
+    .bind(&name)
+    .bind(row.get::<i32, _>("attempts"))
+    .bind(serde_json::to_string(&JobRunStatus::Running)?)
+    .bind(started_at)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(Some(ClaimedJob {
+        kind: serde_json::from_str(&name)?,
+        schedule: row.get("schedule"),
+        attempt: row.get("attempts"),
+        name,
+        run_id,
+        started_at,
+    }))
+}
+
+/// Runs a claimed job, records the outcome and unlocks it. Failures are
+/// retried a couple of times before waiting for the next scheduled run.
+async fn run_claimed(pool: &PgPool, mailer: &Mailer, job: ClaimedJob) -> Result<(), WorkerError> {
+    let result = tokio::select! {
+        result = run_job(job.kind, pool, mailer) => result,
+        never = keep_locked(pool, &job.name) => match never {},
+    };
+    let finished_at = chrono::Utc::now().naive_utc();
+
+    let (status, output, next_run_at, attempts) = match result {
+        Ok(output) => (JobRunStatus::Succeeded, output, next_run(&job.schedule, finished_at), 0),
+        Err(error) if job.attempt < MAX_JOB_ATTEMPTS => {
+            (JobRunStatus::Failed, error, Some(finished_at + job_retry_delay(job.attempt)), job.attempt)
+        }
+        Err(error) => (JobRunStatus::Failed, error, next_run(&job.schedule, finished_at), 0),
+    };
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query("UPDATE job_runs SET status = $1, output = $2, finished_at = $3 WHERE id = $4")
+        .bind(serde_json::to_string(&status)?)
+        .bind(&output)
+        .bind(finished_at)
+        .bind(job.run_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query(
+        "UPDATE jobs SET last_run_at = $1, next_run_at = $2, attempts = $3, locked_until = NULL WHERE name = $4"
+    )
+    .bind(job.started_at)
+    .bind(next_run_at.ok_or_else(|| WorkerError(format!("{} has no next run", job.schedule)))?)
+    .bind(attempts)
+    .bind(&job.name)
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    Ok(())
+}
+
+/// Renews the job's lock for as long as it runs. A job that takes longer
+/// than `job_lease` would otherwise be picked up by another instance halfway.
+async fn keep_locked(pool: &PgPool, name: &str) -> Infallible {
+    loop {
+        tokio::time::sleep(LEASE_RENEWAL_INTERVAL).await;
+        let renewed = sqlx::query("UPDATE jobs SET locked_until = $1 WHERE name = $2")
+            .bind(chrono::Utc::now().naive_utc() + job_lease())
+            .bind(name)
+            .execute(pool)
+            .await;
+        // Tried again next time, the lease has some slack
+        if let Err(err) = renewed {
+            tracing::warn!("Couldn't renew the lock on {name}: {err}");
+        }
+    }
+}
+
+/// What a job did, or why it failed, for the run history.
+async fn run_job(kind: JobKind, pool: &PgPool, mailer: &Mailer) -> Result<String, String> {
+    match kind {
+        JobKind::WeeklyDigests => send_all_digests(pool, mailer).await,
+        JobKind::RatingReminders => send_due_reminders(pool, mailer).await,
+    }
+}
+
+/// Sends the digests for the week ending yesterday in every workspace. Digests
+/// already sent are skipped, so a retry only sends the ones that failed.
+async fn send_all_digests(pool: &PgPool, mailer: &Mailer) -> Result<String, String> {
+    if !mailer.is_enabled() {
+        return Ok("Email isn't set up, no digests sent".to_string());
+    }
+
+    let ending: chrono::NaiveDate = sqlx::query_scalar("SELECT CURRENT_DATE - 1")
+        .fetch_one(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+    let workspace_ids: Vec<i32> = sqlx::query_scalar("SELECT id FROM workspaces ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    let mut total = DigestRun::default();
+    let mut errors = Vec::new();
+    for workspace_id in workspace_ids {
+        match send_digests(pool, mailer, workspace_id, ending).await {
+            Ok(run) => {
+                total.sent += run.sent;
+                total.skipped += run.skipped;
+                total.failed += run.failed;
+            }
+            Err(status) => errors.push(format!("workspace {workspace_id}: {status}")),
+        }
+    }
+
+    let mut summary = format!(
+        "Week ending {ending}: {} sent, {} already sent, {} failed",
+        total.sent, total.skipped, total.failed,
+    );
+    if !errors.is_empty() {
+        summary.push_str(&format!(", errors in {}", errors.join(", ")));
+    }
+
+    if total.failed > 0 || !errors.is_empty() { Err(summary) } else { Ok(summary) }
+}
@@ -0,0 +1,194 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::future::join_all;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use shuttle_runtime::SecretStore;
+use sqlx::{postgres::PgRow, PgPool, Row};
+use crate::models::DeliveryStatus;
+use crate::models::webhook::{is_public_address, retry_delay, sign, MAX_DELIVERY_ATTEMPTS};
+use crate::routes::feed::event_from_row;
+use crate::workers::WorkerError;
+
+/// How often the delivery worker looks for due deliveries
+const DELIVERY_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Deliveries sent per poll
+const DELIVERY_BATCH_SIZE: i64 = 20;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where webhooks may send events. Addresses on the server itself or its
+/// network (loopback, private, link-local) are refused so a webhook can't be
+/// used to reach internal services, unless the `WEBHOOK_ALLOW_PRIVATE_TARGETS`
+/// secret is `true`, e.g. for a receiver running next to a dev server.
+#[derive(Clone, Copy)]
+pub struct WebhookTargets {
+    pub allow_private: bool,
+}
+
+impl WebhookTargets {
+    pub fn from_secrets(secrets: &SecretStore) -> WebhookTargets {
+        WebhookTargets {
+            allow_private: secrets.get("WEBHOOK_ALLOW_PRIVATE_TARGETS").as_deref() == Some("true"),
+        }
+    }
+
+    /// Checks that every address the URL's host resolves to may be sent to.
+    pub async fn check(&self, url: &str) -> Result<(), String> {
+        let url = reqwest::Url::parse(url).map_err(|err| err.to_string())?;
+        if self.allow_private {
+            return Ok(());
+        }
+        // IPv6 literals come bracketed, which the resolver doesn't take
+        let host = url.host_str().ok_or("The URL has no host")?.trim_start_matches('[').trim_end_matches(']');
+        let port = url.port_or_known_default().unwrap_or(80);
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await
+            .map_err(|err| format!("Couldn't resolve {host}: {err}"))?
+            .collect();
+        if addrs.is_empty() || !addrs.iter().all(|addr| is_public_address(addr.ip())) {
+            return Err(format!("{host} isn't a public address"));
+        }
+        Ok(())
+    }
+
+    /// An HTTP client that doesn't follow redirects and, unless private
+    /// targets are allowed, only connects to public addresses. The checks
+    /// happen again when connecting, so a host can't pass `check` and then
+    /// resolve somewhere else.
+    pub(crate) fn client(&self) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder()
+            .timeout(DELIVERY_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none());
+        if !self.allow_private {
+            builder = builder.dns_resolver(Arc::new(PublicResolver));
+        }
+        builder.build().expect("Failed to create webhook client")
+    }
+}
+
+/// Resolves hosts like the system does, but leaves out non-public addresses.
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((name.as_str(), 0)).await?
+                .filter(|addr| is_public_address(addr.ip()))
+                .collect();
+            if addrs.is_empty() {
+                return Err(format!("{} isn't a public address", name.as_str()).into());
+            }
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Sends queued deliveries until the server stops. Runs in the background
+/// next to the API, so several instances can share the queue.
+pub async fn deliver_webhooks(pool: PgPool, targets: WebhookTargets) {
+    let client = targets.client();
+
+    let mut interval = tokio::time::interval(DELIVERY_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        // Failed attempts are recorded on the delivery, anything else is retried next poll
+        if let Err(err) = deliver_due(&pool, &client, targets).await {
+            tracing::warn!("Webhook deliveries failed: {err}");
+        }
+    }
+}
+
+/// Claims a batch of due deliveries and sends them all at once.
+pub(crate) async fn deliver_due(pool: &PgPool, client: &reqwest::Client, targets: WebhookTargets) -> Result<(), WorkerError> {
+    let pending = serde_json::to_string(&DeliveryStatus::Pending)?;
+
+    // Pushing next_attempt_at ahead leases the deliveries, so no other worker
+    // picks them up while they're being sent. Deliveries for a deactivated
+    // webhook wait until it's turned back on.
+    let rows = sqlx::query(
+        "WITH claimed AS (
+            UPDATE webhook_deliveries SET attempts = attempts + 1, next_attempt_at = NOW() + INTERVAL '5 minutes'
+            WHERE id IN (
+                SELECT d.id FROM webhook_deliveries d JOIN webhooks w ON w.id = d.webhook_id
+                WHERE d.status = $1 AND d.next_attempt_at <= NOW() AND w.active
+                ORDER BY d.next_attempt_at
+                LIMIT $2
+                FOR UPDATE OF d SKIP LOCKED
+            )
+            RETURNING id, webhook_id, event_id, attempts
+         )
+         SELECT c.id AS delivery_id, c.attempts, w.url, w.secret,
+             e.id, e.kind, e.user_id, u.username, e.subject_id, e.data, e.created_at
+         FROM claimed c
+         JOIN webhooks w ON w.id = c.webhook_id
+         JOIN events e ON e.id = c.event_id
+         LEFT JOIN users u ON u.id = e.user_id"
+    )
+    .bind(&pending)
+    .bind(DELIVERY_BATCH_SIZE)
+    .fetch_all(pool)
+    .await?;
+
+    join_all(rows.iter().map(|row| deliver(pool, client, targets, row))).await;
+
+    Ok(())
+}
+
+async fn deliver(pool: &PgPool, client: &reqwest::Client, targets: WebhookTargets, row: &PgRow) -> Result<(), WorkerError> {
+    let delivery_id: i32 = row.get("delivery_id");
+    let attempts: i32 = row.get("attempts");
+    let url: String = row.get("url");
+    let secret: String = row.get("secret");
+    let event = event_from_row(row).map_err(|_| WorkerError(format!("Couldn't read the event for delivery {delivery_id}")))?;
+
+    let body = serde_json::to_string(&event)?;
+    let kind = serde_json::to_value(event.kind)?;
+    let timestamp = chrono::Utc::now().timestamp();
+
+    // The host may resolve somewhere else than when the webhook was saved
+    let response = match targets.check(&url).await {
+        Ok(()) => client.post(&url)
+            .header("Content-Type", "application/json")
+            .header("X-Yayayum-Event", kind.as_str().unwrap_or_default())
+            .header("X-Yayayum-Delivery", delivery_id.to_string())
+            .header("X-Yayayum-Timestamp", timestamp.to_string())
+            .header("X-Yayayum-Signature", format!("sha256={}", sign(&secret, timestamp, &body)))
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| err.to_string()),
+        Err(err) => Err(err),
+    };
+
+    // Redirects aren't followed, so they count as failures too
+    let (status_code, error) = match response {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i32), None),
+        Ok(response) => (Some(response.status().as_u16() as i32), Some(format!("Receiver responded with {}", response.status()))),
+        Err(err) => (None, Some(err)),
+    };
+
+    let (status, delivered, retry_in) = match error {
+        None => (DeliveryStatus::Succeeded, true, chrono::Duration::zero()),
+        Some(_) if attempts >= MAX_DELIVERY_ATTEMPTS => (DeliveryStatus::Failed, false, chrono::Duration::zero()),
+        Some(_) => (DeliveryStatus::Pending, false, retry_delay(attempts)),
+    };
+
+    sqlx::query(
+        "UPDATE webhook_deliveries SET status = $1, last_status_code = $2, last_error = $3,
+             delivered_at = CASE WHEN $4 THEN NOW() END,
+             next_attempt_at = NOW() + make_interval(secs => $5)
+         WHERE id = $6"
+    )
+    .bind(serde_json::to_string(&status)?)
+    .bind(status_code)
+    .bind(&error)
+    .bind(delivered)
+    .bind(retry_in.num_seconds() as f64)
+    .bind(delivery_id)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
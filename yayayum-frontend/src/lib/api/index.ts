@@ -7,13 +7,19 @@ export { CancelablePromise, CancelError } from './core/CancelablePromise';
 export { OpenAPI } from './core/OpenAPI';
 export type { OpenAPIConfig } from './core/OpenAPI';
 
+export { Allergen } from './models/Allergen';
 export type { CreateDish } from './models/CreateDish';
 export type { CreateRating } from './models/CreateRating';
 export type { CreateUser } from './models/CreateUser';
 export { DietaryRestriction } from './models/DietaryRestriction';
 export type { Dish } from './models/Dish';
 export { DishCategory } from './models/DishCategory';
+export type { DishSuitability } from './models/DishSuitability';
+export type { Ingredient } from './models/Ingredient';
+export { IngredientSource } from './models/IngredientSource';
 export type { Rating } from './models/Rating';
+export { ReminderChannel } from './models/ReminderChannel';
+export type { UpdateUser } from './models/UpdateUser';
 export type { User } from './models/User';
 
 export { DishesService } from './services/DishesService';
@@ -0,0 +1,23 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * The 14 allergens that must be declared under EU regulation 1169/2011.
+ */
+export enum Allergen {
+    GLUTEN = 'Gluten',
+    CRUSTACEANS = 'Crustaceans',
+    EGGS = 'Eggs',
+    FISH = 'Fish',
+    PEANUTS = 'Peanuts',
+    SOYBEANS = 'Soybeans',
+    MILK = 'Milk',
+    TREE_NUTS = 'TreeNuts',
+    CELERY = 'Celery',
+    MUSTARD = 'Mustard',
+    SESAME = 'Sesame',
+    SULPHITES = 'Sulphites',
+    LUPIN = 'Lupin',
+    MOLLUSCS = 'Molluscs',
+}
@@ -2,14 +2,18 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { Allergen } from './Allergen';
 import type { DietaryRestriction } from './DietaryRestriction';
-import type { DishCategory } from './DishCategory';
 export type CreateDish = {
-    category: DishCategory;
+    allergens?: Array<Allergen>;
+    category: string;
     description: string;
     dietary_restrictions: Array<DietaryRestriction>;
+    ingredient_ids?: Array<number>;
     name: string;
     nr: number;
     price_kr: number;
+    restaurant_id?: number | null;
+    spiciness?: number;
 };
 
@@ -7,6 +7,5 @@ export type CreateRating = {
     dish_id: number;
     photo?: string | null;
     rating: number;
-    user_id: number;
 };
 
@@ -12,5 +12,4 @@ export enum DietaryRestriction {
     KOSHER = 'Kosher',
     LOW_CARB = 'LowCarb',
     KETO = 'Keto',
-    NONE = 'None',
 }
@@ -2,15 +2,23 @@
 /* istanbul ignore file */
 /* tslint:disable */
 /* eslint-disable */
+import type { Allergen } from './Allergen';
 import type { DietaryRestriction } from './DietaryRestriction';
-import type { DishCategory } from './DishCategory';
+import type { DishSuitability } from './DishSuitability';
+import type { Ingredient } from './Ingredient';
 export type Dish = {
-    category: DishCategory;
+    allergens: Array<Allergen>;
+    category: string;
     description: string;
+    diet_compatibility: Array<DietaryRestriction>;
     dietary_restrictions: Array<DietaryRestriction>;
     id: number;
+    ingredients: Array<Ingredient>;
     name: string;
     nr: number;
     price_kr: number;
+    restaurant_id: number;
+    spiciness: number;
+    suitability?: (null | DishSuitability);
 };
 
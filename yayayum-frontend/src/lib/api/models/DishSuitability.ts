@@ -0,0 +1,14 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { DietaryRestriction } from './DietaryRestriction';
+/**
+ * How well a dish matches a user's dietary preferences.
+ */
+export type DishSuitability = {
+    disliked_ingredients: Array<string>;
+    missing_restrictions: Array<DietaryRestriction>;
+    suitable: boolean;
+};
+
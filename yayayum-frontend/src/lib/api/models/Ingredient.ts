@@ -0,0 +1,13 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { Allergen } from './Allergen';
+import type { IngredientSource } from './IngredientSource';
+export type Ingredient = {
+    allergens: Array<Allergen>;
+    id: number;
+    name: string;
+    source: IngredientSource;
+};
+
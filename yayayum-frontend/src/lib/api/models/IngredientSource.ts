@@ -0,0 +1,14 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+export enum IngredientSource {
+    PLANT = 'Plant',
+    DAIRY = 'Dairy',
+    EGG = 'Egg',
+    HONEY = 'Honey',
+    MEAT = 'Meat',
+    POULTRY = 'Poultry',
+    FISH = 'Fish',
+    SHELLFISH = 'Shellfish',
+}
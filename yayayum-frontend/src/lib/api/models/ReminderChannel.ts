@@ -0,0 +1,12 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * How a user gets reminded to rate their lunch.
+ */
+export enum ReminderChannel {
+    IN_APP = 'InApp',
+    EMAIL = 'Email',
+    WEBHOOK = 'Webhook',
+}
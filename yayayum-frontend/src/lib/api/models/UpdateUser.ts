@@ -0,0 +1,24 @@
+/* generated using openapi-typescript-codegen -- do not edit */
+/* istanbul ignore file */
+/* tslint:disable */
+/* eslint-disable */
+import type { DietaryRestriction } from './DietaryRestriction';
+import type { ReminderChannel } from './ReminderChannel';
+/**
+ * Changes to a user. Fields that are left out keep their current value, and
+ * the chat user or email can be removed with null.
+ */
+export type UpdateUser = {
+    username?: string | null;
+    dietary_restrictions?: Array<DietaryRestriction> | null;
+    disliked_ingredients?: Array<string> | null;
+    /**
+     * Only admins can change it, everyone else links their account from the chat
+     */
+    chat_user_id?: string | null;
+    email?: string | null;
+    weekly_digest?: boolean | null;
+    rating_reminders?: boolean | null;
+    reminder_channel?: ReminderChannel | null;
+};
+
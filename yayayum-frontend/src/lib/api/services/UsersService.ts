@@ -3,6 +3,7 @@
 /* tslint:disable */
 /* eslint-disable */
 import type { CreateUser } from '../models/CreateUser';
+import type { UpdateUser } from '../models/UpdateUser';
 import type { User } from '../models/User';
 import type { CancelablePromise } from '../core/CancelablePromise';
 import { OpenAPI } from '../core/OpenAPI';
@@ -36,12 +37,12 @@ export class UsersService {
     /**
      * @param id User ID to modify
      * @param requestBody
-     * @returns User User updated successfully
+     * @returns User User updated, keeping anything left out
      * @throws ApiError
      */
     public static modifyUser(
         id: number,
-        requestBody: CreateUser,
+        requestBody: UpdateUser,
     ): CancelablePromise<User> {
         return __request(OpenAPI, {
             method: 'PUT',
@@ -52,7 +53,10 @@ export class UsersService {
             body: requestBody,
             mediaType: 'application/json',
             errors: {
+                400: `Bad request - invalid email, or a weekly digest or email reminders without an email`,
+                403: `Forbidden - only admins can change someone else or the chat user, others link it from the chat`,
                 404: `User not found`,
+                409: `Conflict - the chat user is already linked to someone else`,
             },
         });
     }
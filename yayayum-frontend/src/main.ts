@@ -7,6 +7,18 @@ import { OpenAPI } from './lib/api'
 export const API_URL = import.meta.env.VITE_API_URL;
 OpenAPI.BASE = API_URL;
 
+// Session token from joining a workspace, handed over once as ?token=... and kept after that
+const TOKEN_KEY = 'yayayum-token';
+const params = new URLSearchParams(window.location.search);
+const linkedToken = params.get('token');
+if (linkedToken) {
+  localStorage.setItem(TOKEN_KEY, linkedToken);
+  params.delete('token');
+  const query = params.toString();
+  window.history.replaceState(null, '', window.location.pathname + (query ? `?${query}` : ''));
+}
+OpenAPI.TOKEN = async () => localStorage.getItem(TOKEN_KEY) ?? '';
+
 const app = mount(App, {
   target: document.getElementById('app')!,
 })